@@ -0,0 +1,78 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+#[macro_use]
+extern crate wayland_client;
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+
+use wayland_client::protocol::{wl_data_device, wl_data_device_manager, wl_output, wl_seat};
+use wayland_client::{Display, GlobalManager, Main};
+
+fuzz_target!(|data: &[u8]| {
+    let (mut stream, client_end) = UnixStream::pair().unwrap();
+    let display = unsafe { Display::from_fd(client_end.into_raw_fd()) }.unwrap();
+    let mut event_queue = display.create_event_queue();
+    let attached_display = (*display).clone().attach(event_queue.token());
+
+    // Bind the globals the server advertises, creating data devices to have objects
+    // created by the server.
+    let data_device_manager = Rc::new(RefCell::new(None));
+    let data_device_manager2 = data_device_manager.clone();
+    let _globals = GlobalManager::new_with_cb(
+        &attached_display,
+        global_filter!(
+            [
+                wl_data_device_manager::WlDataDeviceManager,
+                3,
+                move |manager: Main<wl_data_device_manager::WlDataDeviceManager>,
+                      _: DispatchData| {
+                    *data_device_manager2.borrow_mut() = Some(manager);
+                }
+            ],
+            [wl_seat::WlSeat, 5, move |seat: Main<wl_seat::WlSeat>, _: DispatchData| {
+                seat.quick_assign(|_, _, _| {});
+                let manager = data_device_manager.borrow();
+                if let Some(ref manager) = *manager {
+                    manager.get_data_device(&seat).quick_assign(|_, event, _| {
+                        if let wl_data_device::Event::DataOffer { id } = event {
+                            id.quick_assign(|_, _, _| {});
+                        }
+                    });
+                }
+            }],
+            [wl_output::WlOutput, 3, |output: Main<wl_output::WlOutput>, _: DispatchData| {
+                output.quick_assign(|_, _, _| {});
+            }]
+        ),
+    );
+    if display.flush().is_err() {
+        return;
+    }
+
+    // The input is a sequence of chunks, each prefixed by its length, that are
+    // sent by the server one at a time.
+    let mut data = data;
+    while let Some((&len, rest)) = data.split_first() {
+        let (chunk, rest) = rest.split_at(usize::min(len as usize, rest.len()));
+        data = rest;
+        if stream.write_all(chunk).is_err() {
+            return;
+        }
+        if let Some(guard) = event_queue.prepare_read() {
+            if let Err(e) = guard.read_events() {
+                if e.kind() != std::io::ErrorKind::WouldBlock {
+                    return;
+                }
+            }
+        }
+        if event_queue.dispatch_pending(&mut (), |_, _, _| {}).is_err() {
+            return;
+        }
+        let _ = display.flush();
+    }
+});
@@ -3,9 +3,9 @@
 extern crate libfuzzer_sys;
 extern crate wayland_commons;
 
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{IntoRawFd, RawFd};
 use std::{mem, slice};
-use wayland_commons::wire::{ArgumentType, Message};
+use wayland_commons::wire::{ArgumentType, ArgumentView, MessageView};
 
 unsafe fn convert_slice<T: Sized>(data: &[u8]) -> &[T] {
     let n = mem::size_of::<T>();
@@ -42,5 +42,12 @@ fuzz_target!(|data: &[u8]| {
     // 16 `ArgumentType`s
     let args = get_arg_types(&data[16..32]);
     let data: &[u32] = unsafe { convert_slice(&data[32..]) };
-    let _res = Message::from_raw(data, &args, fds);
+    if let Ok((msg, _, _)) = MessageView::from_raw(data, &args, fds) {
+        // the fds are arbitrary numbers, give them back instead of closing them
+        for arg in msg.args {
+            if let ArgumentView::Fd(fd) = arg {
+                let _ = fd.into_raw_fd();
+            }
+        }
+    }
 });
@@ -0,0 +1,10 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wayland_scanner;
+
+// `interfaces_from_xml()` only parses the protocol file, which must either succeed
+// or return an error on any input, but never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = wayland_scanner::interfaces_from_xml(data, &[]);
+});
@@ -0,0 +1,63 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wayland_server;
+
+use std::io::Write;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use wayland_server::protocol::{wl_compositor, wl_output, wl_seat};
+use wayland_server::{Display, Filter, Main};
+
+fuzz_target!(|data: &[u8]| {
+    let mut display = Display::new();
+    display.create_global::<wl_compositor::WlCompositor, _>(
+        4,
+        Filter::new(|(compositor, _): (Main<wl_compositor::WlCompositor>, u32), _, _| {
+            compositor.quick_assign(|_, request, _| match request {
+                wl_compositor::Request::CreateSurface { id } => id.quick_assign(|_, _, _| {}),
+                wl_compositor::Request::CreateRegion { id } => id.quick_assign(|_, _, _| {}),
+                _ => {}
+            });
+        }),
+    );
+    display.create_global::<wl_seat::WlSeat, _>(
+        5,
+        Filter::new(|(seat, _): (Main<wl_seat::WlSeat>, u32), _, _| {
+            seat.quick_assign(|_, request, _| match request {
+                wl_seat::Request::GetPointer { id } => id.quick_assign(|_, _, _| {}),
+                wl_seat::Request::GetKeyboard { id } => id.quick_assign(|_, _, _| {}),
+                wl_seat::Request::GetTouch { id } => id.quick_assign(|_, _, _| {}),
+                _ => {}
+            });
+        }),
+    );
+    display.create_global::<wl_output::WlOutput, _>(
+        3,
+        Filter::new(|(output, _): (Main<wl_output::WlOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+        }),
+    );
+
+    let (mut stream, server_end) = UnixStream::pair().unwrap();
+    let client = unsafe { display.create_client(server_end.into_raw_fd(), &mut ()) };
+
+    // The input is a sequence of chunks, each prefixed by its length, that are
+    // sent by the client one at a time.
+    let mut data = data;
+    while let Some((&len, rest)) = data.split_first() {
+        // stop once the server has killed the client
+        if !client.alive() {
+            return;
+        }
+        let (chunk, rest) = rest.split_at(usize::min(len as usize, rest.len()));
+        data = rest;
+        if stream.write_all(chunk).is_err() {
+            return;
+        }
+        display.dispatch(Duration::from_millis(0), &mut ()).unwrap();
+        display.flush_clients(&mut ());
+    }
+});
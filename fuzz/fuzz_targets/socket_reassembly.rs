@@ -0,0 +1,44 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wayland_commons;
+
+use std::io::Write;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::net::UnixStream;
+
+use wayland_commons::socket::{BufferedSocket, Socket};
+use wayland_commons::wire::ArgumentType;
+
+// The signature of a message is chosen from its opcode
+const SIGNATURES: &[&[ArgumentType]] = &[
+    &[],
+    &[ArgumentType::Uint, ArgumentType::Int, ArgumentType::Fixed],
+    &[ArgumentType::Str, ArgumentType::Object],
+    &[ArgumentType::Array, ArgumentType::NewId],
+    &[ArgumentType::Str, ArgumentType::Uint, ArgumentType::NewId],
+];
+
+fuzz_target!(|data: &[u8]| {
+    let (mut client, server) = UnixStream::pair().unwrap();
+    server.set_nonblocking(true).unwrap();
+    let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+    // The input is a sequence of chunks, each prefixed by its length, that are
+    // written to the socket one at a time, so that messages are split at arbitrary
+    // places between the reads.
+    let mut data = data;
+    while let Some((&len, rest)) = data.split_first() {
+        let (chunk, rest) = rest.split_at(usize::min(len as usize, rest.len()));
+        data = rest;
+        client.write_all(chunk).unwrap();
+        let ret = socket.read_messages(
+            |_, opcode| Some(SIGNATURES[opcode as usize % SIGNATURES.len()]),
+            |_| true,
+        );
+        // A malformed message is fatal to the connection
+        if let Ok(Err(_)) = ret {
+            return;
+        }
+    }
+});
@@ -2,7 +2,6 @@ extern crate tempfile;
 
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::sync::{Arc, Mutex};
 
 mod helpers;
@@ -53,7 +52,7 @@ fn insert_compositor(server: &mut TestServer) -> Arc<Mutex<Option<Option<ServerB
 
 fn insert_shm(
     server: &mut TestServer,
-) -> Arc<Mutex<Option<(RawFd, Option<ways::Main<ServerBuffer>>)>>> {
+) -> Arc<Mutex<Option<(ways::OwnedFd, Option<ways::Main<ServerBuffer>>)>>> {
     use ways::protocol::{wl_shm, wl_shm_pool};
 
     let buffer = Arc::new(Mutex::new(None));
@@ -141,7 +140,7 @@ fn attach_buffer() {
     let mut file = tempfile::tempfile().unwrap();
     write!(file, "I like trains!").unwrap();
     file.flush().unwrap();
-    let pool = shm.create_pool(file.as_raw_fd(), 42);
+    let pool = shm.create_pool(wayc::BorrowedFd::new(&file), 42).unwrap();
     let buffer = pool.create_buffer(0, 0, 0, 0, Format::Argb8888);
 
     let compositor =
@@ -156,7 +155,7 @@ fn attach_buffer() {
     let shm_buffer = shm_buf.unwrap();
     assert!(&surface_buffer == &*shm_buffer);
 
-    let mut client_file = unsafe { File::from_raw_fd(shm_fd) };
+    let mut client_file = File::from(shm_fd);
     let mut contents = String::new();
     client_file.seek(SeekFrom::Start(0)).unwrap();
     client_file.read_to_string(&mut contents).unwrap();
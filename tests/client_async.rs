@@ -0,0 +1,65 @@
+mod helpers;
+
+use helpers::{wayc, ways, TestClient, TestServer};
+
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+// run the client future in a tokio runtime, while the server answers its requests
+fn block_on<F: Future>(mut server: TestServer, future: F) -> F::Output {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&runtime, async move {
+        tokio::task::spawn_local(async move {
+            loop {
+                server.answer();
+                tokio::task::yield_now().await;
+            }
+        });
+        future.await
+    })
+}
+
+#[test]
+fn client_async_roundtrip() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(2, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let mut client = TestClient::new(&server.socket_name);
+
+    let globals = Rc::new(Cell::new(0));
+    let globals2 = globals.clone();
+    client.display_proxy.get_registry().quick_assign(move |_, event, _| {
+        if let wayc::protocol::wl_registry::Event::Global { .. } = event {
+            globals2.set(globals2.get() + 1);
+        }
+    });
+
+    block_on(server, client.event_queue.sync_roundtrip_async(&mut (), |_, _, _| unreachable!()))
+        .unwrap();
+
+    assert_eq!(globals.get(), 1);
+}
+
+#[test]
+fn client_async_dispatch() {
+    let server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    let done = Rc::new(Cell::new(0));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(done2.get() + 1));
+    let done3 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done3.set(done3.get() + 1));
+
+    // the socket registered by the first dispatch is reused by the next ones
+    let event_queue = &mut client.event_queue;
+    block_on(server, async move {
+        while done.get() < 2 {
+            event_queue.dispatch_async(&mut (), |_, _, _| unreachable!()).await.unwrap();
+        }
+    });
+}
@@ -0,0 +1,86 @@
+mod helpers;
+
+use helpers::{TestClient, TestServer};
+
+use std::cell::Cell;
+use std::io::ErrorKind;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn dispatch_timeout() {
+    let mut server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(true));
+
+    // the server does not answer
+    let start = Instant::now();
+    let ret = client.event_queue.dispatch_timeout(
+        Duration::from_millis(100),
+        &mut (),
+        |_, _, _| unreachable!(),
+    );
+    assert_eq!(ret.unwrap_err().kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(!done.get());
+
+    server.answer();
+    client
+        .event_queue
+        .dispatch_timeout(Duration::from_secs(1), &mut (), |_, _, _| unreachable!())
+        .unwrap();
+    assert!(done.get());
+}
+
+#[test]
+fn sync_roundtrip_timeout() {
+    let mut server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    // the server does not answer
+    let start = Instant::now();
+    let ret = client.event_queue.sync_roundtrip_timeout(
+        Duration::from_millis(100),
+        &mut (),
+        |_, _, _| unreachable!(),
+    );
+    assert_eq!(ret.unwrap_err().kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // the answer to the first roundtrip is dispatched by the next one
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(true));
+    client.display.flush().unwrap();
+    server.answer();
+    client
+        .event_queue
+        .dispatch_timeout(Duration::from_secs(1), &mut (), |_, _, _| unreachable!())
+        .unwrap();
+    assert!(done.get());
+}
+
+#[test]
+fn read_events_timeout() {
+    let mut server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(true));
+    client.display.flush().unwrap();
+
+    // the server does not answer
+    let guard = client.event_queue.prepare_read().unwrap();
+    let ret = guard.read_events_timeout(Duration::from_millis(100));
+    assert_eq!(ret.unwrap_err().kind(), ErrorKind::TimedOut);
+
+    server.answer();
+    let guard = client.event_queue.prepare_read().unwrap();
+    guard.read_events_timeout(Duration::from_secs(1)).unwrap();
+    client.event_queue.dispatch_pending(&mut (), |_, _, _| unreachable!()).unwrap();
+    assert!(done.get());
+}
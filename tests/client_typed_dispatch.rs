@@ -0,0 +1,149 @@
+mod helpers;
+
+use helpers::{wayc, ways, TestClient, TestServer};
+
+use wayc::protocol::{wl_callback, wl_output, wl_registry};
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+struct State {
+    globals: Vec<String>,
+    scales: Vec<i32>,
+    done: bool,
+}
+
+impl wayc::Dispatch<wl_registry::WlRegistry> for State {
+    fn event(
+        &mut self,
+        registry: wayc::Main<wl_registry::WlRegistry>,
+        event: wl_registry::Event,
+        qhandle: &wayc::QueueHandle<State>,
+    ) {
+        if let wl_registry::Event::Global { name, interface, .. } = event {
+            if interface == "wl_output" {
+                registry.bind::<wl_output::WlOutput>(2, name).assign_dispatch(qhandle);
+            }
+            self.globals.push(interface);
+        }
+    }
+}
+
+impl wayc::Dispatch<wl_output::WlOutput> for State {
+    fn event(
+        &mut self,
+        _: wayc::Main<wl_output::WlOutput>,
+        event: wl_output::Event,
+        _: &wayc::QueueHandle<State>,
+    ) {
+        if let wl_output::Event::Scale { factor } = event {
+            self.scales.push(factor);
+        }
+    }
+}
+
+impl wayc::Dispatch<wl_callback::WlCallback> for State {
+    fn event(
+        &mut self,
+        _: wayc::Main<wl_callback::WlCallback>,
+        _: wl_callback::Event,
+        _: &wayc::QueueHandle<State>,
+    ) {
+        self.done = true;
+    }
+}
+
+// roundtrip with the server, dispatching a typed event queue
+fn typed_roundtrip(
+    client: &mut TestClient,
+    event_queue: &mut wayc::EventQueue<State>,
+    server: &mut TestServer,
+    state: &mut State,
+) {
+    let display = (**client.display).clone().attach(event_queue.token());
+    display.sync().assign_dispatch(&event_queue.handle());
+    state.done = false;
+    while !state.done {
+        client.display.flush().unwrap();
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        server.answer();
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        event_queue.dispatch_pending_typed(state).unwrap();
+        if let Some(guard) = event_queue.prepare_read() {
+            guard.read_events().unwrap();
+        }
+        event_queue.dispatch_pending_typed(state).unwrap();
+    }
+}
+
+#[test]
+fn typed_dispatch() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(
+        2,
+        ways::Filter::new(|(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.scale(2);
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let mut event_queue = client.display.create_typed_event_queue::<State>();
+    let display = (**client.display).clone().attach(event_queue.token());
+    display.get_registry().assign_dispatch(&event_queue.handle());
+
+    let mut state = State { globals: Vec::new(), scales: Vec::new(), done: false };
+    // one roundtrip to receive the globals, another one for the events of the output
+    typed_roundtrip(&mut client, &mut event_queue, &mut server, &mut state);
+    typed_roundtrip(&mut client, &mut event_queue, &mut server, &mut state);
+
+    assert_eq!(state.globals, vec!["wl_output".to_owned()]);
+    assert_eq!(state.scales, vec![2]);
+}
+
+#[test]
+fn typed_dispatch_moves_object() {
+    let mut server = TestServer::new();
+
+    let client = TestClient::new(&server.socket_name);
+    let mut event_queue = client.display.create_typed_event_queue::<State>();
+    let mut other_queue = client.display.create_event_queue();
+
+    // the callback is created on the other queue, but assigning it moves it to the typed one
+    let display = (**client.display).clone().attach(other_queue.token());
+    let callback = display.sync();
+    callback.assign_dispatch(&event_queue.handle());
+
+    let mut state = State { globals: Vec::new(), scales: Vec::new(), done: false };
+    for _ in 0..10 {
+        client.display.flush().unwrap();
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        server.answer();
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        if let Some(guard) = event_queue.prepare_read() {
+            guard.read_events().unwrap();
+        }
+        other_queue.dispatch_pending(&mut (), |_, _, _| unreachable!()).unwrap();
+        event_queue.dispatch_pending_typed(&mut state).unwrap();
+        if state.done {
+            break;
+        }
+    }
+    assert!(state.done);
+}
+
+#[test]
+fn typed_dispatch_with_filters() {
+    let mut server = TestServer::new();
+
+    let mut client = TestClient::new(&server.socket_name);
+    let mut event_queue = client.display.create_typed_event_queue::<State>();
+    let display = (**client.display).clone().attach(event_queue.token());
+
+    // objects assigned to filters can live on a typed queue
+    let called = ::std::rc::Rc::new(::std::cell::Cell::new(false));
+    let called2 = called.clone();
+    display.sync().quick_assign(move |_, _, _| called2.set(true));
+
+    let mut state = State { globals: Vec::new(), scales: Vec::new(), done: false };
+    typed_roundtrip(&mut client, &mut event_queue, &mut server, &mut state);
+
+    assert!(called.get());
+}
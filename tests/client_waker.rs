@@ -0,0 +1,88 @@
+mod helpers;
+
+use helpers::{wayc, TestClient, TestServer};
+
+use std::cell::Cell;
+use std::io::ErrorKind;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+// wake up the waker from an other thread after a delay
+fn wake_later(waker: helpers::wayc::Waker) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        waker.wake();
+    })
+}
+
+#[test]
+fn wake_dispatch() {
+    let mut server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(true));
+
+    // the server does not answer
+    let waker = client.event_queue.waker().unwrap();
+    let handle = wake_later(waker.clone());
+    let ret = client.event_queue.dispatch(&mut (), |_, _, _| unreachable!());
+    assert!(wayc::WokenUp::is_woken_up(&ret.unwrap_err()));
+    handle.join().unwrap();
+    assert!(!done.get());
+
+    // the queue keeps working once woken up
+    server.answer();
+    client.event_queue.dispatch(&mut (), |_, _, _| unreachable!()).unwrap();
+    assert!(done.get());
+}
+
+#[test]
+fn wake_before_dispatch() {
+    let server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    let waker = client.event_queue.waker().unwrap();
+    // several wake ups before a wait only interrupt it once
+    waker.wake();
+    waker.wake();
+    let ret = client.event_queue.dispatch(&mut (), |_, _, _| unreachable!());
+    assert!(wayc::WokenUp::is_woken_up(&ret.unwrap_err()));
+    let ret = client.event_queue.dispatch_timeout(
+        Duration::from_millis(50),
+        &mut (),
+        |_, _, _| unreachable!(),
+    );
+    assert_eq!(ret.unwrap_err().kind(), ErrorKind::TimedOut);
+}
+
+#[test]
+fn wake_sync_roundtrip() {
+    let server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    // the server does not answer
+    let handle = wake_later(client.event_queue.waker().unwrap());
+    let ret = client.event_queue.sync_roundtrip(&mut (), |_, _, _| unreachable!());
+    assert!(wayc::WokenUp::is_woken_up(&ret.unwrap_err()));
+    handle.join().unwrap();
+}
+
+#[test]
+fn wake_read_events() {
+    let server = TestServer::new();
+    let client = TestClient::new(&server.socket_name);
+
+    let waker = client.event_queue.waker().unwrap();
+    waker.wake();
+    let guard = client.event_queue.prepare_read().unwrap();
+    assert!(wayc::WokenUp::is_woken_up(&guard.read_events().unwrap_err()));
+
+    let handle = wake_later(waker);
+    let guard = client.event_queue.prepare_read().unwrap();
+    let ret = guard.read_events_timeout(Duration::from_secs(10));
+    assert!(wayc::WokenUp::is_woken_up(&ret.unwrap_err()));
+    handle.join().unwrap();
+}
@@ -14,17 +14,14 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
 
-pub struct TestServer {
-    pub display: self::ways::Display,
+pub struct TestServer<State = ()> {
+    pub display: self::ways::Display<State>,
     pub socket_name: OsString,
 }
 
 impl TestServer {
     pub fn new() -> TestServer {
-        let mut display = self::ways::Display::new();
-        let socket_name = display.add_socket_auto().expect("Failed to create a server socket.");
-
-        TestServer { display, socket_name }
+        TestServer::new_typed()
     }
 
     pub fn answer(&mut self) {
@@ -40,6 +37,23 @@ impl TestServer {
     }
 }
 
+impl<State> TestServer<State> {
+    pub fn new_typed() -> TestServer<State> {
+        let mut display = self::ways::Display::new_typed();
+        let socket_name = display.add_socket_auto().expect("Failed to create a server socket.");
+
+        TestServer { display, socket_name }
+    }
+
+    pub fn answer_typed(&mut self, state: &mut State) {
+        self.display.dispatch_typed(Duration::from_millis(10), state).unwrap();
+        self.display.flush_clients_typed(state);
+        // TODO: find out why native_lib requires two dispatches
+        self.display.dispatch_typed(Duration::from_millis(10), state).unwrap();
+        self.display.flush_clients_typed(state);
+    }
+}
+
 pub struct TestClient {
     pub display: Arc<self::wayc::Display>,
     pub display_proxy: self::wayc::Attached<self::wayc::protocol::wl_display::WlDisplay>,
@@ -79,6 +93,22 @@ pub fn roundtrip_with_ddata<CD: 'static, SD: 'static>(
     server: &mut TestServer,
     client_ddata: &mut CD,
     server_ddata: &mut SD,
+) -> io::Result<()> {
+    roundtrip_with(client, client_ddata, || server.answer_with_ddata(server_ddata))
+}
+
+pub fn roundtrip_typed<S>(
+    client: &mut TestClient,
+    server: &mut TestServer<S>,
+    state: &mut S,
+) -> io::Result<()> {
+    roundtrip_with(client, &mut (), || server.answer_typed(state))
+}
+
+fn roundtrip_with<CD: 'static, F: FnMut()>(
+    client: &mut TestClient,
+    client_ddata: &mut CD,
+    mut answer: F,
 ) -> io::Result<()> {
     // send to the server
     let done = Rc::new(Cell::new(false));
@@ -95,7 +125,7 @@ pub fn roundtrip_with_ddata<CD: 'static, SD: 'static>(
         }
         ::std::thread::sleep(::std::time::Duration::from_millis(100));
         // make it answer messages
-        server.answer_with_ddata(server_ddata);
+        answer();
         ::std::thread::sleep(::std::time::Duration::from_millis(100));
         // dispatch all client-side
         client.event_queue.dispatch_pending(client_ddata, |_, _, _| {})?;
@@ -26,7 +26,7 @@ fn client_wrong_id() {
 
     let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(socket.into_raw_fd()) });
     socket
-        .write_message(&Message {
+        .write_message(Message {
             sender_id: 1, // wl_display
             opcode: 1,    // wl_registry
             args: smallvec![
@@ -53,7 +53,7 @@ fn client_wrong_opcode() {
 
     let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(socket.into_raw_fd()) });
     socket
-        .write_message(&Message {
+        .write_message(Message {
             sender_id: 1, // wl_display
             opcode: 42,   // inexistant
             args: smallvec![],
@@ -78,7 +78,7 @@ fn client_wrong_sender() {
 
     let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(socket.into_raw_fd()) });
     socket
-        .write_message(&Message {
+        .write_message(Message {
             sender_id: 54, // wl_display
             opcode: 0,     // inexistant
             args: smallvec![],
@@ -127,3 +127,420 @@ fn server_code_generation() {
     );
     run_codegen_test(tempfile.path(), SERVER_CODE_TARGET);
 }
+
+#[test]
+fn ill_formed_protocol() {
+    let protocol = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<protocol name=\"test\">
+  <interface name=\"test_iface\" version=\"1\">
+    <request name=\"req\">
+      <arg name=\"foo\" type=\"float\"/>
+    </request>
+  </interface>
+</protocol>";
+    let mut output = Vec::new();
+    match wayland_scanner::try_generate_code_streams(
+        Cursor::new(protocol.as_bytes()),
+        &mut output,
+        Side::Client,
+    ) {
+        Err(wayland_scanner::Error::Parse(e)) => {
+            assert_eq!(e.line, 5);
+            assert_eq!(e.column, 7);
+            assert!(e.message.contains("float"));
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    // truncated file
+    let mut output = Vec::new();
+    match wayland_scanner::try_generate_code_streams(
+        Cursor::new(&protocol.as_bytes()[..100]),
+        &mut output,
+        Side::Server,
+    ) {
+        Err(wayland_scanner::Error::Parse(_)) => {}
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn failed_generation_keeps_target() {
+    use std::io::Write;
+    use wayland_scanner::GenerationOptions;
+
+    let mut protocol = tempfile::NamedTempFile::new().unwrap();
+    protocol.write_all(b"<protocol name=\"test\"><interface name=\"test_iface\"").unwrap();
+    let mut target = tempfile::NamedTempFile::new().unwrap();
+    target.write_all(b"// previous code").unwrap();
+
+    let options = GenerationOptions::new(Side::Client).rustfmt(false);
+    assert!(wayland_scanner::try_generate_code_with_options(
+        protocol.path(),
+        target.path(),
+        &options
+    )
+    .is_err());
+    assert_eq!(std::fs::read_to_string(target.path()).unwrap(), "// previous code");
+
+    let protocol = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scanner_assets/protocol.xml");
+    wayland_scanner::try_generate_code_with_options(&protocol, target.path(), &options).unwrap();
+    let code = std::fs::read_to_string(target.path()).unwrap();
+    assert!(!code.contains("previous code"));
+    assert!(code.contains("wl_display"));
+}
+
+#[test]
+fn invalid_names_and_types() {
+    let cases = [
+        // destructor is only a valid type for messages
+        ("<request name=\"req\"><arg name=\"foo\" type=\"destructor\"/></request>", "destructor"),
+        // missing or empty names
+        ("<request><arg name=\"foo\" type=\"int\"/></request>", "request must have a name"),
+        ("<event name=\"\"/>", "event must have a name"),
+        ("<request name=\"req\"><arg type=\"int\"/></request>", "arg must have a name"),
+        ("<enum><entry name=\"foo\" value=\"0\"/></enum>", "enum must have a name"),
+        ("<enum name=\"e\"><entry name=\"\" value=\"0\"/></enum>", "entry must have a name"),
+    ];
+    for &(contents, message) in &cases {
+        let protocol = format!(
+            "<protocol name=\"test\"><interface name=\"test_iface\" version=\"1\">{}</interface></protocol>",
+            contents
+        );
+        let mut output = Vec::new();
+        match wayland_scanner::try_generate_code_streams(
+            Cursor::new(protocol.as_bytes()),
+            &mut output,
+            Side::Client,
+        ) {
+            Err(wayland_scanner::Error::Parse(e)) => {
+                assert!(e.message.contains(message), "{}: {}", contents, e.message)
+            }
+            other => panic!("Unexpected result for {}: {:?}", contents, other),
+        }
+    }
+
+    for protocol in &[
+        "<protocol name=\"\"></protocol>",
+        "<protocol name=\"test\"><interface version=\"1\"></interface></protocol>",
+    ] {
+        let mut output = Vec::new();
+        match wayland_scanner::try_generate_code_streams(
+            Cursor::new(protocol.as_bytes()),
+            &mut output,
+            Side::Server,
+        ) {
+            Err(wayland_scanner::Error::Parse(e)) => {
+                assert!(e.message.contains("must have a name"))
+            }
+            other => panic!("Unexpected result for {}: {:?}", protocol, other),
+        }
+    }
+}
+
+#[test]
+fn protocol_lints() {
+    use wayland_scanner::{LintLevel, LintMessage};
+
+    let protocol = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<protocol name=\"test\">
+  <interface name=\"test_iface\" version=\"2\">
+    <request name=\"create\">
+      <arg name=\"id\" type=\"new_id\"/>
+      <arg name=\"kind\" type=\"uint\" enum=\"missing\"/>
+    </request>
+    <request name=\"create\" since=\"3\">
+      <arg name=\"edges\" type=\"uint\" enum=\"test_iface.edges\"/>
+      <arg name=\"output\" type=\"object\" enum=\"wl_output.transform\"/>
+    </request>
+    <enum name=\"edges\" bitfield=\"true\">
+      <entry name=\"none\" value=\"0\"/>
+      <entry name=\"top\" value=\"1\"/>
+      <entry name=\"left\" value=\"4\"/>
+      <entry name=\"top_left\" value=\"5\"/>
+      <entry name=\"odd\" value=\"0x12\"/>
+    </enum>
+    <enum name=\"kind\">
+      <entry name=\"a\" value=\"0\"/>
+      <entry name=\"b\" value=\"0\"/>
+    </enum>
+  </interface>
+</protocol>";
+
+    let lint = |level, location: &str, message: &str| LintMessage {
+        level,
+        location: location.into(),
+        message: message.into(),
+    };
+    let lints = wayland_scanner::lint_protocol(Cursor::new(protocol.as_bytes())).unwrap();
+    assert_eq!(
+        lints,
+        vec![
+            lint(
+                LintLevel::Warning,
+                "test_iface.create.id",
+                "new_id without an interface is only expected in bind requests"
+            ),
+            lint(LintLevel::Error, "test_iface.create.kind", "unknown enum `missing`"),
+            lint(LintLevel::Error, "test_iface.create", "duplicate request name"),
+            lint(
+                LintLevel::Warning,
+                "test_iface.create",
+                "since 3 is greater than the interface version 2"
+            ),
+            lint(
+                LintLevel::Warning,
+                "test_iface.edges.odd",
+                "bitfield entry value 0x12 is neither a power of two nor a combination of other \
+                 entries"
+            ),
+            lint(
+                LintLevel::Error,
+                "test_iface.kind.b",
+                "duplicate value 0 in an enum that is not a bitfield"
+            ),
+        ]
+    );
+
+    let mut output = Vec::new();
+    match wayland_scanner::try_generate_code_streams(
+        Cursor::new(protocol.as_bytes()),
+        &mut output,
+        Side::Client,
+    ) {
+        Err(wayland_scanner::Error::Lint(errors)) => {
+            assert!(errors.iter().all(|lint| lint.level == LintLevel::Error));
+            assert_eq!(errors.len(), 3);
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn interface_registry() {
+    use wayland_scanner::{Error, InterfaceRegistry, LintLevel};
+
+    const MACRO_PROTOCOL: &str = include_str!("./scanner_assets/macro_protocol.xml");
+    const XDG_SHELL: &str =
+        include_str!("../wayland-protocols/protocols/stable/xdg-shell/xdg-shell.xml");
+
+    // xdg_toplevel is not in the registry
+    let registry = InterfaceRegistry::new(Side::Server);
+    let mut output = Vec::new();
+    match wayland_scanner::try_generate_code_streams_with_registry(
+        Cursor::new(MACRO_PROTOCOL.as_bytes()),
+        &mut output,
+        &registry,
+        &[],
+    ) {
+        Err(Error::UnresolvedInterfaces(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].level, LintLevel::Error);
+            assert_eq!(errors[0].location, "test_decoration_manager.get_decoration.toplevel");
+            assert!(errors[0].message.contains("xdg_toplevel"));
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    let mut registry = InterfaceRegistry::new(Side::Server);
+    registry.add_protocol_stream(Cursor::new(XDG_SHELL.as_bytes()), "crate::xdg_shell").unwrap();
+    assert_eq!(registry.module("xdg_toplevel"), Some("crate::xdg_shell"));
+    assert_eq!(registry.module("wl_surface"), Some("wayland_server::protocol"));
+
+    let mut output = Vec::new();
+    wayland_scanner::try_generate_code_streams_with_registry(
+        Cursor::new(MACRO_PROTOCOL.as_bytes()),
+        &mut output,
+        &registry,
+        &[],
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with(
+        "use wayland_server::protocol::wl_surface;\nuse crate::xdg_shell::xdg_toplevel;\n"
+    ));
+}
+
+#[test]
+fn protocol_json() {
+    use wayland_scanner::protocol::{Protocol, Type};
+
+    let protocol = wayland_scanner::parse_protocol(Cursor::new(PROTOCOL.as_bytes())).unwrap();
+    assert_eq!(protocol.name, "wayland");
+    let foo = &protocol.interfaces[0];
+    assert_eq!((&foo.name[..], foo.version), ("wl_foo", 3));
+    assert_eq!(foo.requests[0].args[4].typ, Type::Fd);
+    assert_eq!(foo.events[0].since, 2);
+    assert_eq!(foo.enums[0].entries[2].since, 3);
+
+    let mut json = Vec::new();
+    wayland_scanner::dump_protocol_json(Cursor::new(PROTOCOL.as_bytes()), &mut json).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let foo = &value["interfaces"][0];
+    assert_eq!(foo["name"], "wl_foo");
+    assert_eq!(foo["description"][0], "Interface for fooing");
+    assert_eq!(foo["requests"][1]["args"][0]["typ"], "new_id");
+    assert_eq!(foo["requests"][1]["args"][0]["interface"], "wl_bar");
+    assert_eq!(foo["enums"][1]["bitfield"], true);
+    assert_eq!(foo["events"][0]["args"][0]["enum_"], "cake_kind");
+    assert_eq!(value["interfaces"][1]["requests"][1]["typ"], "destructor");
+
+    // the JSON can be read back
+    let protocol: Protocol = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&protocol).unwrap(), value);
+}
+
+#[test]
+fn protocol_compatibility() {
+    use wayland_scanner::{Compatibility, ProtocolChange};
+
+    const OLD: &str = r#"<protocol name="compat">
+      <interface name="test_foo" version="2">
+        <request name="destroy" type="destructor" />
+        <request name="set_size">
+          <arg name="width" type="int" /><arg name="height" type="int" />
+        </request>
+        <request name="set_parent"><arg name="parent" type="object" interface="test_foo" /></request>
+        <event name="done" />
+        <enum name="kind"><entry name="a" value="0" /><entry name="b" value="1" /></enum>
+      </interface>
+      <interface name="test_bar" version="1" />
+    </protocol>"#;
+    const NEW: &str = r#"<protocol name="compat">
+      <interface name="test_foo" version="3">
+        <request name="destroy" type="destructor" />
+        <request name="set_size">
+          <arg name="w" type="int" /><arg name="height" type="uint" />
+        </request>
+        <request name="set_parent">
+          <arg name="parent" type="object" interface="test_foo" allow-null="true" />
+        </request>
+        <request name="set_title" since="3"><arg name="title" type="string" /></request>
+        <request name="set_icon" since="4" />
+        <event name="done" />
+        <event name="closed" />
+        <enum name="kind">
+          <entry name="a" value="0" /><entry name="c" value="2" since="3" />
+          <entry name="d" value="3" />
+        </enum>
+      </interface>
+      <interface name="test_baz" version="1">
+        <request name="destroy" type="destructor" />
+      </interface>
+    </protocol>"#;
+
+    let changes = wayland_scanner::check_compatibility(
+        Cursor::new(OLD.as_bytes()),
+        Cursor::new(NEW.as_bytes()),
+    )
+    .unwrap();
+    let changes = changes
+        .iter()
+        .map(|change| (change.compatibility, &change.location[..]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            (Compatibility::Compatible, "test_foo"),
+            (Compatibility::Compatible, "test_foo.set_size.width"),
+            (Compatibility::Breaking, "test_foo.set_size.height"),
+            (Compatibility::Breaking, "test_foo.set_parent.parent"),
+            (Compatibility::Compatible, "test_foo.set_title"),
+            (Compatibility::Breaking, "test_foo.set_icon"),
+            (Compatibility::Breaking, "test_foo.closed"),
+            (Compatibility::Breaking, "test_foo.kind.b"),
+            (Compatibility::Compatible, "test_foo.kind.c"),
+            (Compatibility::Breaking, "test_foo.kind.d"),
+            (Compatibility::Breaking, "test_bar"),
+            (Compatibility::Compatible, "test_baz"),
+        ]
+    );
+
+    // messages added to a later version of the interface than the new one
+    let unbumped = OLD.replace(
+        r#"<event name="done" />"#,
+        r#"<event name="done" /><event name="closed" since="3" />"#,
+    );
+    let changes = wayland_scanner::check_compatibility(
+        Cursor::new(OLD.as_bytes()),
+        Cursor::new(unbumped.as_bytes()),
+    )
+    .unwrap();
+    assert_eq!(
+        changes,
+        vec![ProtocolChange {
+            compatibility: Compatibility::Breaking,
+            location: "test_foo.closed".into(),
+            message: "event added with since 3, which is greater than the version 2 of the \
+                      interface"
+                .into(),
+        }]
+    );
+
+    // reordering messages changes their opcodes
+    let reordered = OLD.replace(r#"<request name="destroy" type="destructor" />"#, "").replace(
+        "</interface>\n      <interface",
+        r#"<request name="destroy" type="destructor" /></interface><interface"#,
+    );
+    let changes = wayland_scanner::check_compatibility(
+        Cursor::new(OLD.as_bytes()),
+        Cursor::new(reordered.as_bytes()),
+    )
+    .unwrap();
+    assert_eq!(
+        changes[0],
+        ProtocolChange {
+            compatibility: Compatibility::Breaking,
+            location: "test_foo.destroy".into(),
+            message: "request moved from opcode 0 to 2".into(),
+        }
+    );
+    assert!(changes.iter().all(|change| change.compatibility == Compatibility::Breaking));
+
+    let same = wayland_scanner::check_compatibility(
+        Cursor::new(PROTOCOL.as_bytes()),
+        Cursor::new(PROTOCOL.as_bytes()),
+    )
+    .unwrap();
+    assert!(same.is_empty());
+}
+
+#[test]
+fn interface_filter() {
+    use wayland_scanner::{Error, GenerationOptions};
+
+    let generate = |interfaces: &[&str]| {
+        let options = GenerationOptions::new(Side::Client).interfaces(interfaces);
+        let mut output = Vec::new();
+        wayland_scanner::try_generate_code_streams_with_options(
+            Cursor::new(PROTOCOL.as_bytes()),
+            &mut output,
+            &options,
+        )
+        .map(|()| String::from_utf8(output).unwrap())
+    };
+
+    let output = generate(&["wl_foo", "wl_bar"]).unwrap();
+    assert!(output.contains("pub mod wl_foo"));
+    assert!(output.contains("pub mod wl_bar"));
+    assert!(!output.contains("pub mod wl_callback"));
+
+    // wl_foo.create_bar creates a wl_bar, which depends on wl_foo in turn
+    let mut output = Vec::new();
+    wayland_scanner::try_generate_code_streams_with_interfaces(
+        Cursor::new(PROTOCOL.as_bytes()),
+        &mut output,
+        Side::Client,
+        &["wl_foo"],
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), generate(&["wl_bar"]).unwrap());
+
+    match generate(&["wl_baz"]) {
+        Err(Error::UnresolvedInterfaces(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].location, "wl_baz");
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
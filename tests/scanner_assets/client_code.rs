@@ -15,8 +15,8 @@ pub mod wl_foo {
     use super::sys::client::*;
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Proxy, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Proxy, NULLPTR,
     };
     use std::os::raw::c_char;
     #[doc = "Possible cake kinds\n\nList of the possible kind of cake supported by the protocol."]
@@ -57,13 +57,7 @@ pub mod wl_foo {
     #[non_exhaustive]
     pub enum Request {
         #[doc = "do some foo\n\nThis will do some foo with its args."]
-        FooIt {
-            number: i32,
-            unumber: u32,
-            text: String,
-            float: f64,
-            file: ::std::os::unix::io::RawFd,
-        },
+        FooIt { number: i32, unumber: u32, text: String, float: f64, file: OwnedFd },
         #[doc = "create a bar\n\nCreate a bar which will do its bar job."]
         CreateBar {},
     }
@@ -158,7 +152,7 @@ pub mod wl_foo {
                     let _arg_2 = ::std::ffi::CString::new(text).unwrap();
                     _args_array[2].s = _arg_2.as_ptr();
                     _args_array[3].f = (float * 256.) as i32;
-                    _args_array[4].h = file;
+                    _args_array[4].h = ::std::os::unix::io::AsRawFd::as_raw_fd(&file);
                     f(0, &mut _args_array)
                 }
                 Request::CreateBar {} => {
@@ -292,17 +286,19 @@ pub mod wl_foo {
         }
     }
     impl WlFoo {
-        #[doc = "do some foo\n\nThis will do some foo with its args."]
+        #[doc = "do some foo\n\nThis will do some foo with its args.\n\nFails if the file descriptors cannot be duplicated."]
         pub fn foo_it(
             &self,
             number: i32,
             unumber: u32,
             text: String,
             float: f64,
-            file: ::std::os::unix::io::RawFd,
-        ) -> () {
-            let msg = Request::FooIt { number, unumber, text, float, file };
+            file: BorrowedFd<'_>,
+        ) -> ::std::io::Result<()> {
+            let msg =
+                Request::FooIt { number, unumber, text, float, file: file.try_clone_to_owned()? };
             self.0.send::<AnonymousObject>(msg, None);
+            Ok(())
         }
         #[doc = "create a bar\n\nCreate a bar which will do its bar job."]
         pub fn create_bar(&self) -> Main<super::wl_bar::WlBar> {
@@ -352,8 +348,8 @@ pub mod wl_bar {
     use super::sys::client::*;
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Proxy, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Proxy, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
@@ -823,8 +819,8 @@ pub mod wl_display {
     use super::sys::client::*;
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Proxy, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Proxy, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
@@ -969,8 +965,8 @@ pub mod wl_registry {
     use super::sys::client::*;
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Proxy, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Proxy, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
@@ -1170,8 +1166,8 @@ pub mod wl_callback {
     use super::sys::client::*;
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Proxy, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Proxy, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
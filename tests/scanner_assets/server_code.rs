@@ -15,8 +15,8 @@ pub mod wl_foo {
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::sys::server::*;
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Resource, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Resource, NULLPTR,
     };
     use std::os::raw::c_char;
     #[doc = "Possible cake kinds\n\nList of the possible kind of cake supported by the protocol."]
@@ -57,13 +57,7 @@ pub mod wl_foo {
     #[non_exhaustive]
     pub enum Request {
         #[doc = "do some foo\n\nThis will do some foo with its args."]
-        FooIt {
-            number: i32,
-            unumber: u32,
-            text: String,
-            float: f64,
-            file: ::std::os::unix::io::RawFd,
-        },
+        FooIt { number: i32, unumber: u32, text: String, float: f64, file: OwnedFd },
         #[doc = "create a bar\n\nCreate a bar which will do its bar job."]
         CreateBar { id: Main<super::wl_bar::WlBar> },
     }
@@ -192,7 +186,7 @@ pub mod wl_foo {
                         unumber: _args[1].u,
                         text: ::std::ffi::CStr::from_ptr(_args[2].s).to_string_lossy().into_owned(),
                         float: (_args[3].f as f64) / 256.,
-                        file: _args[4].h,
+                        file: <OwnedFd as ::std::os::unix::io::FromRawFd>::from_raw_fd(_args[4].h),
                     })
                 }
                 1 => {
@@ -368,8 +362,8 @@ pub mod wl_bar {
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::sys::server::*;
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Resource, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Resource, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
@@ -850,8 +844,8 @@ pub mod wl_callback {
     use super::sys::common::{wl_argument, wl_array, wl_interface, wl_message};
     use super::sys::server::*;
     use super::{
-        smallvec, types_null, AnonymousObject, Argument, ArgumentType, Interface, Main, Message,
-        MessageDesc, MessageGroup, Object, ObjectMetadata, Resource, NULLPTR,
+        smallvec, types_null, AnonymousObject, Argument, ArgumentType, BorrowedFd, Interface, Main,
+        Message, MessageDesc, MessageGroup, Object, ObjectMetadata, OwnedFd, Resource, NULLPTR,
     };
     use std::os::raw::c_char;
     #[derive(Debug)]
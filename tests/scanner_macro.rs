@@ -0,0 +1,85 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+mod server_decoration {
+    // interfaces from other protocols are taken from this module
+    use wayland_protocols::xdg_shell::server::xdg_toplevel;
+
+    wayland_scanner_macros::generate_protocol!("tests/scanner_assets/macro_protocol.xml", server);
+}
+
+mod client_decoration {
+    use wayland_protocols::xdg_shell::client::xdg_toplevel;
+
+    wayland_scanner_macros::generate_protocol!("tests/scanner_assets/macro_protocol.xml", client);
+}
+
+use client_decoration::test_decoration::Event as ClientEvent;
+use client_decoration::test_decoration_manager::TestDecorationManager as ClientManager;
+use server_decoration::test_decoration::Request as ServerRequest;
+use server_decoration::test_decoration_manager::{
+    Request as ServerManagerRequest, TestDecorationManager as ServerManager,
+};
+
+use wayc::protocol::wl_compositor::WlCompositor as ClientCompositor;
+use ways::protocol::wl_compositor::WlCompositor as ServerCompositor;
+
+#[test]
+fn macro_generated_protocol() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor, _>(
+        1,
+        ways::Filter::new(|(compositor, _): (ways::Main<ServerCompositor>, u32), _, _| {
+            compositor.quick_assign(|_, request, _| {
+                if let ways::protocol::wl_compositor::Request::CreateSurface { id } = request {
+                    id.quick_assign(|_, _, _| {});
+                }
+            });
+        }),
+    );
+    let destroyed = Rc::new(Cell::new(false));
+    let destroyed2 = destroyed.clone();
+    server.display.create_global::<ServerManager, _>(
+        1,
+        ways::Filter::new(move |(manager, _): (ways::Main<ServerManager>, u32), _, _| {
+            let destroyed3 = destroyed2.clone();
+            manager.quick_assign(move |_, request, _| match request {
+                ServerManagerRequest::GetDecoration { id, surface, toplevel } => {
+                    assert!(surface.as_ref().is_alive());
+                    assert!(toplevel.is_none());
+                    let destroyed4 = destroyed3.clone();
+                    id.quick_assign(move |_, request, _| match request {
+                        ServerRequest::Destroy => destroyed4.set(true),
+                    });
+                    id.configure(42);
+                }
+            });
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let compositor = manager.instantiate_exact::<ClientCompositor>(1).unwrap();
+    let decoration_manager = manager.instantiate_exact::<ClientManager>(1).unwrap();
+    let surface = compositor.create_surface();
+    let decoration = decoration_manager.get_decoration(&surface, None);
+    let mode = Rc::new(Cell::new(0));
+    let mode2 = mode.clone();
+    decoration.quick_assign(move |_, event, _| match event {
+        ClientEvent::Configure { mode } => mode2.set(mode),
+    });
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(mode.get(), 42);
+
+    decoration.destroy();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert!(destroyed.get());
+}
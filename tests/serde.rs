@@ -0,0 +1,199 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{wl_compositor, wl_output, wl_seat, wl_surface};
+
+use wayc::protocol::wl_compositor::WlCompositor as ClientCompositor;
+use wayc::protocol::wl_output::WlOutput as ClientOutput;
+
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn enums_by_name() {
+    let transform = wl_output::Transform::Flipped90;
+    assert_eq!(serde_json::to_string(&transform).unwrap(), "\"flipped_90\"");
+    assert_eq!(serde_json::from_str::<wl_output::Transform>("\"flipped_90\"").unwrap(), transform);
+    assert!(serde_json::from_str::<wl_output::Transform>("\"upside_down\"").is_err());
+
+    let capabilities = wl_seat::Capability::Pointer | wl_seat::Capability::Touch;
+    assert_eq!(serde_json::to_string(&capabilities).unwrap(), "[\"pointer\",\"touch\"]");
+    assert_eq!(
+        serde_json::from_str::<wl_seat::Capability>("[\"pointer\",\"touch\"]").unwrap(),
+        capabilities
+    );
+    assert_eq!(
+        serde_json::from_str::<wl_seat::Capability>("[]").unwrap(),
+        wl_seat::Capability::empty()
+    );
+}
+
+#[test]
+fn messages_by_id() {
+    let mut server = TestServer::new();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let requests2 = requests.clone();
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<wl_compositor::WlCompositor>, u32), _, _| {
+                let requests3 = requests2.clone();
+                compositor.quick_assign(move |_, request, _| {
+                    requests3.lock().unwrap().push(serde_json::to_value(&request).unwrap());
+                    match request {
+                        wl_compositor::Request::CreateSurface { id } => {
+                            let requests4 = requests3.clone();
+                            id.quick_assign(move |_, request: wl_surface::Request, _| {
+                                requests4
+                                    .lock()
+                                    .unwrap()
+                                    .push(serde_json::to_value(&request).unwrap());
+                            });
+                        }
+                        wl_compositor::Request::CreateRegion { id } => {
+                            id.quick_assign(|_, _, _| {})
+                        }
+                        _ => {}
+                    }
+                });
+            },
+        ),
+    );
+    server.display.create_global::<wl_output::WlOutput, _>(
+        2,
+        ways::Filter::new(|(output, _): (ways::Main<wl_output::WlOutput>, u32), _, _| {
+            output.geometry(
+                0,
+                0,
+                40,
+                30,
+                wl_output::Subpixel::HorizontalRgb,
+                "make".into(),
+                "model".into(),
+                wl_output::Transform::_90,
+            );
+            output.scale(2);
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = events.clone();
+    let output = manager.instantiate_exact::<ClientOutput>(2).unwrap();
+    output.quick_assign(move |_, event, _| {
+        events2.lock().unwrap().push(serde_json::to_value(&event).unwrap());
+    });
+    let compositor = manager.instantiate_exact::<ClientCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+    let region = compositor.create_region();
+    surface.set_input_region(Some(&region));
+    surface.set_opaque_region(None);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            serde_json::json!({ "geometry": {
+                "x": 0,
+                "y": 0,
+                "physical_width": 40,
+                "physical_height": 30,
+                "subpixel": "horizontal_rgb",
+                "make": "make",
+                "model": "model",
+                "transform": "90",
+            }}),
+            serde_json::json!({ "scale": { "factor": 2 } }),
+        ]
+    );
+
+    let surface_id = surface.as_ref().id();
+    let region_id = region.as_ref().id();
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec![
+            serde_json::json!({ "create_surface": { "id": surface_id } }),
+            serde_json::json!({ "create_region": { "id": region_id } }),
+            serde_json::json!({ "set_input_region": { "region": region_id } }),
+            serde_json::json!({ "set_opaque_region": { "region": null } }),
+        ]
+    );
+}
+
+#[test]
+fn raw_messages_by_opcode() {
+    use wayland_commons::wire::Argument;
+
+    let cstr = |s: &str| Argument::Str(Box::new(std::ffi::CString::new(s).unwrap()));
+
+    let geometry = serde_json::json!({ "geometry": {
+        "x": 0,
+        "y": 0,
+        "physical_width": 40,
+        "physical_height": 30,
+        "subpixel": "horizontal_rgb",
+        "make": "make",
+        "model": "model",
+        "transform": "90",
+    }});
+    let msg = wayc::protocol::wl_output::Event::deserialize_raw(geometry, 3).unwrap();
+    assert_eq!((msg.sender_id, msg.opcode), (3, 0));
+    assert_eq!(
+        &msg.args[..],
+        &[
+            Argument::Int(0),
+            Argument::Int(0),
+            Argument::Int(40),
+            Argument::Int(30),
+            Argument::Int(wl_output::Subpixel::HorizontalRgb.to_raw() as i32),
+            cstr("make"),
+            cstr("model"),
+            Argument::Int(wl_output::Transform::_90.to_raw() as i32),
+        ][..]
+    );
+
+    // the fields can be given in order
+    let mut scale = serde_json::Deserializer::from_str(r#"{ "scale": [2] }"#);
+    let msg = ways::protocol::wl_output::Event::deserialize_raw(&mut scale, 3).unwrap();
+    assert_eq!(msg.opcode, 3);
+    assert_eq!(&msg.args[..], &[Argument::Int(2)][..]);
+
+    let capabilities =
+        serde_json::json!({ "capabilities": { "capabilities": ["pointer", "touch"] } });
+    let msg = wayc::protocol::wl_seat::Event::deserialize_raw(capabilities, 4).unwrap();
+    let capabilities = wl_seat::Capability::Pointer | wl_seat::Capability::Touch;
+    assert_eq!(&msg.args[..], &[Argument::Uint(capabilities.to_raw())][..]);
+
+    // client-side requests do not serialize the objects they create
+    let create_surface = serde_json::json!({ "create_surface": {} });
+    let msg = wayc::protocol::wl_compositor::Request::deserialize_raw(create_surface, 5).unwrap();
+    assert_eq!(&msg.args[..], &[Argument::NewId(0)][..]);
+
+    let set_opaque_region = serde_json::json!({ "set_opaque_region": { "region": null } });
+    let msg = wl_surface::Request::deserialize_raw(set_opaque_region, 6).unwrap();
+    assert_eq!(msg.opcode, 4);
+    assert_eq!(&msg.args[..], &[Argument::Object(0)][..]);
+
+    let bind = serde_json::json!({ "bind": { "name": 1, "id": ["wl_output", 2] } });
+    let msg = wayc::protocol::wl_registry::Request::deserialize_raw(bind, 2).unwrap();
+    assert_eq!(
+        &msg.args[..],
+        &[Argument::Uint(1), cstr("wl_output"), Argument::Uint(2), Argument::NewId(0)][..]
+    );
+
+    let destroy = serde_json::json!("destroy");
+    let msg = wl_surface::Request::deserialize_raw(destroy, 6).unwrap();
+    assert_eq!((msg.opcode, msg.args.len()), (0, 0));
+
+    // unknown messages and entries, and file descriptors
+    assert!(wl_surface::Request::deserialize_raw(serde_json::json!("explode"), 6).is_err());
+    let mode = serde_json::json!({ "scale": { "factor": "two" } });
+    assert!(ways::protocol::wl_output::Event::deserialize_raw(mode, 3).is_err());
+    let keymap = serde_json::json!({ "keymap": { "format": "xkb_v1", "fd": 12, "size": 42 } });
+    assert!(wayc::protocol::wl_keyboard::Event::deserialize_raw(keymap, 8).is_err());
+}
@@ -0,0 +1,43 @@
+#![cfg(not(feature = "server_native"))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_seat::WlSeat as ServerSeat;
+
+use wayc::protocol::wl_seat;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn oversized_event_kills_client() {
+    let mut server = TestServer::new();
+    let server_client = Rc::new(RefCell::new(None));
+    let server_client2 = server_client.clone();
+    server.display.create_global::<ServerSeat, _>(
+        5,
+        ways::Filter::new(move |(seat, _): (ways::Main<ServerSeat>, u32), _, _| {
+            *server_client2.borrow_mut() = seat.as_ref().client();
+            // larger than the default buffer size of 4096 bytes
+            seat.name("a".repeat(5000));
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    manager.instantiate_exact::<wl_seat::WlSeat>(5).unwrap();
+
+    assert!(roundtrip(&mut client, &mut server).is_err());
+    let server_client = server_client.borrow();
+    let server_client = server_client.as_ref().unwrap();
+    assert!(!server_client.alive());
+    let reason = server_client.kill_reason().unwrap();
+    let error = reason.get_ref().unwrap().downcast_ref::<ways::MessageTooLarge>().unwrap();
+    assert_eq!(error.interface, "wl_seat");
+    assert_eq!(error.message, "name");
+}
@@ -0,0 +1,84 @@
+#![cfg(not(feature = "server_native"))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::capture::{CaptureReader, CaptureWriter, Direction};
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use std::io::{Result as IoResult, Write};
+use std::os::unix::io::IntoRawFd;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+// reassemble the wayland messages from the records of a direction
+fn messages(capture: &[u8], direction: Direction) -> Vec<(u32, u16, usize)> {
+    let mut bytes = Vec::new();
+    for record in CaptureReader::new(capture).unwrap() {
+        let record = record.unwrap();
+        assert!(record.fds == 0);
+        if record.direction == direction {
+            bytes.extend_from_slice(&record.bytes);
+        }
+    }
+    let words = bytes
+        .chunks(4)
+        .map(|w| u32::from_ne_bytes([w[0], w[1], w[2], w[3]]))
+        .collect::<Vec<_>>();
+    let mut msgs = Vec::new();
+    let mut offset = 0;
+    while offset < words.len() {
+        let len = (words[offset + 1] >> 16) as usize;
+        msgs.push((words[offset], (words[offset + 1] & 0xFFFF) as u16, len));
+        offset += len / 4;
+    }
+    msgs
+}
+
+#[test]
+fn capture_client_traffic() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(2, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let (server_cx, client_cx) = ::std::os::unix::net::UnixStream::pair().unwrap();
+    let server_client = unsafe { server.display.create_client(server_cx.into_raw_fd(), &mut ()) };
+
+    let buffer = SharedBuffer(Arc::new(Mutex::new(Vec::new())));
+    assert!(server_client
+        .set_capture_hook(Some(Box::new(CaptureWriter::new(buffer.clone()).unwrap())))
+        .is_none());
+
+    let mut client = unsafe { TestClient::from_fd(client_cx.into_raw_fd()) };
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(manager.list().len(), 1);
+
+    // stop the capture
+    assert!(server_client.set_capture_hook(None).is_some());
+
+    let capture = buffer.0.lock().unwrap();
+
+    // wl_display.get_registry and wl_display.sync
+    assert_eq!(messages(&capture, Direction::Received), vec![(1, 1, 12), (1, 0, 12)]);
+
+    // wl_registry.global, wl_callback.done and wl_display.delete_id
+    let sent = messages(&capture, Direction::Sent);
+    assert_eq!(sent.len(), 3);
+    assert_eq!((sent[0].0, sent[0].1), (2, 0));
+    assert_eq!(sent[1], (3, 0, 12));
+    assert_eq!(sent[2], (1, 1, 12));
+}
@@ -0,0 +1,92 @@
+mod helpers;
+
+use helpers::{wayc, ways, TestClient};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use nix::poll::{poll, PollFd, PollFlags};
+
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+fn test_display() -> (Rc<RefCell<ways::Display>>, Rc<RefCell<u32>>, std::ffi::OsString) {
+    let mut display = ways::Display::new();
+    let socket_name = display.add_socket_auto().unwrap();
+    let binds = Rc::new(RefCell::new(0));
+    let binds2 = binds.clone();
+    display.create_global::<ServerOutput, _>(
+        1,
+        ways::Filter::new(move |(_, _): (ways::Main<ServerOutput>, u32), _, _| {
+            *binds2.borrow_mut() += 1;
+        }),
+    );
+    (Rc::new(RefCell::new(display)), binds, socket_name)
+}
+
+#[test]
+fn display_source_dispatch() {
+    let (display, binds, socket_name) = test_display();
+    let source = ways::DisplaySource::new(display.clone());
+
+    let mut client = TestClient::new(&socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    let roundtrip = |client: &mut TestClient| {
+        let done = Rc::new(RefCell::new(false));
+        let done2 = done.clone();
+        client.display_proxy.sync().quick_assign(move |_, _, _| *done2.borrow_mut() = true);
+        client.display.flush().unwrap();
+        while !*done.borrow() {
+            // only dispatch the server when it is ready
+            let mut fds = [PollFd::new(source.poll_fd(), PollFlags::POLLIN)];
+            if poll(&mut fds, 10).unwrap() > 0 {
+                source.dispatch(&mut ()).unwrap();
+            }
+            if let Some(guard) = client.event_queue.prepare_read() {
+                let _ = guard.read_events();
+            }
+            client.event_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+        }
+    };
+
+    roundtrip(&mut client);
+    manager.instantiate_exact::<wayc::protocol::wl_output::WlOutput>(1).unwrap();
+    roundtrip(&mut client);
+
+    assert_eq!(*binds.borrow(), 1);
+}
+
+#[test]
+fn display_source_calloop() {
+    let (display, binds, socket_name) = test_display();
+
+    let mut event_loop = calloop::EventLoop::<()>::try_new().unwrap();
+    event_loop
+        .handle()
+        .insert_source(ways::DisplaySource::new(display.clone()), |(), display, data| {
+            display.dispatch_and_flush(data)
+        })
+        .unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let done2 = done.clone();
+    let client_thread = ::std::thread::spawn(move || {
+        let mut client = TestClient::new(&socket_name);
+        let manager = wayc::GlobalManager::new(&client.display_proxy);
+        client.event_queue.sync_roundtrip(&mut (), |_, _, _| {}).unwrap();
+        manager.instantiate_exact::<wayc::protocol::wl_output::WlOutput>(1).unwrap();
+        client.event_queue.sync_roundtrip(&mut (), |_, _, _| {}).unwrap();
+        done2.store(true, Ordering::SeqCst);
+    });
+
+    while !done.load(Ordering::SeqCst) {
+        event_loop.dispatch(Some(Duration::from_millis(10)), &mut ()).unwrap();
+        display.borrow_mut().flush_clients(&mut ());
+    }
+
+    client_thread.join().unwrap();
+    assert_eq!(*binds.borrow(), 1);
+}
@@ -0,0 +1,113 @@
+#![cfg(not(feature = "server_native"))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_seat::WlSeat as ServerSeat;
+
+use wayc::protocol::wl_seat;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+// large enough to not fit in the kernel buffers of the socket
+const EVENT_COUNT: usize = 4000;
+
+#[test]
+fn slow_client_is_kept_alive() {
+    let mut server = TestServer::new();
+    let server_seat = Rc::new(RefCell::new(None));
+    let server_seat2 = server_seat.clone();
+    server.display.create_global::<ServerSeat, _>(
+        5,
+        ways::Filter::new(move |(seat, _): (ways::Main<ServerSeat>, u32), _, _| {
+            *server_seat2.borrow_mut() = Some(seat);
+        }),
+    );
+    let high_water = Rc::new(Cell::new(0));
+    let high_water2 = high_water.clone();
+    server.display.set_outgoing_high_water_mark(64 * 1024, move |client, pending, _| {
+        assert!(client.alive());
+        assert!(pending >= 64 * 1024);
+        high_water2.set(high_water2.get() + 1);
+    });
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let received = Rc::new(Cell::new(0));
+    let received2 = received.clone();
+    let seat = manager.instantiate_exact::<wl_seat::WlSeat>(5).unwrap();
+    seat.quick_assign(move |_, event, _| {
+        if let wl_seat::Event::Name { name } = event {
+            assert_eq!(name.len(), 100);
+            received2.set(received2.get() + 1);
+        }
+    });
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the client does not read its socket while the server sends
+    let seat = server_seat.borrow_mut().take().unwrap();
+    for _ in 0..EVENT_COUNT {
+        seat.name("a".repeat(100));
+    }
+    server.display.flush_clients(&mut ());
+    server.display.flush_clients(&mut ());
+
+    let server_client = seat.as_ref().client().unwrap();
+    assert!(server_client.alive());
+    assert!(server_client.pending_outgoing_bytes() > 0);
+    assert_eq!(high_water.get(), 1);
+
+    // now read everything
+    for _ in 0..10_000 {
+        if received.get() == EVENT_COUNT {
+            break;
+        }
+        server.display.flush_clients(&mut ());
+        if let Some(guard) = client.event_queue.prepare_read() {
+            let _ = guard.read_events();
+        }
+        client.event_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    }
+
+    assert_eq!(received.get(), EVENT_COUNT);
+    assert!(server_client.alive());
+    assert_eq!(server_client.pending_outgoing_bytes(), 0);
+}
+
+#[test]
+fn outgoing_queue_limit_kills_client() {
+    let mut server = TestServer::new();
+    let server_seat = Rc::new(RefCell::new(None));
+    let server_seat2 = server_seat.clone();
+    server.display.create_global::<ServerSeat, _>(
+        5,
+        ways::Filter::new(move |(seat, _): (ways::Main<ServerSeat>, u32), _, _| {
+            *server_seat2.borrow_mut() = Some(seat);
+        }),
+    );
+    server.display.set_max_pending_outgoing_bytes(Some(16 * 1024));
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    manager.instantiate_exact::<wl_seat::WlSeat>(5).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let seat = server_seat.borrow_mut().take().unwrap();
+    let server_client = seat.as_ref().client().unwrap();
+    for _ in 0..EVENT_COUNT {
+        seat.name("a".repeat(100));
+        server.display.flush_clients(&mut ());
+    }
+
+    assert!(!server_client.alive());
+    let reason = server_client.kill_reason().unwrap();
+    assert_eq!(reason.kind(), std::io::ErrorKind::Other);
+}
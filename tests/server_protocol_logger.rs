@@ -0,0 +1,51 @@
+#![cfg(not(feature = "server_native"))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::debug::{LoggedMessage, MessageDirection};
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn server_protocol_logger() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(2, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let logged = Arc::new(Mutex::new(Vec::new()));
+    let logged2 = logged.clone();
+    server.display.set_protocol_logger(Some(Arc::new(move |msg: &LoggedMessage<'_>| {
+        logged2.lock().unwrap().push((
+            msg.direction,
+            format!("{}@{}.{}", msg.interface, msg.id, msg.message),
+            msg.args.len(),
+        ));
+    })));
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+    manager.instantiate_exact::<wayc::protocol::wl_output::WlOutput>(2).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let count = {
+        let logged = logged.lock().unwrap();
+        assert_eq!(
+            logged[0],
+            (MessageDirection::Dispatched, "wl_display@1.get_registry".into(), 1)
+        );
+        assert_eq!(logged[1], (MessageDirection::Dispatched, "wl_display@1.sync".into(), 1));
+        assert!(logged.contains(&(MessageDirection::Dispatched, "wl_registry@2.bind".into(), 4)));
+        assert!(logged.iter().any(|&(dir, ref name, _)| dir == MessageDirection::Sent
+            && name.starts_with("wl_callback@")
+            && name.ends_with(".done")));
+        logged.len()
+    };
+
+    // removing the logger stops the logging
+    assert!(server.display.set_protocol_logger(None).is_some());
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(logged.lock().unwrap().len(), count);
+}
@@ -0,0 +1,71 @@
+#![cfg(not(feature = "server_native"))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use wayc::protocol::wl_output::WlOutput as ClientOutput;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+#[test]
+fn stale_resource_does_not_alias_new_object() {
+    let mut server = TestServer::new();
+    server.display.set_stale_id_detection(true);
+
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let outputs2 = outputs.clone();
+    let released = Rc::new(Cell::new(0));
+    let released2 = released.clone();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            let released3 = released2.clone();
+            output.quick_assign(move |_, _, _| released3.set(released3.get() + 1));
+            outputs2.borrow_mut().push(output);
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let old_id = {
+        let output = manager.instantiate_exact::<ClientOutput>(3).unwrap();
+        let id = output.as_ref().id();
+        roundtrip(&mut client, &mut server).unwrap();
+        // destroy the output, once the server has acknowledged it its id can be reused
+        output.release();
+        id
+    };
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(released.get(), 1);
+
+    // the order in which ids are reused depends on the client implementation, bind
+    // enough outputs for one of them to get the old id
+    let new_outputs = [
+        manager.instantiate_exact::<ClientOutput>(3).unwrap(),
+        manager.instantiate_exact::<ClientOutput>(3).unwrap(),
+    ];
+    let output = new_outputs.iter().find(|o| o.as_ref().id() == old_id).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the old resource must not be able to reach the new object using the same id
+    let stale_hits = Rc::new(Cell::new(0));
+    let stale_hits2 = stale_hits.clone();
+    {
+        let outputs = outputs.borrow();
+        assert_eq!(outputs.len(), 3);
+        assert!(!outputs[0].as_ref().is_alive());
+        outputs[0].quick_assign(move |_, _, _| stale_hits2.set(stale_hits2.get() + 1));
+    }
+
+    output.release();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(released.get(), 2);
+    assert_eq!(stale_hits.get(), 0);
+}
@@ -0,0 +1,161 @@
+mod helpers;
+
+use helpers::{roundtrip_typed, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{wl_compositor, wl_surface};
+
+use wayc::protocol::wl_compositor::WlCompositor as ClientCompositor;
+
+#[derive(Default)]
+struct State {
+    binds: Vec<(String, u32)>,
+    surfaces: Vec<u32>,
+    destroyed: Vec<u32>,
+}
+
+struct SurfaceData {
+    serial: u32,
+}
+
+impl ways::GlobalDispatch<wl_compositor::WlCompositor, String> for State {
+    fn bind(
+        &mut self,
+        resource: ways::Main<wl_compositor::WlCompositor>,
+        version: u32,
+        name: &String,
+        dhandle: &ways::DisplayHandle<State>,
+    ) {
+        self.binds.push((name.clone(), version));
+        resource.assign_dispatch(dhandle, ());
+    }
+}
+
+impl ways::Dispatch<wl_compositor::WlCompositor> for State {
+    fn request(
+        &mut self,
+        _: ways::Main<wl_compositor::WlCompositor>,
+        request: wl_compositor::Request,
+        _: &(),
+        dhandle: &ways::DisplayHandle<State>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            let serial = self.surfaces.len() as u32;
+            id.assign_dispatch(dhandle, SurfaceData { serial });
+            self.surfaces.push(serial);
+        }
+    }
+}
+
+impl ways::Dispatch<wl_surface::WlSurface, SurfaceData> for State {
+    fn request(
+        &mut self,
+        _: ways::Main<wl_surface::WlSurface>,
+        _: wl_surface::Request,
+        _: &SurfaceData,
+        _: &ways::DisplayHandle<State>,
+    ) {
+    }
+
+    fn destroyed(&mut self, _: ways::Resource<wl_surface::WlSurface>, data: &SurfaceData) {
+        self.destroyed.push(data.serial);
+    }
+}
+
+#[test]
+fn typed_dispatch() {
+    let mut server = TestServer::<State>::new_typed();
+    server
+        .display
+        .create_typed_global::<wl_compositor::WlCompositor, _>(3, "compositor".to_owned());
+    let mut state = State::default();
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+
+    let compositor = manager.instantiate_exact::<ClientCompositor>(2).unwrap();
+    let first = compositor.create_surface();
+    compositor.create_surface();
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+
+    assert_eq!(state.binds, vec![("compositor".to_owned(), 2)]);
+    assert_eq!(state.surfaces, vec![0, 1]);
+    assert!(state.destroyed.is_empty());
+
+    // destruction by a destructor request
+    first.destroy();
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+    assert_eq!(state.destroyed, vec![0]);
+
+    // destruction by the disconnection of the client
+    ::std::mem::drop(manager);
+    ::std::mem::drop(compositor);
+    ::std::mem::drop(first);
+    ::std::mem::drop(client);
+    server.display.dispatch_typed(::std::time::Duration::from_millis(10), &mut state).unwrap();
+    server.display.flush_clients_typed(&mut state);
+    assert_eq!(state.destroyed, vec![0, 1]);
+}
+
+#[test]
+fn typed_user_data() {
+    let mut server = TestServer::<State>::new_typed();
+    // a typed global hidden to the client
+    server.display.create_typed_global_with_filter::<wl_compositor::WlCompositor, _, _>(
+        1,
+        "hidden".to_owned(),
+        |_| false,
+    );
+    let mut state = State::default();
+
+    let surfaces = ::std::rc::Rc::new(::std::cell::RefCell::new(Vec::new()));
+    let surfaces2 = surfaces.clone();
+    let filter_requests = ::std::rc::Rc::new(::std::cell::Cell::new(0));
+    let filter_requests2 = filter_requests.clone();
+    let dhandle = server.display.handle();
+    // a global handled by a filter on the same display, assigning its surfaces to the state
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<wl_compositor::WlCompositor>, u32), _, _| {
+                let surfaces = surfaces2.clone();
+                let filter_requests = filter_requests2.clone();
+                let dhandle = dhandle.clone();
+                compositor.quick_assign(move |_, request, _| {
+                    filter_requests.set(filter_requests.get() + 1);
+                    if let wl_compositor::Request::CreateSurface { id } = request {
+                        id.assign_dispatch(&dhandle, SurfaceData { serial: 42 });
+                        surfaces.borrow_mut().push(id);
+                    }
+                });
+            },
+        ),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+
+    assert_eq!(manager.list().len(), 1);
+    let compositor = manager.instantiate_exact::<ClientCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+
+    assert!(state.binds.is_empty());
+    assert_eq!(filter_requests.get(), 1);
+    let surfaces = surfaces.borrow();
+    assert_eq!(surfaces.len(), 1);
+    assert_eq!(surfaces[0].as_ref().user_data().get::<SurfaceData>().unwrap().serial, 42);
+
+    // the user data of a resource cannot be replaced
+    let dhandle = server.display.handle();
+    let reassign = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+        surfaces[0].assign_dispatch(&dhandle, SurfaceData { serial: 0 })
+    }));
+    assert!(reassign.is_err());
+    assert_eq!(surfaces[0].as_ref().user_data().get::<SurfaceData>().unwrap().serial, 42);
+
+    surface.destroy();
+    roundtrip_typed(&mut client, &mut server, &mut state).unwrap();
+    assert_eq!(state.destroyed, vec![42]);
+}
@@ -0,0 +1,100 @@
+extern crate wayland_client;
+extern crate wayland_commons;
+extern crate wayland_protocols;
+
+use std::env;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+
+use wayland_client::protocol::*;
+use wayland_commons::decode::InterfaceInfo;
+use wayland_commons::mitm::{Context, Mitm, MitmHook, Verdict};
+use wayland_commons::wire::{Argument, Message};
+use wayland_protocols::xdg_shell::client::*;
+
+// A proxy hiding some globals from its clients
+//
+// It listens on the `wayland-proxy` socket and forwards the connections to the compositor
+// the environment points to, hiding the globals whose interfaces are given as arguments:
+//
+//     filtering_proxy wl_seat wl_output &
+//     WAYLAND_DISPLAY=wayland-proxy some-client
+
+// The interfaces the proxy understands, the globals of other interfaces are always hidden
+fn interfaces() -> Vec<InterfaceInfo> {
+    vec![
+        InterfaceInfo::from_interface::<wl_display::WlDisplay>(),
+        InterfaceInfo::from_interface::<wl_registry::WlRegistry>(),
+        InterfaceInfo::from_interface::<wl_callback::WlCallback>(),
+        InterfaceInfo::from_interface::<wl_compositor::WlCompositor>(),
+        InterfaceInfo::from_interface::<wl_shm_pool::WlShmPool>(),
+        InterfaceInfo::from_interface::<wl_shm::WlShm>(),
+        InterfaceInfo::from_interface::<wl_buffer::WlBuffer>(),
+        InterfaceInfo::from_interface::<wl_data_offer::WlDataOffer>(),
+        InterfaceInfo::from_interface::<wl_data_source::WlDataSource>(),
+        InterfaceInfo::from_interface::<wl_data_device::WlDataDevice>(),
+        InterfaceInfo::from_interface::<wl_data_device_manager::WlDataDeviceManager>(),
+        InterfaceInfo::from_interface::<wl_shell::WlShell>(),
+        InterfaceInfo::from_interface::<wl_shell_surface::WlShellSurface>(),
+        InterfaceInfo::from_interface::<wl_surface::WlSurface>(),
+        InterfaceInfo::from_interface::<wl_seat::WlSeat>(),
+        InterfaceInfo::from_interface::<wl_pointer::WlPointer>(),
+        InterfaceInfo::from_interface::<wl_keyboard::WlKeyboard>(),
+        InterfaceInfo::from_interface::<wl_touch::WlTouch>(),
+        InterfaceInfo::from_interface::<wl_output::WlOutput>(),
+        InterfaceInfo::from_interface::<wl_region::WlRegion>(),
+        InterfaceInfo::from_interface::<wl_subcompositor::WlSubcompositor>(),
+        InterfaceInfo::from_interface::<wl_subsurface::WlSubsurface>(),
+        InterfaceInfo::from_interface::<xdg_wm_base::XdgWmBase>(),
+        InterfaceInfo::from_interface::<xdg_positioner::XdgPositioner>(),
+        InterfaceInfo::from_interface::<xdg_surface::XdgSurface>(),
+        InterfaceInfo::from_interface::<xdg_toplevel::XdgToplevel>(),
+        InterfaceInfo::from_interface::<xdg_popup::XdgPopup>(),
+    ]
+}
+
+struct HideGlobals {
+    hidden: Vec<String>,
+}
+
+impl MitmHook for HideGlobals {
+    fn event(&mut self, event: &mut Message, ctx: &mut Context<'_>) -> Verdict {
+        let is_global = ctx.interface(event.sender_id).map(|i| &i.name[..]) == Some("wl_registry")
+            && event.opcode == 0;
+        if let (true, Some(Argument::Str(interface))) = (is_global, event.args.get(1)) {
+            if self.hidden.iter().any(|hidden| interface.to_bytes() == hidden.as_bytes()) {
+                println!("Hiding global {:?}", interface);
+                // the proxy takes care of also hiding its removal and forbidding its binding
+                return Verdict::Drop;
+            }
+        }
+        Verdict::Forward
+    }
+}
+
+fn main() {
+    let hidden = env::args().skip(1).collect::<Vec<_>>();
+
+    let runtime_dir =
+        PathBuf::from(env::var_os("XDG_RUNTIME_DIR").expect("XDG_RUNTIME_DIR is not set"));
+    let upstream =
+        runtime_dir.join(env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into()));
+    let socket = runtime_dir.join("wayland-proxy");
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).unwrap();
+    println!("Listening on {}", socket.display());
+
+    for client in listener.incoming() {
+        let client = client.unwrap();
+        let server = UnixStream::connect(&upstream).unwrap();
+        let hook = HideGlobals { hidden: hidden.clone() };
+        thread::spawn(move || {
+            let mut mitm = Mitm::new(client, server, interfaces(), hook).unwrap();
+            match mitm.run() {
+                Ok(()) => println!("Connection closed"),
+                Err(e) => println!("Connection closed with an error: {}", e),
+            }
+        });
+    }
+}
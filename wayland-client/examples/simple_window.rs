@@ -5,7 +5,6 @@
 use std::{
     cmp::min,
     io::{BufWriter, Write},
-    os::unix::io::AsRawFd,
     process::exit,
     time::Instant,
 };
@@ -13,7 +12,7 @@ use std::{
 use wayland_client::{
     event_enum,
     protocol::{wl_compositor, wl_keyboard, wl_pointer, wl_seat, wl_shm},
-    Display, Filter, GlobalManager,
+    BorrowedFd, Display, Filter, GlobalManager,
 };
 use wayland_protocols::xdg_shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
 
@@ -87,10 +86,12 @@ fn main() {
     // The SHM allows us to share memory with the server, and create buffers
     // on this shared memory to paint our surfaces
     let shm = globals.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
-    let pool = shm.create_pool(
-        tmp.as_raw_fd(),            // RawFd to the tempfile serving as shared memory
-        (buf_x * buf_y * 4) as i32, // size in bytes of the shared memory (4 bytes per pixel)
-    );
+    let pool = shm
+        .create_pool(
+            BorrowedFd::new(&tmp),      // the tempfile serving as shared memory
+            (buf_x * buf_y * 4) as i32, // size in bytes of the shared memory (4 bytes per pixel)
+        )
+        .unwrap();
     let buffer = pool.create_buffer(
         0,                        // Start of the buffer in the pool
         buf_x as i32,             // width of the buffer in pixels
@@ -0,0 +1,172 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::rc::Rc;
+
+use nix::fcntl::{fcntl, FcntlArg};
+use nix::poll::{poll, PollFd, PollFlags};
+use tokio::io::unix::AsyncFd;
+
+use crate::{AnonymousObject, DispatchData, EventQueue, Main, RawEvent};
+
+/// A private duplicate of the connection fd, registered in the tokio reactor
+///
+/// Using a duplicate rather than the connection fd itself allows several event
+/// queues of the same connection to be awaited at the same time in a single
+/// runtime, as the reactor would otherwise refuse to register the same fd twice.
+pub(crate) struct ConnectionFd(RawFd);
+
+impl ConnectionFd {
+    fn new(fd: RawFd) -> io::Result<ConnectionFd> {
+        match fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0)) {
+            Ok(dup) => Ok(ConnectionFd(dup)),
+            Err(::nix::Error::Sys(errno)) => Err(errno.into()),
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl AsRawFd for ConnectionFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for ConnectionFd {
+    fn drop(&mut self) {
+        let _ = ::nix::unistd::close(self.0);
+    }
+}
+
+// the connection fd registered in the reactor, created with the first async dispatch
+pub(crate) type AsyncFdSlot = RefCell<Option<Rc<AsyncFd<ConnectionFd>>>>;
+
+/// Check whether the socket still has some data available for reading, without blocking
+fn has_pending_input(fd: RawFd) -> bool {
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+    match poll(&mut fds, 0) {
+        Ok(n) => n > 0,
+        // let the next read report the error
+        Err(_) => true,
+    }
+}
+
+/// Async versions of the dispatching methods
+///
+/// These methods are available with the `async` cargo feature and must be awaited
+/// from within a [tokio](https://tokio.rs) runtime with its IO driver enabled.
+///
+/// They drive the event queue using `Display::flush()`, `EventQueue::prepare_read()`,
+/// `ReadEventsGuard::read_events()` and `EventQueue::dispatch_pending()`, awaiting the
+/// readiness of the wayland socket instead of blocking on it. As a consequence they
+/// can be freely mixed with the other methods of the event queue, and with other
+/// threads reading the same connection.
+///
+/// The returned futures are not `Send`, as the `EventQueue` itself is not. They are
+/// cancel-safe: dropping one of them before completion cancels any pending read
+/// intention without losing events.
+///
+/// The wayland socket is registered in the reactor of the runtime the first time one of
+/// these methods is called, so all of them must then be awaited from this same runtime.
+impl EventQueue {
+    // the registration of the wayland socket in the reactor, reused by all the async dispatches
+    fn async_fd(&self) -> io::Result<Rc<AsyncFd<ConnectionFd>>> {
+        let mut slot = self.async_fd.borrow_mut();
+        if let Some(ref fd) = *slot {
+            return Ok(fd.clone());
+        }
+        let fd = Rc::new(AsyncFd::new(ConnectionFd::new(self.display().get_connection_fd())?)?);
+        *slot = Some(fd.clone());
+        Ok(fd)
+    }
+
+    /// Asynchronously dispatches events
+    ///
+    /// This is the async equivalent of `dispatch()`: pending requests are sent to the
+    /// server, and if no events were already in the internal buffer of this queue, it
+    /// waits until some are received and dispatches them.
+    ///
+    /// The provided `data` will be mutably accessible from all the callbacks, via the
+    /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
+    /// can just provide a `&mut ()` there.
+    ///
+    /// On success returns the number of dispatched events.
+    /// If an error is returned, your connection with the wayland compositor is probably lost.
+    /// You may want to check `Display::protocol_error()` to see if it was caused by a protocol error.
+    pub async fn dispatch_async<T: std::any::Any, F>(
+        &mut self,
+        data: &mut T,
+        mut fallback: F,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let fd = self.async_fd()?;
+        loop {
+            let dispatched = self.dispatch_pending(data, &mut fallback)?;
+            if dispatched > 0 {
+                return Ok(dispatched);
+            }
+
+            // send our pending requests, waiting for the socket to be writable if needed
+            loop {
+                match self.display().flush() {
+                    Ok(()) => break,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        fd.writable().await?.clear_ready();
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let guard = match self.prepare_read() {
+                Some(guard) => guard,
+                // events were queued in the meantime, dispatch them
+                None => continue,
+            };
+
+            let mut ready = fd.readable().await?;
+            match guard.read_events() {
+                Ok(()) => {
+                    if !has_pending_input(fd.get_ref().as_raw_fd()) {
+                        ready.clear_ready();
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready.clear_ready(),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Asynchronous roundtrip
+    ///
+    /// This is the async equivalent of `sync_roundtrip()`: it sends all pending requests
+    /// to the server and resolves once the server has processed all of them and the
+    /// events it sent in response have been dispatched.
+    ///
+    /// The provided `data` will be mutably accessible from all the callbacks, via the
+    /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
+    /// can just provide a `&mut ()` there.
+    ///
+    /// On success returns the number of dispatched events.
+    /// If an error is returned, your connection with the wayland compositor is probably lost.
+    /// You may want to check `Display::protocol_error()` to see if it was caused by a protocol error.
+    pub async fn sync_roundtrip_async<T: std::any::Any, F>(
+        &mut self,
+        data: &mut T,
+        mut fallback: F,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        self.display().attach(self.token()).sync().quick_assign(move |_, _, _| done2.set(true));
+
+        let mut dispatched = 0;
+        while !done.get() {
+            dispatched += self.dispatch_async(data, &mut fallback).await?;
+        }
+        Ok(dispatched)
+    }
+}
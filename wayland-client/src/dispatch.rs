@@ -0,0 +1,53 @@
+use crate::{Interface, Main, Proxy, QueueHandle};
+
+/// A state handling the events of the objects of an interface
+///
+/// This trait is implemented by the state of your application for each interface whose
+/// objects it handles. The objects are assigned to the state with `Main::assign_dispatch()`,
+/// and their events are then given to the `event()` method when their event queue is
+/// dispatched, with a mutable access to the state and the handle of the queue.
+///
+/// This is an alternative to assigning the objects to `Filter`s and recovering the state
+/// from the `DispatchData`. See the typed dispatching section of the `EventQueue`
+/// documentation.
+///
+/// ```no_run
+/// # extern crate wayland_client;
+/// use wayland_client::{Display, Dispatch, Main, QueueHandle};
+/// use wayland_client::protocol::wl_registry;
+///
+/// struct State {
+///     globals: Vec<(u32, String, u32)>,
+/// }
+///
+/// impl Dispatch<wl_registry::WlRegistry> for State {
+///     fn event(
+///         &mut self,
+///         _: Main<wl_registry::WlRegistry>,
+///         event: wl_registry::Event,
+///         _: &QueueHandle<State>,
+///     ) {
+///         match event {
+///             wl_registry::Event::Global { name, interface, version } => {
+///                 self.globals.push((name, interface, version))
+///             }
+///             wl_registry::Event::GlobalRemove { name } => {
+///                 self.globals.retain(|&(n, _, _)| n != name)
+///             }
+///             _ => {}
+///         }
+///     }
+/// }
+///
+/// # let display = Display::connect_to_env().unwrap();
+/// let mut event_queue = display.create_typed_event_queue::<State>();
+/// let attached_display = (*display).clone().attach(event_queue.token());
+/// attached_display.get_registry().assign_dispatch(&event_queue.handle());
+///
+/// let mut state = State { globals: Vec::new() };
+/// event_queue.sync_roundtrip_typed(&mut state).unwrap();
+/// ```
+pub trait Dispatch<I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>>: Sized + 'static {
+    /// Handle an event received by an object assigned to this state
+    fn event(&mut self, proxy: Main<I>, event: I::Event, qhandle: &QueueHandle<Self>);
+}
@@ -198,6 +198,14 @@ impl Display {
         EventQueue::new(evq_inner, self.clone())
     }
 
+    /// Create a new event queue dispatching events to a state of type `State`
+    ///
+    /// See the typed dispatching section of the `EventQueue` documentation.
+    pub fn create_typed_event_queue<State>(&self) -> EventQueue<State> {
+        let evq_inner = DisplayInner::create_event_queue(&self.inner);
+        EventQueue::new(evq_inner, self.clone())
+    }
+
     /// Retrieve the last protocol error if any occured
     ///
     /// If your client does not respect some part of a protocol it is using, the server
@@ -220,6 +228,77 @@ impl Display {
         self.inner.get_connection_fd()
     }
 
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set a hook capturing the raw traffic of this connection
+    ///
+    /// The hook will receive the exact bytes of all requests sent and events received
+    /// from now on, see the [`capture`](capture/index.html) module for details. A
+    /// `capture::CaptureWriter` can be used to save the traffic to a file. Passing
+    /// `None` stops the capture.
+    ///
+    /// Returns the previously set hook, if any.
+    ///
+    /// This method is only available with the rust implementation, as `libwayland-client.so`
+    /// does not give access to its socket.
+    pub fn set_capture_hook(
+        &self,
+        hook: Option<Box<dyn crate::capture::CaptureHook>>,
+    ) -> Option<Box<dyn crate::capture::CaptureHook>> {
+        self.inner.set_capture_hook(hook)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the logger receiving the protocol messages of this connection
+    ///
+    /// The logger will receive all requests sent and events dispatched from now on, see
+    /// the [`debug`](debug/index.html) module for the available loggers. Passing `None`
+    /// disables the logging.
+    ///
+    /// If the `WAYLAND_DEBUG` env variable is set to `1` or `client` when the connection
+    /// is created, a `debug::StderrLogger` is installed by default.
+    ///
+    /// Returns the previously set logger, if any.
+    ///
+    /// This method is only available with the rust implementation, as `libwayland-client.so`
+    /// does not allow to hook into its debug output.
+    pub fn set_protocol_logger(
+        &self,
+        logger: Option<Arc<dyn crate::debug::ProtocolLogger>>,
+    ) -> Option<Arc<dyn crate::debug::ProtocolLogger>> {
+        self.inner.set_protocol_logger(logger)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the size of the buffers of this connection
+    ///
+    /// The size is in bytes, and is the maximum size of the messages that can be sent and
+    /// received on this connection. It cannot be smaller than the default of 4096 bytes, and
+    /// a message cannot be larger than 65535 bytes in any case.
+    ///
+    /// Sending a request larger than this size is an error that puts the connection in an
+    /// inconsistent state: the dispatching methods of the `EventQueue`s will then fail with
+    /// an `io::Error` of kind `InvalidInput`, wrapping a `MessageTooLarge` error that names
+    /// the faulty request.
+    ///
+    /// This method is only available with the rust implementation, as this version of
+    /// `libwayland-client.so` does not allow to configure its buffers.
+    pub fn set_max_buffer_size(&self, size: usize) {
+        self.inner.set_max_buffer_size(size)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Enable or disable the detection of stale object ids
+    ///
+    /// Object ids are reused as soon as the server has acknowledged their destruction, so
+    /// an old `Proxy` or a late event may refer to an id that now belongs to a different
+    /// object. Such uses are always ignored, and when this detection is enabled they are
+    /// also reported on stderr. This is meant as a debugging tool and is disabled by default.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn set_stale_id_detection(&self, enabled: bool) {
+        self.inner.set_stale_id_detection(enabled)
+    }
+
     #[cfg(feature = "use_system_lib")]
     /// Create a Display and from an external display
     ///
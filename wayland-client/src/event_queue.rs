@@ -1,5 +1,12 @@
+use std::cell::{Cell, RefCell};
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{io, rc::Rc};
 
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::eventfd::{eventfd, EfdFlags};
+
 use crate::imp::EventQueueInner;
 use crate::{AnonymousObject, DispatchData, Display, Main, RawEvent};
 
@@ -97,12 +104,41 @@ use crate::{AnonymousObject, DispatchData, Display, Main, RawEvent};
 ///     // the file descriptor provided by the `get_connection_fd()` method.
 /// }
 /// ```
-pub struct EventQueue {
+///
+/// ## Typed dispatching
+///
+/// An event queue can also be generic over the state of your application, in which case it is
+/// created with `Display::create_typed_event_queue()`. The objects are then assigned to this
+/// state with `Main::assign_dispatch()`, given the [`QueueHandle`](struct.QueueHandle.html) of
+/// the queue, and their events are given to the [`Dispatch`](trait.Dispatch.html)
+/// implementation of the state for their interface when the queue is dispatched with
+/// `dispatch_typed()`, `dispatch_pending_typed()` or `sync_roundtrip_typed()`. The state these
+/// methods take being of the type of the queue, a mismatch is a compile-time error.
+///
+/// Objects assigned to a `Filter` can live on the same queue, the `DispatchData` given to
+/// them is then empty. The methods taking arbitrary dispatch data are only available on
+/// untyped queues.
+///
+/// ## Waking up a blocked dispatch
+///
+/// A thread blocked dispatching an event queue can be woken up from an other thread using
+/// the [`Waker`](struct.Waker.html) returned by `waker()`.
+pub struct EventQueue<State = ()> {
     // EventQueue is *not* Send
     pub(crate) inner: Rc<EventQueueInner>,
     display: Display,
+    wake: WakeSlot,
+    state: StateSlot<State>,
+    #[cfg(feature = "async")]
+    pub(crate) async_fd: crate::async_queue::AsyncFdSlot,
 }
 
+// the eventfd of the wakers of a queue, created with the first of them
+type WakeSlot = Rc<RefCell<Option<Arc<EventFd>>>>;
+
+// the state of a queue while it is being dispatched, null otherwise
+type StateSlot<State> = Rc<Cell<*mut State>>;
+
 /// A token representing this event queue
 ///
 /// This token can be cloned and is meant to allow easier
@@ -114,10 +150,238 @@ pub struct QueueToken {
     pub(crate) inner: Rc<EventQueueInner>,
 }
 
-impl EventQueue {
-    pub(crate) fn new(inner: EventQueueInner, display: Display) -> EventQueue {
-        EventQueue { inner: Rc::new(inner), display }
+/// A handle to a typed event queue
+///
+/// This handle can be cloned and is required to assign objects to the state of the queue
+/// with `Main::assign_dispatch()`, ensuring that their events are given to the state the
+/// queue is dispatched with. A reference to it is also given to the `Dispatch`
+/// implementations, allowing them to assign the objects they create.
+pub struct QueueHandle<State> {
+    pub(crate) inner: Rc<EventQueueInner>,
+    pub(crate) state: StateSlot<State>,
+}
+
+impl<State> Clone for QueueHandle<State> {
+    fn clone(&self) -> QueueHandle<State> {
+        QueueHandle { inner: self.inner.clone(), state: self.state.clone() }
+    }
+}
+
+impl<State> QueueHandle<State> {
+    /// Create a new token associated with this event queue
+    ///
+    /// See `QueueToken` documentation for its use.
+    pub fn token(&self) -> QueueToken {
+        QueueToken { inner: self.inner.clone() }
+    }
+}
+
+impl<State> EventQueue<State> {
+    pub(crate) fn new(inner: EventQueueInner, display: Display) -> EventQueue<State> {
+        EventQueue {
+            inner: Rc::new(inner),
+            display,
+            wake: Rc::new(RefCell::new(None)),
+            state: Rc::new(Cell::new(std::ptr::null_mut())),
+            #[cfg(feature = "async")]
+            async_fd: RefCell::new(None),
+        }
+    }
+
+    // make `state` accessible to the objects assigned to it for the duration of `f`
+    fn with_state<T, F: FnOnce() -> T>(&self, state: &mut State, f: F) -> T {
+        struct Reset<'a, S>(&'a Cell<*mut S>);
+        impl<'a, S> Drop for Reset<'a, S> {
+            fn drop(&mut self) {
+                self.0.set(std::ptr::null_mut());
+            }
+        }
+        self.state.set(state);
+        let _reset = Reset(&self.state);
+        f()
+    }
+
+    fn dispatch_until<T: std::any::Any, F>(
+        &self,
+        state: &mut State,
+        data: &mut T,
+        fallback: F,
+        deadline: Option<Instant>,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let mut data = DispatchData::wrap(data);
+        let wake = self.wake.borrow();
+        self.with_state(state, || match (deadline, wake.as_ref()) {
+            (None, None) => self.inner.dispatch(data.reborrow(), fallback),
+            (deadline, wake) => {
+                self.inner.dispatch_until(data.reborrow(), fallback, deadline, wake.map(|w| &**w))
+            }
+        })
+    }
+
+    fn dispatch_pending_with<T: std::any::Any, F>(
+        &self,
+        state: &mut State,
+        data: &mut T,
+        fallback: F,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let mut data = DispatchData::wrap(data);
+        self.with_state(state, || self.inner.dispatch_pending(data.reborrow(), fallback))
+    }
+
+    fn sync_roundtrip_until<T: std::any::Any, F>(
+        &self,
+        state: &mut State,
+        data: &mut T,
+        mut fallback: F,
+        deadline: Option<Instant>,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let mut data = DispatchData::wrap(data);
+        let wake = self.wake.borrow();
+        if deadline.is_none() && wake.is_none() {
+            return self.with_state(state, || self.inner.sync_roundtrip(data.reborrow(), fallback));
+        }
+
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        self.display().attach(self.token()).sync().quick_assign(move |_, _, _| done2.set(true));
+
+        self.with_state(state, || {
+            let mut dispatched = 0;
+            while !done.get() {
+                dispatched += self.inner.dispatch_until(
+                    data.reborrow(),
+                    &mut fallback,
+                    deadline,
+                    wake.as_ref().map(|w| &**w),
+                )?;
+            }
+            Ok(dispatched)
+        })
+    }
+
+    /// Create a new token associated with this event queue
+    ///
+    /// See `QueueToken` documentation for its use.
+    pub fn token(&self) -> QueueToken {
+        QueueToken { inner: self.inner.clone() }
+    }
+
+    /// Create a new handle to this event queue
+    ///
+    /// See `QueueHandle` documentation for its use.
+    pub fn handle(&self) -> QueueHandle<State> {
+        QueueHandle { inner: self.inner.clone(), state: self.state.clone() }
+    }
+
+    /// Prepare an concurrent read
+    ///
+    /// Will declare your intention to read events from the server socket.
+    ///
+    /// Will return `None` if there are still some events awaiting dispatch on this EventIterator.
+    /// In this case, you need to call `dispatch_pending()` before calling this method again.
+    ///
+    /// The guard can then be used by two means:
+    ///
+    ///  - Calling its `cancel()` method (or letting it go out of scope): the read intention will
+    ///    be cancelled
+    ///  - Calling its `read_events()` method: will block until all existing guards are destroyed
+    ///    by one of these methods, then events will be read and all blocked `read_events()` calls
+    ///    will return.
+    ///
+    /// This call will otherwise not block on the server socket if it is empty, and return
+    /// an io error `WouldBlock` in such cases.
+    pub fn prepare_read(&self) -> Option<ReadEventsGuard> {
+        match self.inner.prepare_read() {
+            Ok(()) => Some(ReadEventsGuard {
+                inner: self.inner.clone(),
+                wake: self.wake.clone(),
+                done: false,
+            }),
+            Err(()) => None,
+        }
+    }
+
+    /// Get a handle to wake up a thread blocked dispatching this event queue
+    ///
+    /// The returned `Waker` can be cloned and sent to other threads. Waking it up makes the
+    /// dispatching methods of this queue and the `ReadEventsGuard`s it creates return a
+    /// `WokenUp` error rather than waiting for events. See `Waker::wake()` for details.
+    ///
+    /// All the wakers of a queue share an eventfd, created the first time this method is called.
+    pub fn waker(&self) -> io::Result<Waker> {
+        let mut wake = self.wake.borrow_mut();
+        let fd = match *wake {
+            Some(ref fd) => fd.clone(),
+            None => wake.get_or_insert(Arc::new(EventFd::new()?)).clone(),
+        };
+        Ok(Waker { fd })
+    }
+
+    /// Access the `Display` of the connection
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// Dispatches events from the internal buffer to the state
+    ///
+    /// Behaves like `EventQueue::dispatch()`, the events of the objects assigned with
+    /// `Main::assign_dispatch()` being given to the `Dispatch` implementations of `state`.
+    /// The events of the objects that are not assigned to anything are discarded.
+    pub fn dispatch_typed(&mut self, state: &mut State) -> io::Result<u32> {
+        self.dispatch_until(state, &mut (), |_, _, _| {}, None)
+    }
+
+    /// Dispatches pending events from the internal buffer to the state
+    ///
+    /// Behaves like `EventQueue::dispatch_pending()`, see `dispatch_typed()` for how the
+    /// events are dispatched.
+    pub fn dispatch_pending_typed(&mut self, state: &mut State) -> io::Result<u32> {
+        self.dispatch_pending_with(state, &mut (), |_, _, _| {})
+    }
+
+    /// Synchronous roundtrip dispatching the events to the state
+    ///
+    /// Behaves like `EventQueue::sync_roundtrip()`, see `dispatch_typed()` for how the
+    /// events are dispatched.
+    pub fn sync_roundtrip_typed(&mut self, state: &mut State) -> io::Result<u32> {
+        self.sync_roundtrip_until(state, &mut (), |_, _, _| {}, None)
     }
+
+    /// Dispatches events to the state, waiting for them at most `timeout`
+    ///
+    /// Behaves like `EventQueue::dispatch_timeout()`, see `dispatch_typed()` for how the
+    /// events are dispatched.
+    pub fn dispatch_timeout_typed(
+        &mut self,
+        timeout: Duration,
+        state: &mut State,
+    ) -> io::Result<u32> {
+        self.dispatch_until(state, &mut (), |_, _, _| {}, Some(Instant::now() + timeout))
+    }
+
+    /// Synchronous roundtrip dispatching the events to the state, waiting at most `timeout`
+    ///
+    /// Behaves like `EventQueue::sync_roundtrip_timeout()`, see `dispatch_typed()` for how
+    /// the events are dispatched.
+    pub fn sync_roundtrip_timeout_typed(
+        &mut self,
+        timeout: Duration,
+        state: &mut State,
+    ) -> io::Result<u32> {
+        self.sync_roundtrip_until(state, &mut (), |_, _, _| {}, Some(Instant::now() + timeout))
+    }
+}
+
+impl EventQueue {
     /// Dispatches events from the internal buffer.
     ///
     /// Dispatches all events to their appropriate filters.
@@ -130,14 +394,15 @@ impl EventQueue {
     /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
     /// can just provide a `&mut ()` there.
     ///
+    /// If it is woken up by a `Waker` while waiting for events, a `WokenUp` error is returned.
+    ///
     /// If an error is returned, your connection with the wayland compositor is probably lost.
     /// You may want to check `Display::protocol_error()` to see if it was caused by a protocol error.
     pub fn dispatch<T: std::any::Any, F>(&mut self, data: &mut T, fallback: F) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        let mut data = DispatchData::wrap(data);
-        self.inner.dispatch(data.reborrow(), fallback)
+        self.dispatch_until(&mut (), data, fallback, None)
     }
 
     /// Dispatches pending events from the internal buffer.
@@ -160,8 +425,7 @@ impl EventQueue {
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        let mut data = DispatchData::wrap(data);
-        self.inner.dispatch_pending(data.reborrow(), fallback)
+        self.dispatch_pending_with(&mut (), data, fallback)
     }
 
     /// Synchronous roundtrip
@@ -176,6 +440,9 @@ impl EventQueue {
     /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
     /// can just provide a `&mut ()` there.
     ///
+    /// If it is woken up by a `Waker` while waiting for the server, a `WokenUp` error is
+    /// returned.
+    ///
     /// On success returns the number of dispatched events.
     /// If an error is returned, your connection with the wayland compositor is probably lost.
     /// You may want to check `Display::protocol_error()` to see if it was caused by a protocol error.
@@ -187,44 +454,41 @@ impl EventQueue {
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        let mut data = DispatchData::wrap(data);
-        self.inner.sync_roundtrip(data.reborrow(), fallback)
+        self.sync_roundtrip_until(&mut (), data, fallback, None)
     }
 
-    /// Create a new token associated with this event queue
+    /// Dispatches events from the internal buffer, waiting for them at most `timeout`
     ///
-    /// See `QueueToken` documentation for its use.
-    pub fn token(&self) -> QueueToken {
-        QueueToken { inner: self.inner.clone() }
+    /// Behaves like `dispatch()`, except that if no events are received before the timeout
+    /// expires, an io error `TimedOut` is returned. With the system library, reading the
+    /// events still waits for the other threads that prepared a read, as `dispatch()` does.
+    pub fn dispatch_timeout<T: std::any::Any, F>(
+        &mut self,
+        timeout: Duration,
+        data: &mut T,
+        fallback: F,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        self.dispatch_until(&mut (), data, fallback, Some(Instant::now() + timeout))
     }
 
-    /// Prepare an concurrent read
-    ///
-    /// Will declare your intention to read events from the server socket.
-    ///
-    /// Will return `None` if there are still some events awaiting dispatch on this EventIterator.
-    /// In this case, you need to call `dispatch_pending()` before calling this method again.
-    ///
-    /// The guard can then be used by two means:
+    /// Synchronous roundtrip, waiting for the server at most `timeout`
     ///
-    ///  - Calling its `cancel()` method (or letting it go out of scope): the read intention will
-    ///    be cancelled
-    ///  - Calling its `read_events()` method: will block until all existing guards are destroyed
-    ///    by one of these methods, then events will be read and all blocked `read_events()` calls
-    ///    will return.
-    ///
-    /// This call will otherwise not block on the server socket if it is empty, and return
-    /// an io error `WouldBlock` in such cases.
-    pub fn prepare_read(&self) -> Option<ReadEventsGuard> {
-        match self.inner.prepare_read() {
-            Ok(()) => Some(ReadEventsGuard { inner: self.inner.clone(), done: false }),
-            Err(()) => None,
-        }
-    }
-
-    /// Access the `Display` of the connection
-    pub fn display(&self) -> &Display {
-        &self.display
+    /// Behaves like `sync_roundtrip()`, except that if the server has not answered before the
+    /// timeout expires, an io error `TimedOut` is returned. The events received in the meantime
+    /// are dispatched.
+    pub fn sync_roundtrip_timeout<T: std::any::Any, F>(
+        &mut self,
+        timeout: Duration,
+        data: &mut T,
+        fallback: F,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        self.sync_roundtrip_until(&mut (), data, fallback, Some(Instant::now() + timeout))
     }
 }
 
@@ -233,6 +497,7 @@ impl EventQueue {
 /// See `EventQueue::prepare_read()` for details about its use.
 pub struct ReadEventsGuard {
     inner: Rc<EventQueueInner>,
+    wake: WakeSlot,
     done: bool,
 }
 
@@ -241,11 +506,33 @@ impl ReadEventsGuard {
     ///
     /// Reads events from the server socket. If other `ReadEventsGuard` exists, will block
     /// until they are all consumed or destroyed.
+    ///
+    /// If a `Waker` of the event queue has been woken up, the read is cancelled and a
+    /// `WokenUp` error is returned. The wait for the other guards can however not be
+    /// interrupted with the system library.
     pub fn read_events(mut self) -> io::Result<()> {
         self.done = true;
+        if let Some(ref wake) = *self.wake.borrow() {
+            if wake.take() {
+                self.inner.cancel_read();
+                return Err(woken_up());
+            }
+        }
         self.inner.read_events()
     }
 
+    /// Read events, waiting for them at most `timeout`
+    ///
+    /// Behaves like `read_events()`, except that if no events are available on the server
+    /// socket before the timeout expires, the read is cancelled and an io error `TimedOut`
+    /// is returned. It can be woken up by a `Waker` of the event queue while waiting, in which
+    /// case a `WokenUp` error is returned.
+    pub fn read_events_timeout(mut self, timeout: Duration) -> io::Result<()> {
+        self.done = true;
+        let wake = self.wake.borrow();
+        self.inner.read_events_until(Some(Instant::now() + timeout), wake.as_ref().map(|w| &**w))
+    }
+
     /// Cancel the read
     ///
     /// Will cancel the read intention associated with this guard. Never blocks.
@@ -263,3 +550,129 @@ impl Drop for ReadEventsGuard {
         }
     }
 }
+
+/// A handle to wake up a thread blocked dispatching an event queue
+///
+/// It is obtained with `EventQueue::waker()`, and can be cloned and sent to other threads.
+#[derive(Clone, Debug)]
+pub struct Waker {
+    fd: Arc<EventFd>,
+}
+
+impl Waker {
+    /// Wake up the thread dispatching the event queue
+    ///
+    /// The thread currently waiting for events in one of the dispatching methods of the queue,
+    /// or in `ReadEventsGuard::read_events_timeout()`, returns a `WokenUp` error. If
+    /// no thread is waiting, the next wait returns immediately instead, as does the next
+    /// `ReadEventsGuard::read_events()`. Waking up several times before a wait only interrupts
+    /// it once.
+    ///
+    /// The events that are already pending are dispatched without waiting, and so without
+    /// consuming the wake up.
+    pub fn wake(&self) {
+        // the counter of the eventfd can not overflow in practice, and an error would mean
+        // it is already readable anyway
+        let _ = nix::unistd::write(self.fd.0, &1u64.to_ne_bytes());
+    }
+}
+
+/// An eventfd, closed when dropped
+#[derive(Debug)]
+pub(crate) struct EventFd(RawFd);
+
+impl EventFd {
+    fn new() -> io::Result<EventFd> {
+        match eventfd(0, EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK) {
+            Ok(fd) => Ok(EventFd(fd)),
+            Err(::nix::Error::Sys(e)) => Err(e.into()),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Reset the eventfd, returns whether it was woken up
+    fn take(&self) -> bool {
+        let mut buf = [0u8; 8];
+        nix::unistd::read(self.0, &mut buf).is_ok()
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.0);
+    }
+}
+
+/// The error returned when waiting for events is interrupted by a `Waker`
+///
+/// It is returned wrapped in an io error of kind `Other`, distinct from the `Interrupted`
+/// errors caused by signals. Use `WokenUp::is_woken_up()` to recognize it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WokenUp;
+
+impl WokenUp {
+    /// Whether an io error returned by an event queue was caused by a `Waker`
+    pub fn is_woken_up(error: &io::Error) -> bool {
+        error.get_ref().map(|e| e.is::<WokenUp>()).unwrap_or(false)
+    }
+}
+
+impl std::error::Error for WokenUp {}
+
+impl std::fmt::Display for WokenUp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("The event queue was woken up by a Waker.")
+    }
+}
+
+fn woken_up() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, WokenUp)
+}
+
+/// Wait until a file descriptor is ready for given events, or until the deadline
+///
+/// Returns an io error `TimedOut` if the deadline is reached first, and a `WokenUp` error
+/// if the eventfd of a `Waker` is woken up first, resetting it.
+pub(crate) fn wait_for_fd(
+    fd: RawFd,
+    flags: PollFlags,
+    deadline: Option<Instant>,
+    wake: Option<&EventFd>,
+) -> io::Result<()> {
+    loop {
+        let timeout = match deadline {
+            Some(deadline) => {
+                // wait at most a day at once, the loop goes on until the deadline
+                let remaining = deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(Duration::from_secs(24 * 60 * 60));
+                // round up, so that poll() does not return before the deadline
+                let round_up = remaining.subsec_nanos() > remaining.subsec_millis() * 1_000_000;
+                remaining.as_millis() as i32 + round_up as i32
+            }
+            None => -1,
+        };
+        let mut fds = [
+            PollFd::new(fd, flags),
+            PollFd::new(wake.map(|w| w.0).unwrap_or(-1), PollFlags::POLLIN),
+        ];
+        match poll(&mut fds, timeout) {
+            Ok(_) if fds[1].revents().map(|r| !r.is_empty()).unwrap_or(false) => {
+                if wake.map(EventFd::take).unwrap_or(false) {
+                    return Err(woken_up());
+                }
+            }
+            Ok(0) => {
+                if timeout == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "timed out waiting for the wayland socket",
+                    ));
+                }
+            }
+            Ok(_) => return Ok(()),
+            Err(::nix::Error::Sys(e)) => return Err(e.into()),
+            Err(_) => unreachable!(),
+        }
+    }
+}
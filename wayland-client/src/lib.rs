@@ -58,6 +58,14 @@
 //! If an object is not assigned to any `Filter`, its events will instead be delivered to the
 //! fallback closure given to its event queue when dispatching it.
 //!
+//! ### Typed dispatching
+//!
+//! Alternatively, the state of your application can implement the `Dispatch<I>` trait for the
+//! interfaces whose events it handles, and your objects can be assigned to it with
+//! `Main::assign_dispatch()`, given the handle of an `EventQueue<State>` created with
+//! `Display::create_typed_event_queue()`. Their events are then given to the state when this
+//! queue is dispatched. Both models can be used on the same event queue.
+//!
 //! ## Event Queues
 //!
 //! The Wayland client machinery provides the possibility to have one or more event queues
@@ -86,6 +94,22 @@
 //! When this is done, the library will be loaded a runtime rather than directly linked. And trying
 //! to create a `Display` on a system that does not have this library will return a `NoWaylandLib`
 //! error.
+//!
+//! ## Async integration
+//!
+//! If you activate the `async` cargo feature, `EventQueue` gains the `dispatch_async()` and
+//! `sync_roundtrip_async()` methods, which await the readiness of the wayland socket using
+//! [tokio](https://tokio.rs) instead of blocking the thread. They are available with both the
+//! rust implementation and `libwayland-client.so`.
+//!
+//! ## Serialization
+//!
+//! If you activate the `serde` cargo feature, the `Request` and `Event` enums of the `protocol`
+//! module implement serde's `Serialize` trait, objects being serialized by their id, and the
+//! other enums implement `Serialize` and `Deserialize` by the names of their entries. This can be
+//! used to record the messages of a connection. As objects cannot be recreated from their id, the
+//! recorded messages are deserialized into their raw form by the `deserialize_raw()` method of
+//! these enums.
 
 #![warn(missing_docs)]
 
@@ -98,20 +122,26 @@ extern crate downcast_rs as downcast;
 #[cfg_attr(feature = "use_system_lib", macro_use)]
 extern crate wayland_sys;
 
+#[cfg(feature = "async")]
+mod async_queue;
+mod dispatch;
 mod display;
 mod event_queue;
 mod globals;
 mod proxy;
 
 pub use anonymous_object::AnonymousObject;
+pub use dispatch::Dispatch;
 pub use display::{ConnectError, Display, ProtocolError};
-pub use event_queue::{EventQueue, QueueToken, ReadEventsGuard};
+pub use event_queue::{EventQueue, QueueHandle, QueueToken, ReadEventsGuard, Waker, WokenUp};
 pub use globals::{GlobalError, GlobalEvent, GlobalImplementor, GlobalManager};
 pub use imp::ProxyMap;
 pub use proxy::{Attached, Main, Proxy};
+pub use wayland_commons::{capture, debug};
 pub use wayland_commons::{
     filter::{DispatchData, Filter},
     user_data::UserData,
+    wire::{BorrowedFd, MessageTooLarge, OwnedFd},
     Interface, MessageGroup, NoMessage,
 };
 
@@ -140,7 +170,9 @@ pub mod protocol {
     pub(crate) use crate::{AnonymousObject, Attached, Main, Proxy, ProxyMap};
     pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
     pub(crate) use wayland_commons::smallvec;
-    pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+    pub(crate) use wayland_commons::wire::{
+        Argument, ArgumentType, BorrowedFd, Message, MessageDesc, OwnedFd,
+    };
     pub(crate) use wayland_commons::{Interface, MessageGroup};
     pub(crate) use wayland_sys as sys;
     include!(concat!(env!("OUT_DIR"), "/wayland_api.rs"));
@@ -209,8 +241,8 @@ pub enum Argument {
     NewId(Option<Main<AnonymousObject>>),
     /// Vec<u8>
     Array(Option<Vec<u8>>),
-    /// RawFd
-    Fd(std::os::unix::io::RawFd),
+    /// OwnedFd
+    Fd(OwnedFd),
 }
 
 /// An generic event
@@ -1,7 +1,11 @@
 use std::cell::RefCell;
 use std::io;
 use std::sync::Arc;
+use std::time::Instant;
 
+use nix::poll::PollFlags;
+
+use crate::event_queue::{wait_for_fd, EventFd};
 use crate::{AnonymousObject, DispatchData, Main, RawEvent};
 use wayland_sys::client::*;
 
@@ -55,6 +59,66 @@ impl EventQueueInner {
         })
     }
 
+    /// Dispatch, waiting for events at most until the deadline or until woken up
+    ///
+    /// This follows `wl_display_dispatch_queue()`, polling the socket with a timeout.
+    pub(crate) fn dispatch_until<F>(
+        &self,
+        data: DispatchData,
+        fallback: F,
+        deadline: Option<Instant>,
+        wake: Option<&EventFd>,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        with_dispatch_meta(fallback, data, || {
+            // don't read events if there are some pending
+            if self.prepare_read().is_ok() {
+                let fd = self.inner.get_connection_fd();
+                // flush the outgoing socket, EPIPE is ignored so that we can read the
+                // protocol error
+                loop {
+                    let ret = unsafe {
+                        ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.inner.ptr())
+                    };
+                    if ret >= 0 {
+                        break;
+                    }
+                    let err = io::Error::last_os_error();
+                    let waited = match err.raw_os_error() {
+                        Some(libc::EAGAIN) => wait_for_fd(fd, PollFlags::POLLOUT, deadline, wake),
+                        Some(libc::EPIPE) => break,
+                        _ => Err(err),
+                    };
+                    if let Err(e) = waited {
+                        self.cancel_read();
+                        return Err(e);
+                    }
+                }
+                // wait for incoming messages to arrive
+                if let Err(e) = wait_for_fd(fd, PollFlags::POLLIN, deadline, wake) {
+                    self.cancel_read();
+                    return Err(e);
+                }
+                self.read_events()?;
+            }
+            let ret = unsafe {
+                ffi_dispatch!(
+                    WAYLAND_CLIENT_HANDLE,
+                    wl_display_dispatch_queue_pending,
+                    self.inner.ptr(),
+                    self.wlevq
+                )
+            };
+            if ret >= 0 {
+                Ok(ret as u32)
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        })
+    }
+
     pub(crate) fn dispatch_pending<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
@@ -124,6 +188,20 @@ impl EventQueueInner {
         }
     }
 
+    pub(crate) fn read_events_until(
+        &self,
+        deadline: Option<Instant>,
+        wake: Option<&EventFd>,
+    ) -> io::Result<()> {
+        if let Err(e) =
+            wait_for_fd(self.inner.get_connection_fd(), PollFlags::POLLIN, deadline, wake)
+        {
+            self.cancel_read();
+            return Err(e);
+        }
+        self.read_events()
+    }
+
     pub(crate) fn cancel_read(&self) {
         unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_cancel_read, self.inner.ptr()) }
     }
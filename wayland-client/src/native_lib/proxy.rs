@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::os::raw::{c_int, c_void};
+use std::os::unix::io::FromRawFd;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
 
@@ -231,6 +232,16 @@ impl ProxyInner {
         self.wrapping = Some(wrapper_ptr);
     }
 
+    pub(crate) fn set_queue(&self, queue: &EventQueueInner) {
+        if self.is_external() || !self.is_alive() {
+            return;
+        }
+
+        unsafe {
+            queue.assign_proxy(self.ptr);
+        }
+    }
+
     pub(crate) fn c_ptr(&self) -> *mut wl_proxy {
         self.wrapping.unwrap_or(self.ptr)
     }
@@ -497,7 +508,7 @@ unsafe fn parse_raw_event<I: Interface>(opcode: u32, args: *const wl_argument) -
                     ))
                 }
             }
-            ArgumentType::Fd => crate::Argument::Fd(a.h),
+            ArgumentType::Fd => crate::Argument::Fd(FromRawFd::from_raw_fd(a.h)),
             ArgumentType::Object => {
                 if a.o.is_null() {
                     crate::Argument::Object(None)
@@ -7,7 +7,8 @@ use wayland_commons::Interface;
 
 use wayland_sys::client::*;
 
-use crate::event_queue::QueueToken;
+use crate::event_queue::{QueueHandle, QueueToken};
+use crate::Dispatch;
 
 use crate::imp::ProxyInner;
 
@@ -272,6 +273,37 @@ where
     {
         self.assign(Filter::new(move |(proxy, event), _, data| f(proxy, event, data)))
     }
+
+    /// Assign this object to the state of a typed event queue
+    ///
+    /// The object is moved to the event queue of `qhandle`, and all future events received
+    /// by this object will be delivered to the `Dispatch<I>` implementation of `State`, the
+    /// state this queue is dispatched with by methods like `EventQueue::dispatch_typed()`.
+    pub fn assign_dispatch<State>(&self, qhandle: &QueueHandle<State>)
+    where
+        I: Sync,
+        State: Dispatch<I>,
+        I::Event: MessageGroup<Map = crate::ProxyMap>,
+    {
+        self.inner.inner.as_ref().inner.set_queue(&qhandle.inner);
+        let qhandle = qhandle.clone();
+        self.assign(Filter::new(move |(proxy, event): (Main<I>, I::Event), _, _| {
+            // the state is only set while its queue is dispatched, and is taken out of the
+            // queue while it is in use so that it is never accessed twice
+            let state = qhandle.state.replace(std::ptr::null_mut());
+            if state.is_null() {
+                eprintln!(
+                    "[wayland-client] Event for object {}@{} dispatched outside of the typed queue it \
+                     is assigned to, ignoring.",
+                    I::NAME,
+                    proxy.as_ref().id()
+                );
+                return;
+            }
+            unsafe { (*state).event(proxy, event, &qhandle) };
+            qhandle.state.set(state);
+        }));
+    }
 }
 
 impl Main<AnonymousObject> {
@@ -1,15 +1,17 @@
 use std::cell::RefCell;
 use std::os::unix::io::{FromRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use nix::Result as NixResult;
 
 use wayland_commons::map::{Object, ObjectMap, SERVER_ID_LIMIT};
 use wayland_commons::socket::{BufferedSocket, Socket};
-use wayland_commons::wire::{Argument, ArgumentType, Message, MessageParseError};
+use wayland_commons::wire::{Argument, ArgumentType, Message, MessageParseError, MessageTooLarge};
 
 use super::proxy::ObjectMeta;
 use super::queues::QueueBuffer;
+use super::SharedLogger;
 
 use crate::ProtocolError;
 
@@ -17,6 +19,7 @@ use crate::ProtocolError;
 pub(crate) enum Error {
     Protocol(ProtocolError),
     Parse(MessageParseError),
+    TooLarge(MessageTooLarge),
     Nix(::nix::Error),
 }
 
@@ -25,6 +28,8 @@ pub(crate) struct Connection {
     pub(crate) map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
     pub(crate) last_error: Arc<Mutex<Option<Error>>>,
     pub(crate) display_buffer: QueueBuffer,
+    pub(crate) logger: SharedLogger,
+    pub(crate) stale_id_detection: Arc<AtomicBool>,
 }
 
 impl Connection {
@@ -41,10 +46,16 @@ impl Connection {
             map: Arc::new(Mutex::new(map)),
             last_error: Arc::new(Mutex::new(None)),
             display_buffer,
+            logger: Arc::new(Mutex::new(None)),
+            stale_id_detection: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub(crate) fn write_message(&mut self, msg: &Message) -> NixResult<()> {
+    pub(crate) fn stale_id_detection(&self) -> bool {
+        self.stale_id_detection.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn write_message(&mut self, msg: Message) -> NixResult<()> {
         self.socket.write_message(msg)
     }
 
@@ -91,12 +102,12 @@ impl Connection {
 
                 // dispatch the message to the proper object
                 let mut map = map.borrow_mut();
-                let object = map.find(msg.sender_id);
+                let object = map.find_with_generation(msg.sender_id);
 
                 // create a new object if applicable
                 if let Some((mut child, dead_parent)) = object
                     .as_ref()
-                    .and_then(|o| o.event_child(msg.opcode).map(|c| (c, o.meta.client_destroyed)))
+                    .and_then(|&(ref o, _)| o.event_child(msg.opcode).map(|c| (c, o.meta.client_destroyed)))
                 {
                     let new_id = msg
                         .args
@@ -138,18 +149,13 @@ impl Connection {
 
                 // send the message to the appropriate pending queue
                 match object {
-                    Some(Object { meta: ObjectMeta { client_destroyed: true, .. }, .. }) | None => {
+                    Some((Object { meta: ObjectMeta { client_destroyed: true, .. }, .. }, _)) | None => {
                         // this is a message sent to a destroyed object
-                        // to avoid dying because of races, we just consume it into void
-                        // closing any associated FDs
-                        for a in msg.args {
-                            if let Argument::Fd(fd) = a {
-                                let _ = ::nix::unistd::close(fd);
-                            }
-                        }
+                        // to avoid dying because of races, we just consume it into void,
+                        // closing any associated FDs as it is dropped
                     }
-                    Some(obj) => {
-                        obj.meta.buffer.lock().unwrap().push_back(msg);
+                    Some((obj, generation)) => {
+                        obj.meta.buffer.lock().unwrap().push_back((msg, generation));
                     }
                 };
 
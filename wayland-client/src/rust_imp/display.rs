@@ -1,9 +1,9 @@
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 
-use wayland_commons::debug;
+use wayland_commons::capture::CaptureHook;
+use wayland_commons::debug::{ProtocolLogger, StderrLogger};
 use wayland_commons::map::{Object, ObjectMap};
 use wayland_commons::wire::Message;
 use wayland_commons::MessageGroup;
@@ -14,7 +14,7 @@ use crate::{ConnectError, ProtocolError, Proxy};
 
 use super::connection::{Connection, Error as CxError};
 use super::proxy::{ObjectMeta, ProxyInner};
-use super::{Dispatched, EventQueueInner, ProxyMap, WAYLAND_DEBUG};
+use super::{Dispatched, EventQueueInner, ProxyMap};
 
 pub(crate) struct DisplayInner {
     connection: Arc<Mutex<Connection>>,
@@ -23,19 +23,17 @@ pub(crate) struct DisplayInner {
 
 impl DisplayInner {
     pub unsafe fn from_fd(fd: RawFd) -> Result<Arc<DisplayInner>, ConnectError> {
-        if let Some(value) = std::env::var_os("WAYLAND_DEBUG") {
-            // Follow libwayland-client and enable debug log only on `1` and `client` values.
-            if value == "1" || value == "client" {
-                // Toggle debug log.
-                WAYLAND_DEBUG.store(true, Ordering::Relaxed);
-            }
-        }
-
         // The special buffer for display events
         let buffer = super::queues::create_queue_buffer();
         let display_object = Object::from_interface::<WlDisplay>(1, ObjectMeta::new(buffer));
         let (connection, map) = {
-            let c = Connection::new(fd, display_object);
+            let mut c = Connection::new(fd, display_object);
+            if let Some(value) = std::env::var_os("WAYLAND_DEBUG") {
+                // Follow libwayland-client and enable debug log only on `1` and `client` values.
+                if value == "1" || value == "client" {
+                    c.logger = Arc::new(Mutex::new(Some(Arc::new(StderrLogger))));
+                }
+            }
             let m = c.map.clone();
             (Arc::new(Mutex::new(c)), m)
         };
@@ -87,6 +85,31 @@ impl DisplayInner {
     pub(crate) fn get_connection_fd(&self) -> ::std::os::unix::io::RawFd {
         self.connection.lock().unwrap().socket.get_socket().as_raw_fd()
     }
+
+    pub(crate) fn set_capture_hook(
+        &self,
+        hook: Option<Box<dyn CaptureHook>>,
+    ) -> Option<Box<dyn CaptureHook>> {
+        self.connection.lock().unwrap().socket.set_capture_hook(hook)
+    }
+
+    pub(crate) fn set_max_buffer_size(&self, size: usize) {
+        self.connection.lock().unwrap().socket.set_max_buffer_size(size)
+    }
+
+    pub(crate) fn set_protocol_logger(
+        &self,
+        logger: Option<Arc<dyn ProtocolLogger>>,
+    ) -> Option<Arc<dyn ProtocolLogger>> {
+        let cx = self.connection.lock().unwrap();
+        let mut guard = cx.logger.lock().unwrap();
+        std::mem::replace(&mut *guard, logger)
+    }
+
+    pub(crate) fn set_stale_id_detection(&self, enabled: bool) {
+        let cx = self.connection.lock().unwrap();
+        cx.stale_id_detection.store(enabled, std::sync::atomic::Ordering::Relaxed);
+    }
 }
 
 // WlDisplay needs its own dispatcher, as it can be dispatched from multiple threads
@@ -99,19 +122,10 @@ impl super::Dispatcher for DisplayDispatcher {
     fn dispatch(
         &mut self,
         msg: Message,
-        proxy: ProxyInner,
+        _proxy: ProxyInner,
         map: &mut ProxyMap,
         _data: crate::DispatchData,
     ) -> Dispatched {
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_dispatched_message(
-                proxy.object.interface,
-                proxy.id,
-                proxy.object.events[msg.opcode as usize].name,
-                &msg.args,
-            );
-        }
-
         let event = match wl_display::Event::from_raw(msg, map) {
             Ok(v) => v,
             Err(()) => return Dispatched::BadMsg,
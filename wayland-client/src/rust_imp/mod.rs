@@ -1,9 +1,9 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 
 use downcast::Downcast;
 
-use wayland_commons::debug;
+use wayland_commons::debug::ProtocolLogger;
 use wayland_commons::filter::Filter;
 use wayland_commons::map::ObjectMap;
 use wayland_commons::wire::Message;
@@ -20,8 +20,8 @@ pub(crate) use self::display::DisplayInner;
 pub(crate) use self::proxy::ProxyInner;
 pub(crate) use self::queues::EventQueueInner;
 
-/// Flag to toggle debug output.
-static WAYLAND_DEBUG: AtomicBool = AtomicBool::new(false);
+/// The protocol logger of a connection, shared by its event queues and proxies.
+pub(crate) type SharedLogger = Arc<Mutex<Option<Arc<dyn ProtocolLogger>>>>;
 
 /// A handle to the object map internal to the library state.
 ///
@@ -127,15 +127,6 @@ where
     ) -> Dispatched {
         let opcode = msg.opcode as usize;
 
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_dispatched_message(
-                proxy.object.interface,
-                proxy.id,
-                proxy.object.events[opcode].name,
-                &msg.args,
-            );
-        }
-
         let message = match I::Event::from_raw(msg, map) {
             Ok(v) => v,
             Err(()) => return Dispatched::BadMsg,
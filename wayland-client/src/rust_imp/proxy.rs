@@ -1,16 +1,16 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
-use wayland_commons::debug;
+use wayland_commons::debug::{LoggedMessage, MessageDirection};
 use wayland_commons::filter::Filter;
 use wayland_commons::map::{Object, ObjectMap, ObjectMetadata};
 use wayland_commons::user_data::UserData;
-use wayland_commons::wire::{Argument, ArgumentType};
+use wayland_commons::wire::{Argument, ArgumentType, MessageTooLarge};
 use wayland_commons::MessageGroup;
 
-use super::connection::Connection;
+use super::connection::{Connection, Error};
 use super::queues::QueueBuffer;
-use super::{Dispatcher, EventQueueInner, WAYLAND_DEBUG};
+use super::{Dispatcher, EventQueueInner};
 use crate::{Interface, Main, Proxy};
 
 #[derive(Clone)]
@@ -66,6 +66,7 @@ pub(crate) struct ProxyInner {
     pub(crate) connection: Arc<Mutex<Connection>>,
     pub(crate) object: Object<ObjectMeta>,
     pub(crate) id: u32,
+    pub(crate) generation: u32,
     pub(crate) queue: Option<QueueBuffer>,
 }
 
@@ -75,11 +76,12 @@ impl ProxyInner {
         map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
         connection: Arc<Mutex<Connection>>,
     ) -> Option<ProxyInner> {
-        let me = map.lock().unwrap().find(id);
-        me.map(|obj| ProxyInner {
+        let me = map.lock().unwrap().find_with_generation(id);
+        me.map(|(obj, generation)| ProxyInner {
             map,
             connection,
             id,
+            generation,
             queue: Some(obj.meta.buffer.clone()),
             object: obj,
         })
@@ -94,6 +96,7 @@ impl ProxyInner {
             map,
             connection,
             id,
+            generation: 0,
             queue: None,
             object: Object::from_interface::<I>(1, ObjectMeta::dead()),
         }
@@ -131,6 +134,13 @@ impl ProxyInner {
         self.queue = Some(queue.buffer.clone())
     }
 
+    pub(crate) fn set_queue(&self, queue: &EventQueueInner) {
+        // ignore failure if target object is dead
+        let _ = self.map.lock().unwrap().with_generation(self.id, self.generation, |obj| {
+            obj.meta.buffer = queue.buffer.clone();
+        });
+    }
+
     pub(crate) fn send<I, J>(&self, msg: I::Request, version: Option<u32>) -> Option<ProxyInner>
     where
         I: Interface,
@@ -178,14 +188,18 @@ impl ProxyInner {
                 if alive { ObjectMeta::new(target_queue.clone()) } else { ObjectMeta::dead() },
             );
             let mut new_id = 0;
+            let mut generation = 0;
             if alive {
-                new_id = self.map.lock().unwrap().client_insert_new(new_object.clone());
+                let mut map = self.map.lock().unwrap();
+                new_id = map.client_insert_new(new_object.clone());
+                generation = map.generation(new_id).unwrap_or(0);
                 msg.args[nid_idx] = Argument::NewId(new_id);
             }
             Some(ProxyInner {
                 map: self.map.clone(),
                 connection: self.connection.clone(),
                 id: new_id,
+                generation,
                 object: new_object,
                 queue: Some(target_queue),
             })
@@ -193,22 +207,50 @@ impl ProxyInner {
             None
         };
 
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_send_message(
-                I::NAME,
-                self.id,
+        if let Some(ref logger) = *conn_lock.logger.lock().unwrap() {
+            logger.log(&LoggedMessage {
+                direction: MessageDirection::Sent,
+                timestamp: std::time::SystemTime::now(),
+                interface: I::NAME,
+                id: self.id,
                 alive,
-                self.object.requests[msg.opcode as usize].name,
-                &msg.args,
-            );
+                message: self.object.requests[msg.opcode as usize].name,
+                args: &msg.args,
+            });
         }
 
         // Only actually send the message (& process destructor) if the object is alive.
         if !alive {
+            if conn_lock.stale_id_detection() {
+                eprintln!(
+                    "[wayland-client] Request {}.{} sent to dead object {}@{} (generation {}), ignoring.",
+                    I::NAME,
+                    self.object.requests[opcode as usize].name,
+                    I::NAME,
+                    self.id,
+                    self.generation
+                );
+            }
             return ret;
         }
 
-        conn_lock.write_message(&msg).expect("Sending a message failed.");
+        match conn_lock.write_message(msg) {
+            Ok(()) => {}
+            Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG)) => {
+                let error = MessageTooLarge {
+                    interface: I::NAME,
+                    message: self.object.requests[opcode as usize].name,
+                    buffer_size: conn_lock.socket.max_buffer_size(),
+                };
+                eprintln!("[wayland-client] {}", error);
+                // the request was lost, the connection is now in an inconsistent state
+                *conn_lock.last_error.lock().unwrap() = Some(Error::TooLarge(error));
+            }
+            Err(e) => {
+                // the request was lost, the connection is now in an inconsistent state
+                *conn_lock.last_error.lock().unwrap() = Some(Error::Nix(e));
+            }
+        }
 
         if destructor {
             self.object.meta.alive.store(false, Ordering::Release);
@@ -216,7 +258,7 @@ impl ProxyInner {
             // Cleanup the map as appropriate.
             let mut map = conn_lock.map.lock().unwrap();
             let server_destroyed = map
-                .with(self.id, |obj| {
+                .with_generation(self.id, self.generation, |obj| {
                     obj.meta.client_destroyed = true;
                     obj.meta.server_destroyed
                 })
@@ -240,9 +282,18 @@ impl ProxyInner {
         E: From<(Main<I>, I::Event)> + 'static,
         I::Event: MessageGroup<Map = super::ProxyMap>,
     {
-        // ignore failure if target object is dead
-        let _ = self.map.lock().unwrap().with(self.id, |obj| {
+        // ignore failure if target object is dead, making sure to not touch an other object
+        // that would have reused its id
+        let ret = self.map.lock().unwrap().with_generation(self.id, self.generation, |obj| {
             obj.meta.dispatcher = super::make_dispatcher(filter);
         });
+        if ret.is_err() && self.connection.lock().unwrap().stale_id_detection() {
+            eprintln!(
+                "[wayland-client] Assigning a filter to dead object {}@{} (generation {}), ignoring.",
+                I::NAME,
+                self.id,
+                self.generation
+            );
+        }
     }
 }
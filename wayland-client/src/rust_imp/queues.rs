@@ -4,19 +4,24 @@ use std::io;
 use std::os::unix::io::AsRawFd;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use nix::poll::{poll, PollFd, PollFlags};
+use nix::poll::PollFlags;
 
+use wayland_commons::debug::{LoggedMessage, MessageDirection};
 use wayland_commons::map::ObjectMap;
 use wayland_commons::wire::{Argument, Message};
 
 use super::connection::{Connection, Error as CError};
 use super::proxy::{ObjectMeta, ProxyInner};
-use super::Dispatched;
+use super::{Dispatched, SharedLogger};
 
+use crate::event_queue::{wait_for_fd, EventFd};
 use crate::{AnonymousObject, DispatchData, Filter, Main, RawEvent};
 
-pub(crate) type QueueBuffer = Arc<Mutex<VecDeque<Message>>>;
+/// The pending events of a queue, along with the generation of the object they were
+/// sent to.
+pub(crate) type QueueBuffer = Arc<Mutex<VecDeque<(Message, u32)>>>;
 
 pub(crate) fn create_queue_buffer() -> QueueBuffer {
     Arc::new(Mutex::new(VecDeque::new()))
@@ -27,6 +32,7 @@ pub(crate) struct EventQueueInner {
     pub(crate) map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
     pub(crate) buffer: QueueBuffer,
     display_buffer: QueueBuffer,
+    logger: SharedLogger,
 }
 
 impl EventQueueInner {
@@ -34,19 +40,34 @@ impl EventQueueInner {
         connection: Arc<Mutex<Connection>>,
         buffer: Option<QueueBuffer>,
     ) -> EventQueueInner {
-        let (map, display_buffer) = {
+        let (map, display_buffer, logger) = {
             let cx = connection.lock().unwrap();
-            (cx.map.clone(), cx.display_buffer.clone())
+            (cx.map.clone(), cx.display_buffer.clone(), cx.logger.clone())
         };
         EventQueueInner {
             connection,
             map,
             buffer: buffer.unwrap_or_else(create_queue_buffer),
             display_buffer,
+            logger,
         }
     }
 
-    pub(crate) fn dispatch<F>(&self, mut data: DispatchData, mut fallback: F) -> io::Result<u32>
+    pub(crate) fn dispatch<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        self.dispatch_until(data, fallback, None, None)
+    }
+
+    /// Dispatch, waiting for events at most until the deadline or until woken up
+    pub(crate) fn dispatch_until<F>(
+        &self,
+        mut data: DispatchData,
+        mut fallback: F,
+        deadline: Option<Instant>,
+        wake: Option<&EventFd>,
+    ) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
@@ -66,14 +87,9 @@ impl EventQueueInner {
                     Ok(_) => break,
                     Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => {
                         // EAGAIN, we need to wait before writing, so we poll the socket
-                        let poll_ret = poll(&mut [PollFd::new(socket_fd, PollFlags::POLLOUT)], -1);
-                        match poll_ret {
-                            Ok(_) => continue,
-                            Err(::nix::Error::Sys(e)) => {
-                                self.cancel_read();
-                                return Err(e.into());
-                            }
-                            Err(_) => unreachable!(),
+                        if let Err(e) = wait_for_fd(socket_fd, PollFlags::POLLOUT, deadline, wake) {
+                            self.cancel_read();
+                            return Err(e);
                         }
                     }
                     Err(::nix::Error::Sys(e)) => {
@@ -90,13 +106,9 @@ impl EventQueueInner {
         }
 
         // wait for incoming messages to arrive
-        match poll(&mut [PollFd::new(socket_fd, PollFlags::POLLIN)], -1) {
-            Ok(_) => (),
-            Err(::nix::Error::Sys(e)) => {
-                self.cancel_read();
-                return Err(e.into());
-            }
-            Err(_) => unreachable!(),
+        if let Err(e) = wait_for_fd(socket_fd, PollFlags::POLLIN, deadline, wake) {
+            self.cancel_read();
+            return Err(e);
         }
         let read_ret = self.read_events();
 
@@ -120,7 +132,7 @@ impl EventQueueInner {
 
     fn dispatch_buffer<F>(
         &self,
-        buffer: &Mutex<VecDeque<Message>>,
+        buffer: &Mutex<VecDeque<(Message, u32)>>,
         mut data: DispatchData,
         mut fallback: F,
     ) -> io::Result<u32>
@@ -131,7 +143,7 @@ impl EventQueueInner {
         let mut proxymap = super::ProxyMap::make(self.map.clone(), self.connection.clone());
         loop {
             let msg = { buffer.lock().unwrap().pop_front() };
-            let msg = match msg {
+            let (msg, generation) = match msg {
                 Some(m) => m,
                 None => break,
             };
@@ -139,29 +151,43 @@ impl EventQueueInner {
             if let Some(proxy) = ProxyInner::from_id(id, self.map.clone(), self.connection.clone())
             {
                 let object = proxy.object.clone();
-                if object.meta.client_destroyed {
+                let stale = proxy.generation != generation;
+                if stale && self.connection.lock().unwrap().stale_id_detection() {
+                    eprintln!(
+                        "[wayland-client] Event {} for object {} (generation {}) dispatched after \
+                        its id was reused by {}@{} (generation {}), discarding.",
+                        msg.opcode, id, generation, object.interface, id, proxy.generation
+                    );
+                }
+                if stale || object.meta.client_destroyed {
                     // This is a potential race, if we reach here it means that the proxy was
-                    // destroyed by the user between this message was queued and now. To handle it
-                    // correctly, we must close any FDs it contains, mark any child object as
-                    // destroyed (but the server will never know about it, so the ids will be
-                    // leaked) and discard the event.
+                    // destroyed by the user between this message was queued and now (and its id
+                    // possibly reused by a new object). To handle it
+                    // correctly, we must mark any child object as destroyed (but the server will
+                    // never know about it, so the ids will be leaked) and discard the event, which
+                    // closes any FDs it contains.
                     for arg in msg.args {
-                        match arg {
-                            Argument::Fd(fd) => {
-                                let _ = ::nix::unistd::close(fd);
-                            }
-                            Argument::NewId(id) => {
-                                let mut map = self.map.lock().unwrap();
-                                map.with(id, |obj| {
-                                    obj.meta.client_destroyed = true;
-                                })
-                                .unwrap();
-                            }
-                            _ => {}
+                        if let Argument::NewId(id) = arg {
+                            let mut map = self.map.lock().unwrap();
+                            map.with(id, |obj| {
+                                obj.meta.client_destroyed = true;
+                            })
+                            .unwrap();
                         }
                     }
                     continue;
                 }
+                if let Some(ref logger) = *self.logger.lock().unwrap() {
+                    logger.log(&LoggedMessage {
+                        direction: MessageDirection::Dispatched,
+                        timestamp: std::time::SystemTime::now(),
+                        interface: object.interface,
+                        id,
+                        alive: true,
+                        message: object.events[msg.opcode as usize].name,
+                        args: &msg.args,
+                    });
+                }
                 let mut dispatcher = object.meta.dispatcher.lock().unwrap();
                 match dispatcher.dispatch(msg, proxy, &mut proxymap, data.reborrow()) {
                     Dispatched::Yes => {
@@ -256,11 +282,25 @@ impl EventQueueInner {
                 eprintln!("[wayland-client] Parse error while reading events: {}", e);
                 Err(::nix::errno::Errno::EPROTO.into())
             }
+            Err(CError::TooLarge(e)) => Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
             Err(CError::Nix(::nix::Error::Sys(errno))) => Err(errno.into()),
             Err(CError::Nix(_)) => unreachable!(),
         }
     }
 
+    pub(crate) fn read_events_until(
+        &self,
+        deadline: Option<Instant>,
+        wake: Option<&EventFd>,
+    ) -> io::Result<()> {
+        let socket_fd = self.connection.lock().unwrap().socket.get_socket().as_raw_fd();
+        if let Err(e) = wait_for_fd(socket_fd, PollFlags::POLLIN, deadline, wake) {
+            self.cancel_read();
+            return Err(e);
+        }
+        self.read_events()
+    }
+
     pub(crate) fn cancel_read(&self) {
         // TODO: un-mock
     }
@@ -18,7 +18,7 @@ fn main() {
     let mut socket = BufferedSocket::new(unsafe { Socket::from_raw_fd(socket.into_raw_fd()) });
 
     socket
-        .write_message(&Message {
+        .write_message(Message {
             sender_id: 1, // wl_display
             opcode: 1,    // get registry
             args: smallvec![
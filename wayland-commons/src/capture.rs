@@ -0,0 +1,305 @@
+//! Wire-level traffic capture
+//!
+//! A `BufferedSocket` can be given a `CaptureHook`, which will be fed with the exact
+//! bytes exchanged on the socket, in both directions, as they are sent or received.
+//!
+//! The `CaptureWriter` hook stores this traffic on disk in the format described below,
+//! which can be read back using `CaptureReader`.
+//!
+//! ## Capture file format
+//!
+//! A capture file starts with a 16-bytes header:
+//!
+//! | offset | size | content                                                        |
+//! |--------|------|----------------------------------------------------------------|
+//! | 0      | 8    | magic bytes `WLCAPTUR`                                         |
+//! | 8      | 4    | format version, currently `1`                                  |
+//! | 12     | 4    | flags, bit 0 is set if the payloads are big-endian             |
+//!
+//! It is followed by a sequence of records, one for each socket message sent or received,
+//! each made of a 20-bytes header followed by the payload:
+//!
+//! | offset | size | content                                                        |
+//! |--------|------|----------------------------------------------------------------|
+//! | 0      | 1    | direction: `0` if received, `1` if sent by the capturing side  |
+//! | 1      | 3    | reserved, set to zero                                          |
+//! | 4      | 8    | timestamp, in nanoseconds since the UNIX epoch                 |
+//! | 12     | 4    | number of file descriptors attached to the message             |
+//! | 16     | 4    | length of the payload, in bytes                                |
+//! | 20     | ..   | payload: the raw bytes of the wayland messages                 |
+//!
+//! All the integers of the file and record headers are little-endian. The payload is
+//! stored exactly as it was on the wire, meaning in the native endianness of the capturing
+//! machine, as advertised by the flags of the file header. `CaptureReader` returns it
+//! unchanged, as converting it requires the signatures of the messages to tell the integers
+//! from the contents of the strings and arrays. The `Decoder` of the
+//! [`decode`](../decode/index.html) module does it when reading a foreign-endian capture.
+//!
+//! File descriptors cannot be captured, only their number is recorded. When decoding
+//! the payload, each `fd` argument consumes one of the file descriptors of its record
+//! or of the previous ones, as they are not necessarily attached to the same socket
+//! message as the wayland message using them.
+//!
+//! A single record may contain several wayland messages, and a wayland message may be
+//! split across several records.
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Magic bytes at the start of a capture file
+pub const CAPTURE_MAGIC: &[u8; 8] = b"WLCAPTUR";
+/// Version of the capture file format
+pub const CAPTURE_VERSION: u32 = 1;
+
+const FLAG_BIG_ENDIAN: u32 = 1;
+
+/// Direction of a captured socket message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The message was received by the capturing side
+    Received,
+    /// The message was sent by the capturing side
+    Sent,
+}
+
+/// A socket message, as seen by a `CaptureHook`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// Direction of the message
+    pub direction: Direction,
+    /// Time at which the message was sent or received
+    pub timestamp: SystemTime,
+    /// Raw bytes of the message
+    pub bytes: &'a [u8],
+    /// Number of file descriptors attached to the message
+    pub fds: usize,
+}
+
+/// A receiver for the traffic of a socket
+///
+/// It is invoked each time a message is successfully sent or received.
+pub trait CaptureHook: Send {
+    /// Process a captured socket message
+    fn capture(&mut self, record: &Record<'_>);
+}
+
+impl<F: FnMut(&Record<'_>) + Send> CaptureHook for F {
+    fn capture(&mut self, record: &Record<'_>) {
+        self(record)
+    }
+}
+
+/// A `CaptureHook` writing the traffic in the capture file format
+///
+/// If an I/O error occurs while writing, it is reported on stderr and the capture
+/// is stopped.
+///
+/// The writer is not flushed after each record, so you may want to wrap it in a
+/// `BufWriter`. It is dropped (and thus flushed) along with the socket capturing
+/// the traffic.
+pub struct CaptureWriter<W: Write + Send> {
+    writer: Option<W>,
+}
+
+impl<W: Write + Send> CaptureWriter<W> {
+    /// Create a new capture writer, writing the file header
+    pub fn new(mut writer: W) -> io::Result<CaptureWriter<W>> {
+        let flags = if cfg!(target_endian = "big") { FLAG_BIG_ENDIAN } else { 0 };
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_all(&CAPTURE_VERSION.to_le_bytes())?;
+        writer.write_all(&flags.to_le_bytes())?;
+        Ok(CaptureWriter { writer: Some(writer) })
+    }
+
+    /// Retrieve the underlying writer
+    ///
+    /// Returns `None` if the capture was stopped because of an error.
+    pub fn into_inner(self) -> Option<W> {
+        self.writer
+    }
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &Record<'_>) -> io::Result<()> {
+    let timestamp = record.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let direction = match record.direction {
+        Direction::Received => 0u8,
+        Direction::Sent => 1u8,
+    };
+    let mut header = [0u8; 20];
+    header[0] = direction;
+    header[4..12].copy_from_slice(&(timestamp.as_nanos() as u64).to_le_bytes());
+    header[12..16].copy_from_slice(&(record.fds as u32).to_le_bytes());
+    header[16..20].copy_from_slice(&(record.bytes.len() as u32).to_le_bytes());
+    writer.write_all(&header)?;
+    writer.write_all(record.bytes)
+}
+
+impl<W: Write + Send> CaptureHook for CaptureWriter<W> {
+    fn capture(&mut self, record: &Record<'_>) {
+        if let Some(ref mut writer) = self.writer {
+            if let Err(e) = write_record(writer, record) {
+                eprintln!("[wayland-commons] Failed to write capture record, stopping capture: {}", e);
+                self.writer = None;
+            }
+        }
+    }
+}
+
+/// A socket message read from a capture file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedRecord {
+    /// Direction of the message
+    pub direction: Direction,
+    /// Time at which the message was sent or received
+    pub timestamp: SystemTime,
+    /// Raw bytes of the message, in the endianness of the capturing machine
+    pub bytes: Vec<u8>,
+    /// Number of file descriptors attached to the message
+    pub fds: usize,
+}
+
+impl OwnedRecord {
+    /// Borrow this record
+    pub fn as_record(&self) -> Record<'_> {
+        Record {
+            direction: self.direction,
+            timestamp: self.timestamp,
+            bytes: &self.bytes,
+            fds: self.fds,
+        }
+    }
+}
+
+/// A reader for capture files
+///
+/// It is an iterator over the records of the file.
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    swap_endianness: bool,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Create a new capture reader, checking the file header
+    ///
+    /// Errors with `InvalidData` if the header is not a valid capture file header.
+    pub fn new(mut reader: R) -> io::Result<CaptureReader<R>> {
+        let mut header = [0u8; 16];
+        reader.read_exact(&mut header)?;
+        if &header[0..8] != CAPTURE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a wayland capture file"));
+        }
+        let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if version != CAPTURE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported capture format version {}", version),
+            ));
+        }
+        let flags = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        let big_endian = flags & FLAG_BIG_ENDIAN != 0;
+        Ok(CaptureReader { reader, swap_endianness: big_endian != cfg!(target_endian = "big") })
+    }
+
+    /// Whether the capture was made on a machine of the other endianness
+    ///
+    /// The payloads of the records are then to be decoded with
+    /// `Decoder::set_swap_endianness(true)`.
+    pub fn swap_endianness(&self) -> bool {
+        self.swap_endianness
+    }
+
+    /// Read the next record of the file
+    ///
+    /// Returns `Ok(None)` if the end of the file is reached.
+    pub fn read_record(&mut self) -> io::Result<Option<OwnedRecord>> {
+        let mut header = [0u8; 20];
+        // detect a clean EOF before the record header
+        let mut read = 0;
+        while read < header.len() {
+            match self.reader.read(&mut header[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let direction = match header[0] {
+            0 => Direction::Received,
+            1 => Direction::Sent,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid record direction")),
+        };
+        let mut u64_bytes = [0u8; 8];
+        u64_bytes.copy_from_slice(&header[4..12]);
+        let timestamp = UNIX_EPOCH + Duration::from_nanos(u64::from_le_bytes(u64_bytes));
+        let fds = u32::from_le_bytes([header[12], header[13], header[14], header[15]]) as usize;
+        let len = u32::from_le_bytes([header[16], header[17], header[18], header[19]]) as usize;
+        let mut bytes = vec![0u8; len];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(Some(OwnedRecord { direction, timestamp, bytes, fds }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<OwnedRecord>;
+
+    fn next(&mut self) -> Option<io::Result<OwnedRecord>> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_cycle() {
+        let records = [
+            OwnedRecord {
+                direction: Direction::Sent,
+                timestamp: UNIX_EPOCH + Duration::from_nanos(1_614_297_600_123_456_789),
+                bytes: vec![1, 0, 0, 0, 1, 0, 12, 0, 2, 0, 0, 0],
+                fds: 0,
+            },
+            OwnedRecord {
+                direction: Direction::Received,
+                timestamp: UNIX_EPOCH + Duration::from_nanos(1_614_297_600_223_456_789),
+                bytes: vec![3, 0, 0, 0, 0, 0, 8, 0],
+                fds: 2,
+            },
+        ];
+
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        for record in &records {
+            writer.capture(&record.as_record());
+        }
+        let data = writer.into_inner().unwrap();
+        assert_eq!(data.len(), 16 + 20 + 12 + 20 + 8);
+
+        let reader = CaptureReader::new(&data[..]).unwrap();
+        let read = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(&read[..], &records[..]);
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(CaptureReader::new(&b"WLCAPTUX\x01\0\0\0\0\0\0\0"[..]).is_err());
+        assert!(CaptureReader::new(&b"WLCAPTUR\x02\0\0\0\0\0\0\0"[..]).is_err());
+        assert!(CaptureReader::new(&b"WLCAPTUR\x01\0\0\0\0\0\0\0"[..]).is_ok());
+    }
+
+    #[test]
+    fn truncated_record() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer.capture(&Record {
+            direction: Direction::Sent,
+            timestamp: SystemTime::now(),
+            bytes: &[1, 0, 0, 0, 0, 0, 8, 0],
+            fds: 0,
+        });
+        let mut data = writer.into_inner().unwrap();
+        data.pop();
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+        assert!(reader.read_record().is_err());
+    }
+}
@@ -1,71 +1,211 @@
 //! Debugging helpers to handle `WAYLAND_DEBUG` env variable.
-
+//!
+//! Protocol messages can be reported to a `ProtocolLogger`, which receives them as structured
+//! `LoggedMessage`s. The default logger installed when `WAYLAND_DEBUG` is set is `StderrLogger`,
+//! which prints them to stderr in the same format as `libwayland`.
+//!
+//! If the `log` cargo feature is enabled, `LogLogger` forwards the messages to the `log` crate,
+//! and if the `tracing` cargo feature is enabled, `TracingLogger` forwards them to the `tracing`
+//! crate.
+
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::wire::Argument;
 
-/// Print the dispatched message to stderr in a following format:
-///
-/// [timestamp] <- interface@id.msg_name(args)
-pub fn print_dispatched_message(interface: &str, id: u32, msg_name: &str, args: &[Argument]) {
-    // Add timestamp to output.
-    print_timestamp();
-
-    eprint!(" <- {}@{}.{}", interface, id, msg_name);
-
-    print_args(args);
+/// Direction of a logged message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageDirection {
+    /// The message is being sent to the other side
+    Sent,
+    /// The message was received and is being dispatched
+    Dispatched,
+}
 
-    // Add a new line.
-    eprintln!();
+/// A protocol message, as seen by a `ProtocolLogger`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedMessage<'a> {
+    /// Direction of the message
+    pub direction: MessageDirection,
+    /// Time at which the message was sent or dispatched
+    pub timestamp: SystemTime,
+    /// Interface of the object sending the message
+    pub interface: &'a str,
+    /// Id of the object sending the message
+    pub id: u32,
+    /// Whether the object is alive
+    ///
+    /// Messages sent on dead objects are logged, but not actually sent.
+    pub alive: bool,
+    /// Name of the message
+    pub message: &'a str,
+    /// Arguments of the message
+    pub args: &'a [Argument],
 }
 
-/// Print the send message to stderr in a following format:
+/// Formats the message in the following format:
 ///
 /// [timestamp] -> interface@id.msg_name(args)
 ///
-/// If `is_alive` is `false` the `[ZOMBIE]` is added after `id`.
-pub fn print_send_message(
-    interface: &str,
-    id: u32,
-    is_alive: bool,
-    msg_name: &str,
-    args: &[Argument],
-) {
-    // Add timestamp to output.
-    print_timestamp();
+/// The arrow is `<-` for dispatched messages, and `[ZOMBIE]` is added after `id` if the
+/// object is dead.
+impl<'a> fmt::Display for LoggedMessage<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Ok(timestamp) = self.timestamp.duration_since(UNIX_EPOCH) {
+            write!(f, "[{}.{:06}]", timestamp.as_secs(), timestamp.subsec_micros())?;
+        }
+        write!(
+            f,
+            " {} {}@{}{}.{}",
+            match self.direction {
+                MessageDirection::Sent => "->",
+                MessageDirection::Dispatched => "<-",
+            },
+            self.interface,
+            self.id,
+            if self.alive { "" } else { "[ZOMBIE]" },
+            self.message
+        )?;
+        fmt_args(f, self.args)
+    }
+}
 
-    eprint!(" -> {}@{}{}.{}", interface, id, if is_alive { "" } else { "[ZOMBIE]" }, msg_name);
+/// Format arguments with opening/closing bracket.
+fn fmt_args(f: &mut fmt::Formatter<'_>, args: &[Argument]) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", arg)?;
+    }
+    write!(f, ")")
+}
 
-    print_args(args);
+/// A receiver for the protocol messages of a connection
+///
+/// It is invoked for each message sent or dispatched, and can be shared between several
+/// connections.
+pub trait ProtocolLogger: Send + Sync {
+    /// Log a protocol message
+    fn log(&self, message: &LoggedMessage<'_>);
+}
 
-    // Add a new line.
-    eprintln!();
+impl<F: Fn(&LoggedMessage<'_>) + Send + Sync> ProtocolLogger for F {
+    fn log(&self, message: &LoggedMessage<'_>) {
+        self(message)
+    }
 }
 
-/// Print arguments with opening/closing bracket.
-fn print_args(args: &[Argument]) {
-    let num_args = args.len();
+/// A `ProtocolLogger` printing the messages to stderr
+///
+/// This is the logger used when the `WAYLAND_DEBUG` env variable is set.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StderrLogger;
 
-    eprint!("(");
+impl ProtocolLogger for StderrLogger {
+    fn log(&self, message: &LoggedMessage<'_>) {
+        eprintln!("{}", message);
+    }
+}
 
-    if num_args > 0 {
-        // Explicitly handle first argument to handle one arg functions nicely.
-        eprint!("{}", args[0]);
+/// A `ProtocolLogger` forwarding the messages to the `log` crate
+///
+/// The messages are logged at the `Trace` level, with a target of the form
+/// `wayland_protocol::<interface>`, so that they can be filtered by interface. For example,
+/// with `env_logger`, `RUST_LOG=wayland_protocol::wl_surface=trace` only shows the messages
+/// of `wl_surface` objects.
+#[cfg(feature = "log")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LogLogger;
+
+#[cfg(feature = "log")]
+impl ProtocolLogger for LogLogger {
+    fn log(&self, message: &LoggedMessage<'_>) {
+        let target = format!("wayland_protocol::{}", message.interface);
+        let metadata = log::Metadata::builder().level(log::Level::Trace).target(&target).build();
+        let logger = log::logger();
+        if logger.enabled(&metadata) {
+            logger.log(
+                &log::Record::builder()
+                    .metadata(metadata)
+                    .args(format_args!("{}", message))
+                    .module_path_static(Some(module_path!()))
+                    .build(),
+            );
+        }
+    }
+}
 
-        // Handle the rest.
-        for arg in args.iter().take(num_args).skip(1) {
-            eprint!(", {}", arg);
+/// A `ProtocolLogger` forwarding the messages to the `tracing` crate
+///
+/// The messages are recorded as `TRACE` events with the `wayland_protocol` target, and
+/// `direction`, `interface`, `id`, `message` and `args` fields. They can be filtered by
+/// interface using field filters, for example with the `EnvFilter` of `tracing-subscriber`:
+/// `wayland_protocol[{interface=wl_surface}]=trace`.
+#[cfg(feature = "tracing")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TracingLogger;
+
+#[cfg(feature = "tracing")]
+impl ProtocolLogger for TracingLogger {
+    fn log(&self, message: &LoggedMessage<'_>) {
+        struct Args<'a>(&'a [Argument]);
+        impl<'a> fmt::Display for Args<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_args(f, self.0)
+            }
         }
+
+        tracing::trace!(
+            target: "wayland_protocol",
+            direction = match message.direction {
+                MessageDirection::Sent => "sent",
+                MessageDirection::Dispatched => "dispatched",
+            },
+            interface = message.interface,
+            id = message.id,
+            alive = message.alive,
+            message = message.message,
+            args = %Args(message.args),
+        );
     }
+}
 
-    eprint!(")")
+/// Print the dispatched message to stderr in a following format:
+///
+/// [timestamp] <- interface@id.msg_name(args)
+pub fn print_dispatched_message(interface: &str, id: u32, msg_name: &str, args: &[Argument]) {
+    StderrLogger.log(&LoggedMessage {
+        direction: MessageDirection::Dispatched,
+        timestamp: SystemTime::now(),
+        interface,
+        id,
+        alive: true,
+        message: msg_name,
+        args,
+    });
 }
 
-/// Print timestamp in seconds.microseconds format.
-fn print_timestamp() {
-    if let Ok(timestamp) = SystemTime::now().duration_since(UNIX_EPOCH) {
-        let sc = timestamp.as_secs();
-        let ms = timestamp.subsec_micros();
-        eprint!("[{}.{:06}]", sc, ms);
-    }
+/// Print the send message to stderr in a following format:
+///
+/// [timestamp] -> interface@id.msg_name(args)
+///
+/// If `is_alive` is `false` the `[ZOMBIE]` is added after `id`.
+pub fn print_send_message(
+    interface: &str,
+    id: u32,
+    is_alive: bool,
+    msg_name: &str,
+    args: &[Argument],
+) {
+    StderrLogger.log(&LoggedMessage {
+        direction: MessageDirection::Sent,
+        timestamp: SystemTime::now(),
+        interface,
+        id,
+        alive: is_alive,
+        message: msg_name,
+        args,
+    });
 }
@@ -0,0 +1,753 @@
+//! Offline decoding of captured traffic
+//!
+//! This module provides a `Decoder` which, given the description of the interfaces of the
+//! protocols in use, decodes the raw traffic captured by a `CaptureHook` (see the
+//! [`capture`](../capture/index.html) module) into fully described messages.
+//!
+//! The decoder replays the lifetime of the objects of the connection using an `ObjectMap`,
+//! which allows it to resolve the interface of each object. The interface descriptions can
+//! either be created from generated code using `InterfaceInfo::from_interface()`, or parsed
+//! from the protocol XML files, which is what the `wayland-decode` tool of `wayland-scanner`
+//! does.
+//!
+//! The capture is expected to start with the connection, as objects created before the
+//! start of the capture cannot be resolved.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::capture::{Direction, Record};
+use crate::map::{Object, ObjectMap, ObjectMetadata};
+use crate::wire::{Argument, ArgumentType, Message, MessageDesc};
+use crate::{Interface, MessageGroup};
+
+/// The side of the connection a capture was made on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// The capture was made by a client, sent messages are requests
+    Client,
+    /// The capture was made by a server, sent messages are events
+    Server,
+}
+
+/// Description of an interface
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterfaceInfo {
+    /// Name of the interface
+    pub name: String,
+    /// Version of the interface
+    pub version: u32,
+    /// Requests of the interface, in opcode order
+    pub requests: Vec<MessageInfo>,
+    /// Events of the interface, in opcode order
+    pub events: Vec<MessageInfo>,
+    /// Enums of the interface
+    pub enums: Vec<EnumInfo>,
+}
+
+/// Description of a message
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageInfo {
+    /// Name of the message
+    pub name: String,
+    /// Minimal version of the interface for this message
+    pub since: u32,
+    /// Whether this message is a destructor
+    pub destructor: bool,
+    /// Arguments of the message
+    pub args: Vec<ArgInfo>,
+}
+
+/// Description of an argument
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgInfo {
+    /// Name of the argument
+    pub name: String,
+    /// Type of the argument
+    ///
+    /// A `NewId` argument without `interface` is expanded on the wire to the interface
+    /// name, the version and the id of the new object, as is the case for `wl_registry.bind`.
+    pub kind: ArgumentType,
+    /// Interface of the object for `Object` and `NewId` arguments, if known
+    pub interface: Option<String>,
+    /// Enum associated with this argument, either as `enum_name` for an enum of the same
+    /// interface or as `interface_name.enum_name`
+    pub enum_: Option<String>,
+}
+
+/// Description of an enum
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumInfo {
+    /// Name of the enum
+    pub name: String,
+    /// Whether this enum is a bitfield
+    pub bitfield: bool,
+    /// Names and values of the entries of the enum
+    pub entries: Vec<(String, u32)>,
+}
+
+impl InterfaceInfo {
+    /// Create the description of an interface from its generated code
+    ///
+    /// Generated code does not keep the names of the arguments nor the enums, as such
+    /// the arguments are named `arg0`, `arg1`, etc. and enum values are not resolved.
+    pub fn from_interface<I: Interface>() -> InterfaceInfo {
+        InterfaceInfo {
+            name: I::NAME.into(),
+            version: I::VERSION,
+            requests: messages_info::<I::Request>(),
+            events: messages_info::<I::Event>(),
+            enums: Vec::new(),
+        }
+    }
+}
+
+impl MessageInfo {
+    /// The signature of this message on the wire
+    ///
+    /// This is the types of its arguments, with the `NewId` arguments without interface
+    /// expanded to the interface name, the version and the id of the new object.
+    pub fn wire_signature(&self) -> Vec<ArgumentType> {
+        let mut signature = Vec::with_capacity(self.args.len());
+        for arg in &self.args {
+            if arg.kind == ArgumentType::NewId && arg.interface.is_none() {
+                signature.extend_from_slice(&[
+                    ArgumentType::Str,
+                    ArgumentType::Uint,
+                    ArgumentType::NewId,
+                ]);
+            } else {
+                signature.push(arg.kind);
+            }
+        }
+        signature
+    }
+}
+
+fn messages_info<M: MessageGroup>() -> Vec<MessageInfo> {
+    M::MESSAGES
+        .iter()
+        .enumerate()
+        .map(|(opcode, desc): (usize, &MessageDesc)| {
+            let child = M::child(opcode as u16, 1, &()).map(|obj| obj.interface.to_owned());
+            MessageInfo {
+                name: desc.name.into(),
+                since: desc.since,
+                destructor: desc.destructor,
+                args: desc
+                    .signature
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &kind)| ArgInfo {
+                        name: format!("arg{}", i),
+                        kind,
+                        interface: if kind == ArgumentType::NewId { child.clone() } else { None },
+                        enum_: None,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// A decoded argument
+#[derive(Debug, PartialEq)]
+pub struct DecodedArg {
+    /// Name of the argument
+    pub name: String,
+    /// Value of the argument
+    ///
+    /// File descriptors cannot be captured, as such `Fd` arguments always hold the invalid
+    /// file descriptor `-1`.
+    pub value: Argument,
+    /// Interface of the object for `Object` and `NewId` arguments, if known
+    pub interface: Option<String>,
+    /// Name of the value if the argument is associated with an enum
+    pub enum_value: Option<String>,
+}
+
+/// A decoded message
+#[derive(Debug, PartialEq)]
+pub struct DecodedMessage {
+    /// Time at which the message was sent or received
+    pub timestamp: SystemTime,
+    /// Direction of the message
+    pub direction: Direction,
+    /// Id of the object sending the message
+    pub sender_id: u32,
+    /// Opcode of the message
+    pub opcode: u16,
+    /// Interface of the sender object, if known
+    pub interface: Option<String>,
+    /// Name of the message, if known
+    pub name: Option<String>,
+    /// Arguments of the message, or `None` if they could not be decoded
+    pub args: Option<Vec<DecodedArg>>,
+    /// Objects created by this message, with their interface if known
+    pub created: Vec<(u32, Option<String>)>,
+    /// Whether this message destroyed its sender object
+    pub destroyed: bool,
+}
+
+impl fmt::Display for DecodedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Ok(timestamp) = self.timestamp.duration_since(UNIX_EPOCH) {
+            write!(f, "[{}.{:06}] ", timestamp.as_secs(), timestamp.subsec_micros())?;
+        }
+        write!(
+            f,
+            "{} {}@{}.",
+            if self.direction == Direction::Sent { "->" } else { "<-" },
+            self.interface.as_deref().unwrap_or("[unknown]"),
+            self.sender_id,
+        )?;
+        match self.name {
+            Some(ref name) => write!(f, "{}", name)?,
+            None => write!(f, "[opcode {}]", self.opcode)?,
+        }
+        match self.args {
+            Some(ref args) => {
+                write!(f, "(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", arg.name)?;
+                    let interface = arg.interface.as_deref().unwrap_or("[unknown]");
+                    match arg.value {
+                        Argument::Object(0) | Argument::NewId(0) => write!(f, "null")?,
+                        Argument::Object(id) => write!(f, "{}@{}", interface, id)?,
+                        Argument::NewId(id) => write!(f, "new id {}@{}", interface, id)?,
+                        Argument::Fd(_) => write!(f, "fd")?,
+                        Argument::Fixed(v) => write!(f, "{}", v as f64 / 256.)?,
+                        ref other => write!(f, "{}", other)?,
+                    }
+                    if let Some(ref value) = arg.enum_value {
+                        write!(f, " ({})", value)?;
+                    }
+                }
+                write!(f, ")")
+            }
+            None => write!(f, "([undecodable])"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct DecodeMeta {
+    interface: Option<String>,
+    info: Option<Rc<InterfaceInfo>>,
+}
+
+impl ObjectMetadata for DecodeMeta {
+    fn child(&self) -> DecodeMeta {
+        self.clone()
+    }
+}
+
+/// A decoder for captured traffic
+///
+/// Feed it with the records of a capture in order using `decode()`.
+pub struct Decoder {
+    side: Side,
+    interfaces: HashMap<String, Rc<InterfaceInfo>>,
+    map: ObjectMap<DecodeMeta>,
+    sent: Vec<u8>,
+    received: Vec<u8>,
+    swap_endianness: bool,
+}
+
+impl Decoder {
+    /// Create a new decoder, for a capture made on given side
+    ///
+    /// The interfaces must include the description of `wl_display`, which is used
+    /// as the root object of the connection.
+    pub fn new<It: IntoIterator<Item = InterfaceInfo>>(side: Side, interfaces: It) -> Decoder {
+        let interfaces = interfaces
+            .into_iter()
+            .map(|info| (info.name.clone(), Rc::new(info)))
+            .collect::<HashMap<_, _>>();
+        let mut decoder = Decoder {
+            side,
+            interfaces,
+            map: ObjectMap::new(),
+            sent: Vec::new(),
+            received: Vec::new(),
+            swap_endianness: false,
+        };
+        let display = decoder.make_object(Some("wl_display"), 1);
+        decoder.map.insert_at(1, display).unwrap();
+        decoder
+    }
+
+    fn make_object(&self, interface: Option<&str>, version: u32) -> Object<DecodeMeta> {
+        let meta = DecodeMeta {
+            interface: interface.map(Into::into),
+            info: interface.and_then(|name| self.interfaces.get(name).cloned()),
+        };
+        Object { version, ..Object::placeholder(meta) }
+    }
+
+    /// Set whether the capture was made on a machine of the other endianness
+    ///
+    /// The integers of the messages are then byte-swapped, leaving the contents of their
+    /// strings and arrays untouched. See `CaptureReader::swap_endianness()`.
+    pub fn set_swap_endianness(&mut self, swap: bool) {
+        self.swap_endianness = swap;
+    }
+
+    /// Decode a record of the capture
+    ///
+    /// Returns the messages that were fully contained in the records decoded so far.
+    pub fn decode(&mut self, record: &Record<'_>) -> Vec<DecodedMessage> {
+        match record.direction {
+            Direction::Sent => self.sent.extend_from_slice(record.bytes),
+            Direction::Received => self.received.extend_from_slice(record.bytes),
+        }
+
+        let swap_endianness = self.swap_endianness;
+        let mut messages = Vec::new();
+        loop {
+            let stream = match record.direction {
+                Direction::Sent => &mut self.sent,
+                Direction::Received => &mut self.received,
+            };
+            if stream.len() < 8 {
+                break;
+            }
+            let swap = |w: u32| if swap_endianness { w.swap_bytes() } else { w };
+            let sender_id = swap(u32::from_ne_bytes([stream[0], stream[1], stream[2], stream[3]]));
+            let word = swap(u32::from_ne_bytes([stream[4], stream[5], stream[6], stream[7]]));
+            let len = (word >> 16) as usize;
+            if len < 8 || len & 3 != 0 {
+                // the stream is corrupted, we cannot find the message boundaries any more
+                messages.push(DecodedMessage {
+                    timestamp: record.timestamp,
+                    direction: record.direction,
+                    sender_id,
+                    opcode: (word & 0xFFFF) as u16,
+                    interface: None,
+                    name: None,
+                    args: None,
+                    created: Vec::new(),
+                    destroyed: false,
+                });
+                stream.clear();
+                break;
+            }
+            if stream.len() < len {
+                // wait for the rest of the message
+                break;
+            }
+            let mut words = stream
+                .drain(..len)
+                .collect::<Vec<u8>>()
+                .chunks_exact(4)
+                .map(|w| u32::from_ne_bytes([w[0], w[1], w[2], w[3]]))
+                .collect::<Vec<u32>>();
+            words[0] = sender_id;
+            words[1] = word;
+            let msg = self.decode_message(record, &mut words);
+            messages.push(msg);
+        }
+        messages
+    }
+
+    fn decode_message(&mut self, record: &Record<'_>, words: &mut [u32]) -> DecodedMessage {
+        let is_request = match (self.side, record.direction) {
+            (Side::Client, Direction::Sent) | (Side::Server, Direction::Received) => true,
+            (Side::Client, Direction::Received) | (Side::Server, Direction::Sent) => false,
+        };
+        let sender_id = words[0];
+        let opcode = (words[1] & 0xFFFF) as u16;
+        let mut decoded = DecodedMessage {
+            timestamp: record.timestamp,
+            direction: record.direction,
+            sender_id,
+            opcode,
+            interface: None,
+            name: None,
+            args: None,
+            created: Vec::new(),
+            destroyed: false,
+        };
+
+        let object = match self.map.find(sender_id) {
+            Some(object) => object,
+            None => return decoded,
+        };
+        decoded.interface = object.meta.interface.clone();
+        let info = match object.meta.info {
+            Some(ref info) => info.clone(),
+            None => return decoded,
+        };
+        let msg_info =
+            match if is_request { &info.requests } else { &info.events }.get(opcode as usize) {
+                Some(msg_info) => msg_info,
+                None => return decoded,
+            };
+        decoded.name = Some(msg_info.name.clone());
+
+        let signature = msg_info.wire_signature();
+        // file descriptors cannot be captured, use placeholders instead
+        let fd_count = signature.iter().filter(|&&t| t == ArgumentType::Fd).count();
+        let fds = vec![-1; fd_count];
+        if self.swap_endianness {
+            swap_arguments(&mut words[2..], &signature);
+        }
+        let message = match Message::from_raw(words, &signature, &fds) {
+            Ok((message, _, _)) => message,
+            Err(_) => return decoded,
+        };
+
+        let mut args = Vec::with_capacity(msg_info.args.len());
+        let mut values = message.args.into_iter();
+        for arg in &msg_info.args {
+            let mut decoded_arg = DecodedArg {
+                name: arg.name.clone(),
+                value: Argument::Int(0),
+                interface: arg.interface.clone(),
+                enum_value: None,
+            };
+            match arg.kind {
+                ArgumentType::NewId => {
+                    let version = if arg.interface.is_none() {
+                        match (values.next(), values.next()) {
+                            (Some(Argument::Str(name)), Some(Argument::Uint(version))) => {
+                                decoded_arg.interface = Some(name.to_string_lossy().into_owned());
+                                version
+                            }
+                            _ => return decoded,
+                        }
+                    } else {
+                        object.version
+                    };
+                    decoded_arg.value = values.next().unwrap();
+                    if let Argument::NewId(id) = decoded_arg.value {
+                        if id != 0 {
+                            let child = self.make_object(decoded_arg.interface.as_deref(), version);
+                            if self.map.insert_at(id, child.clone()).is_err() {
+                                // we missed the destruction of the previous object with this id
+                                self.map.remove(id);
+                                let _ = self.map.insert_at(id, child);
+                            }
+                            decoded.created.push((id, decoded_arg.interface.clone()));
+                        }
+                    }
+                }
+                ArgumentType::Object => {
+                    decoded_arg.value = values.next().unwrap();
+                    if let Argument::Object(id) = decoded_arg.value {
+                        if let Some(obj) = self.map.find(id) {
+                            decoded_arg.interface = obj.meta.interface;
+                        }
+                    }
+                }
+                _ => {
+                    decoded_arg.value = values.next().unwrap();
+                    if let Some(ref enum_) = arg.enum_ {
+                        decoded_arg.enum_value = self.enum_value(&info, enum_, &decoded_arg.value);
+                    }
+                }
+            }
+            args.push(decoded_arg);
+        }
+        decoded.args = Some(args);
+
+        if msg_info.destructor {
+            self.map.remove(sender_id);
+            decoded.destroyed = true;
+        }
+
+        decoded
+    }
+
+    fn enum_value(&self, info: &InterfaceInfo, enum_: &str, value: &Argument) -> Option<String> {
+        let value = match *value {
+            Argument::Uint(v) => v,
+            Argument::Int(v) => v as u32,
+            _ => return None,
+        };
+        let enum_info = match enum_.find('.') {
+            Some(idx) => self
+                .interfaces
+                .get(&enum_[..idx])?
+                .enums
+                .iter()
+                .find(|e| e.name == enum_[idx + 1..])?,
+            None => info.enums.iter().find(|e| e.name == enum_)?,
+        };
+        if let Some((name, _)) = enum_info.entries.iter().find(|&&(_, v)| v == value) {
+            return Some(name.clone());
+        }
+        if !enum_info.bitfield {
+            return None;
+        }
+        // decompose the bitfield in its flags
+        let mut names = Vec::new();
+        let mut remaining = value;
+        for &(ref name, v) in &enum_info.entries {
+            if v != 0 && value & v == v {
+                names.push(name.clone());
+                remaining &= !v;
+            }
+        }
+        if remaining != 0 {
+            names.push(format!("{:#x}", remaining));
+        }
+        Some(names.join(" | "))
+    }
+}
+
+// byte-swap the integers of the arguments of a message, as well as the lengths of its strings
+// and arrays but not their contents
+fn swap_arguments(words: &mut [u32], signature: &[ArgumentType]) {
+    let mut idx = 0;
+    for kind in signature {
+        if idx >= words.len() {
+            // truncated message, it will fail to decode anyway
+            return;
+        }
+        match *kind {
+            ArgumentType::Fd => {}
+            ArgumentType::Str | ArgumentType::Array => {
+                words[idx] = words[idx].swap_bytes();
+                // the contents are padded to a word boundary
+                let len = words[idx] as usize;
+                idx += 1 + ((len + 3) >> 2);
+            }
+            _ => {
+                words[idx] = words[idx].swap_bytes();
+                idx += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arg(
+        name: &str,
+        kind: ArgumentType,
+        interface: Option<&str>,
+        enum_: Option<&str>,
+    ) -> ArgInfo {
+        ArgInfo {
+            name: name.into(),
+            kind,
+            interface: interface.map(Into::into),
+            enum_: enum_.map(Into::into),
+        }
+    }
+
+    fn msg(name: &str, destructor: bool, args: Vec<ArgInfo>) -> MessageInfo {
+        MessageInfo { name: name.into(), since: 1, destructor, args }
+    }
+
+    fn interfaces() -> Vec<InterfaceInfo> {
+        vec![
+            InterfaceInfo {
+                name: "wl_display".into(),
+                version: 1,
+                requests: vec![
+                    msg(
+                        "sync",
+                        false,
+                        vec![arg("callback", ArgumentType::NewId, Some("wl_callback"), None)],
+                    ),
+                    msg(
+                        "get_registry",
+                        false,
+                        vec![arg("registry", ArgumentType::NewId, Some("wl_registry"), None)],
+                    ),
+                ],
+                events: vec![
+                    msg(
+                        "error",
+                        false,
+                        vec![
+                            arg("object_id", ArgumentType::Object, None, None),
+                            arg("code", ArgumentType::Uint, None, Some("error")),
+                            arg("message", ArgumentType::Str, None, None),
+                        ],
+                    ),
+                    msg("delete_id", false, vec![arg("id", ArgumentType::Uint, None, None)]),
+                ],
+                enums: vec![EnumInfo {
+                    name: "error".into(),
+                    bitfield: false,
+                    entries: vec![("invalid_object".into(), 0), ("invalid_method".into(), 1)],
+                }],
+            },
+            InterfaceInfo {
+                name: "wl_registry".into(),
+                version: 1,
+                requests: vec![msg(
+                    "bind",
+                    false,
+                    vec![
+                        arg("name", ArgumentType::Uint, None, None),
+                        arg("id", ArgumentType::NewId, None, None),
+                    ],
+                )],
+                events: vec![msg(
+                    "global",
+                    false,
+                    vec![
+                        arg("name", ArgumentType::Uint, None, None),
+                        arg("interface", ArgumentType::Str, None, None),
+                        arg("version", ArgumentType::Uint, None, None),
+                    ],
+                )],
+                enums: vec![],
+            },
+            InterfaceInfo {
+                name: "wl_callback".into(),
+                version: 1,
+                requests: vec![],
+                events: vec![msg("done", true, vec![arg("data", ArgumentType::Uint, None, None)])],
+                enums: vec![],
+            },
+            InterfaceInfo {
+                name: "wl_seat".into(),
+                version: 7,
+                requests: vec![],
+                events: vec![msg(
+                    "capabilities",
+                    false,
+                    vec![arg("capabilities", ArgumentType::Uint, None, Some("wl_seat.capability"))],
+                )],
+                enums: vec![EnumInfo {
+                    name: "capability".into(),
+                    bitfield: true,
+                    entries: vec![
+                        ("pointer".into(), 1),
+                        ("keyboard".into(), 2),
+                        ("touch".into(), 4),
+                    ],
+                }],
+            },
+        ]
+    }
+
+    fn bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_ne_bytes().to_vec()).collect()
+    }
+
+    fn record(direction: Direction, bytes: &[u8]) -> Record<'_> {
+        Record { direction, timestamp: UNIX_EPOCH, bytes, fds: 0 }
+    }
+
+    #[test]
+    fn decode_client_capture() {
+        let mut decoder = Decoder::new(Side::Client, interfaces());
+
+        // wl_display@1.get_registry(2), wl_display@1.sync(3)
+        let sent = bytes(&[1, 12 << 16 | 1, 2, 1, 12 << 16, 3]);
+        let msgs = decoder.decode(&record(Direction::Sent, &sent));
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(
+            msgs[0].to_string(),
+            "[0.000000] -> wl_display@1.get_registry(registry: new id wl_registry@2)"
+        );
+        assert_eq!(msgs[0].created, vec![(2, Some("wl_registry".into()))]);
+        assert_eq!(msgs[1].created, vec![(3, Some("wl_callback".into()))]);
+
+        // wl_registry@2.global(1, "wl_seat", 7) split across two records, then wl_callback@3.done(42)
+        let mut received = bytes(&[2, 28 << 16, 1, 8]);
+        received.extend_from_slice(b"wl_seat\0");
+        received.extend_from_slice(&bytes(&[7, 3, 12 << 16, 42]));
+        assert!(decoder.decode(&record(Direction::Received, &received[..10])).is_empty());
+        let msgs = decoder.decode(&record(Direction::Received, &received[10..]));
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(
+            msgs[0].to_string(),
+            "[0.000000] <- wl_registry@2.global(name: 1, interface: \"wl_seat\", version: 7)"
+        );
+        assert_eq!(msgs[1].to_string(), "[0.000000] <- wl_callback@3.done(data: 42)");
+        assert!(msgs[1].destroyed);
+
+        // wl_registry@2.bind(1, "wl_seat", 7, 3): reuses the id of the destroyed callback
+        let mut sent = bytes(&[2, 32 << 16, 1, 8]);
+        sent.extend_from_slice(b"wl_seat\0");
+        sent.extend_from_slice(&bytes(&[7, 3]));
+        let msgs = decoder.decode(&record(Direction::Sent, &sent));
+        assert_eq!(
+            msgs[0].to_string(),
+            "[0.000000] -> wl_registry@2.bind(name: 1, id: new id wl_seat@3)"
+        );
+
+        // wl_seat@3.capabilities(pointer | keyboard), wl_display@1.error(wl_seat@3, invalid_method, "")
+        let received = bytes(&[3, 12 << 16, 3, 1, 24 << 16, 3, 1, 1, 0]);
+        let msgs = decoder.decode(&record(Direction::Received, &received));
+        assert_eq!(
+            msgs[0].to_string(),
+            "[0.000000] <- wl_seat@3.capabilities(capabilities: 3 (pointer | keyboard))"
+        );
+        assert_eq!(
+            msgs[1].to_string(),
+            "[0.000000] <- wl_display@1.error(object_id: wl_seat@3, code: 1 (invalid_method), message: \"\")"
+        );
+    }
+
+    #[test]
+    fn decode_foreign_endian_capture() {
+        use crate::capture::{CaptureReader, CAPTURE_MAGIC, CAPTURE_VERSION};
+
+        // wl_registry@2.global(1, "wl_seat", 7) captured on a machine of the other endianness
+        let mut payload =
+            bytes(&[2u32.swap_bytes(), (28u32 << 16).swap_bytes(), 1u32.swap_bytes()]);
+        payload.extend_from_slice(&bytes(&[8u32.swap_bytes()]));
+        payload.extend_from_slice(b"wl_seat\0");
+        payload.extend_from_slice(&bytes(&[7u32.swap_bytes()]));
+        let flags: u32 = if cfg!(target_endian = "big") { 0 } else { 1 };
+        let mut capture = CAPTURE_MAGIC.to_vec();
+        capture.extend_from_slice(&CAPTURE_VERSION.to_le_bytes());
+        capture.extend_from_slice(&flags.to_le_bytes());
+        capture.extend_from_slice(&[0; 12]);
+        capture.extend_from_slice(&0u32.to_le_bytes());
+        capture.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        capture.extend_from_slice(&payload);
+
+        let mut reader = CaptureReader::new(&capture[..]).unwrap();
+        assert!(reader.swap_endianness());
+        let global = reader.read_record().unwrap().unwrap();
+        // the payload is returned as captured
+        assert_eq!(global.bytes, payload);
+
+        let mut decoder = Decoder::new(Side::Client, interfaces());
+        decoder.set_swap_endianness(true);
+        // wl_display@1.get_registry(2)
+        let sent = bytes(&[1u32.swap_bytes(), (12u32 << 16 | 1).swap_bytes(), 2u32.swap_bytes()]);
+        decoder.decode(&record(Direction::Sent, &sent));
+        let msgs = decoder.decode(&global.as_record());
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(
+            msgs[0].to_string(),
+            "[0.000000] <- wl_registry@2.global(name: 1, interface: \"wl_seat\", version: 7)"
+        );
+    }
+
+    #[test]
+    fn decode_server_capture_unknown() {
+        let mut decoder = Decoder::new(Side::Server, interfaces());
+
+        // a request on an unknown object, then an unknown opcode of wl_display
+        let received = bytes(&[42, 12 << 16 | 3, 0, 1, 8 << 16 | 7]);
+        let msgs = decoder.decode(&record(Direction::Received, &received));
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].interface, None);
+        assert_eq!(msgs[0].to_string(), "[0.000000] <- [unknown]@42.[opcode 3]([undecodable])");
+        assert_eq!(msgs[1].interface.as_deref(), Some("wl_display"));
+        assert_eq!(msgs[1].name, None);
+
+        // the server receives requests: wl_display@1.sync(2)
+        let msgs = decoder.decode(&record(Direction::Received, &bytes(&[1, 12 << 16, 2])));
+        assert_eq!(msgs[0].name.as_deref(), Some("sync"));
+        assert_eq!(msgs[0].created, vec![(2, Some("wl_callback".into()))]);
+    }
+}
@@ -21,15 +21,24 @@ extern crate nix;
 use std::os::raw::c_void;
 use wayland_sys::common as syscom;
 
+pub mod capture;
 pub mod debug;
+pub mod decode;
 pub mod filter;
 pub mod map;
+#[cfg(feature = "serde")]
+pub mod message_serde;
+pub mod mitm;
 pub mod socket;
 pub mod user_data;
 pub mod wire;
 
 pub use smallvec::smallvec;
 
+/// Re-export of the serde crate, used by the code generated with serde support
+#[cfg(feature = "serde")]
+pub use serde;
+
 /// A group of messages
 ///
 /// This represents a group of message that can be serialized on the protocol wire.
@@ -98,10 +98,19 @@ fn childs_from<M: MessageGroup, Meta: ObjectMetadata>(
 ///
 /// Keeps track of which object id is associated to which
 /// interface object, and which is currently unused.
+///
+/// Ids are reused as soon as they are freed. To tell apart the successive objects
+/// using a same id, each id has a generation, which is incremented every time an object
+/// is inserted with this id.
 #[derive(Default)]
 pub struct ObjectMap<Meta: ObjectMetadata> {
-    client_objects: Vec<Option<Object<Meta>>>,
-    server_objects: Vec<Option<Object<Meta>>>,
+    client_objects: Vec<Slot<Meta>>,
+    server_objects: Vec<Slot<Meta>>,
+}
+
+struct Slot<Meta: ObjectMetadata> {
+    object: Option<Object<Meta>>,
+    generation: u32,
 }
 
 impl<Meta: ObjectMetadata> ObjectMap<Meta> {
@@ -110,29 +119,49 @@ impl<Meta: ObjectMetadata> ObjectMap<Meta> {
         ObjectMap { client_objects: Vec::new(), server_objects: Vec::new() }
     }
 
-    /// Find an object in the store
-    pub fn find(&self, id: u32) -> Option<Object<Meta>> {
+    fn slot(&self, id: u32) -> Option<&Slot<Meta>> {
         if id == 0 {
             None
         } else if id >= SERVER_ID_LIMIT {
-            self.server_objects.get((id - SERVER_ID_LIMIT) as usize).and_then(Clone::clone)
+            self.server_objects.get((id - SERVER_ID_LIMIT) as usize)
         } else {
-            self.client_objects.get((id - 1) as usize).and_then(Clone::clone)
+            self.client_objects.get((id - 1) as usize)
         }
     }
 
+    fn slot_mut(&mut self, id: u32) -> Option<&mut Slot<Meta>> {
+        if id == 0 {
+            None
+        } else if id >= SERVER_ID_LIMIT {
+            self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize)
+        } else {
+            self.client_objects.get_mut((id - 1) as usize)
+        }
+    }
+
+    /// Find an object in the store
+    pub fn find(&self, id: u32) -> Option<Object<Meta>> {
+        self.slot(id).and_then(|slot| slot.object.clone())
+    }
+
+    /// Find an object in the store, along with its generation
+    pub fn find_with_generation(&self, id: u32) -> Option<(Object<Meta>, u32)> {
+        self.slot(id).and_then(|slot| slot.object.clone().map(|obj| (obj, slot.generation)))
+    }
+
+    /// Get the generation of the object associated with given id
+    ///
+    /// Returns `None` if there is no object with this id. Generations start at 1.
+    pub fn generation(&self, id: u32) -> Option<u32> {
+        self.slot(id).and_then(|slot| slot.object.as_ref().map(|_| slot.generation))
+    }
+
     /// Remove an object from the store
     ///
     /// Does nothing if the object didn't previously exists
     pub fn remove(&mut self, id: u32) {
-        if id == 0 {
-            // nothing
-        } else if id >= SERVER_ID_LIMIT {
-            if let Some(place) = self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize) {
-                *place = None;
-            }
-        } else if let Some(place) = self.client_objects.get_mut((id - 1) as usize) {
-            *place = None;
+        if let Some(slot) = self.slot_mut(id) {
+            slot.object = None;
         }
     }
 
@@ -166,33 +195,40 @@ impl<Meta: ObjectMetadata> ObjectMap<Meta> {
     // -- The lint is allowed because fixing it would be a breaking change --
     #[allow(clippy::result_unit_err)]
     pub fn with<T, F: FnOnce(&mut Object<Meta>) -> T>(&mut self, id: u32, f: F) -> Result<T, ()> {
-        if id == 0 {
-            Err(())
-        } else if id >= SERVER_ID_LIMIT {
-            if let Some(&mut Some(ref mut obj)) =
-                self.server_objects.get_mut((id - SERVER_ID_LIMIT) as usize)
-            {
+        match self.slot_mut(id) {
+            Some(&mut Slot { object: Some(ref mut obj), .. }) => Ok(f(obj)),
+            _ => Err(()),
+        }
+    }
+
+    /// Mutably access an object of the map, if it has given generation
+    ///
+    /// Fails if there is no object with this id, or if it has a different generation,
+    /// meaning the expected object has been removed and its id reused.
+    #[allow(clippy::result_unit_err)]
+    pub fn with_generation<T, F: FnOnce(&mut Object<Meta>) -> T>(
+        &mut self,
+        id: u32,
+        generation: u32,
+        f: F,
+    ) -> Result<T, ()> {
+        match self.slot_mut(id) {
+            Some(&mut Slot { object: Some(ref mut obj), generation: g }) if g == generation => {
                 Ok(f(obj))
-            } else {
-                Err(())
             }
-        } else if let Some(&mut Some(ref mut obj)) = self.client_objects.get_mut((id - 1) as usize)
-        {
-            Ok(f(obj))
-        } else {
-            Err(())
+            _ => Err(()),
         }
     }
 
     /// Mutably access all objects of the map in sequence
     pub fn with_all<F: FnMut(u32, &mut Object<Meta>)>(&mut self, mut f: F) {
-        for (id, place) in self.client_objects.iter_mut().enumerate() {
-            if let Some(ref mut obj) = *place {
+        for (id, slot) in self.client_objects.iter_mut().enumerate() {
+            if let Some(ref mut obj) = slot.object {
                 f(id as u32 + 1, obj);
             }
         }
-        for (id, place) in self.server_objects.iter_mut().enumerate() {
-            if let Some(ref mut obj) = *place {
+        for (id, slot) in self.server_objects.iter_mut().enumerate() {
+            if let Some(ref mut obj) = slot.object {
                 f(id as u32 + SERVER_ID_LIMIT, obj);
             }
         }
@@ -200,17 +236,16 @@ impl<Meta: ObjectMetadata> ObjectMap<Meta> {
 }
 
 // insert a new object in a store at the first free place
-fn insert_in<Meta: ObjectMetadata>(
-    store: &mut Vec<Option<Object<Meta>>>,
-    object: Object<Meta>,
-) -> u32 {
-    match store.iter().position(Option::is_none) {
+fn insert_in<Meta: ObjectMetadata>(store: &mut Vec<Slot<Meta>>, object: Object<Meta>) -> u32 {
+    match store.iter().position(|slot| slot.object.is_none()) {
         Some(id) => {
-            store[id] = Some(object);
+            let slot = &mut store[id];
+            slot.object = Some(object);
+            slot.generation = slot.generation.wrapping_add(1);
             id as u32
         }
         None => {
-            store.push(Some(object));
+            store.push(Slot { object: Some(object), generation: 1 });
             (store.len() - 1) as u32
         }
     }
@@ -218,23 +253,52 @@ fn insert_in<Meta: ObjectMetadata>(
 
 // insert an object at a given place in a store
 fn insert_in_at<Meta: ObjectMetadata>(
-    store: &mut Vec<Option<Object<Meta>>>,
+    store: &mut Vec<Slot<Meta>>,
     id: usize,
     object: Object<Meta>,
 ) -> Result<(), ()> {
     match id.cmp(&store.len()) {
         Ordering::Greater => Err(()),
         Ordering::Equal => {
-            store.push(Some(object));
+            store.push(Slot { object: Some(object), generation: 1 });
             Ok(())
         }
         Ordering::Less => {
-            let previous = &mut store[id];
-            if !previous.is_none() {
+            let slot = &mut store[id];
+            if slot.object.is_some() {
                 return Err(());
             }
-            *previous = Some(object);
+            slot.object = Some(object);
+            slot.generation = slot.generation.wrapping_add(1);
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_changes_on_id_reuse() {
+        let mut map = ObjectMap::<()>::new();
+        let id = map.client_insert_new(Object::placeholder(()));
+        let generation = map.generation(id).unwrap();
+        assert!(map.with_generation(id, generation, |_| ()).is_ok());
+
+        map.remove(id);
+        assert_eq!(map.generation(id), None);
+        assert!(map.with_generation(id, generation, |_| ()).is_err());
+
+        // the id is reused, but not the generation
+        assert_eq!(map.client_insert_new(Object::placeholder(())), id);
+        let new_generation = map.generation(id).unwrap();
+        assert_ne!(new_generation, generation);
+        assert!(map.with_generation(id, generation, |_| ()).is_err());
+        assert!(map.with_generation(id, new_generation, |_| ()).is_ok());
+
+        map.remove(id);
+        map.insert_at(id, Object::placeholder(())).unwrap();
+        assert_eq!(map.find_with_generation(id).map(|(_, g)| g), Some(new_generation + 1));
+    }
+}
@@ -0,0 +1,344 @@
+//! Deserialization of messages serialized by the generated code
+//!
+//! With serde support, the `Request` and `Event` enums generated by `wayland-scanner` are
+//! serialized with their objects as ids, and their enums by the names of their entries. As
+//! objects cannot be recreated from their id outside of a connection, they are deserialized
+//! into their raw `wire::Message` instead, using the formats of their arguments described by
+//! the generated code. The message is identified by its opcode, the index of its variant, or
+//! by its name.
+//!
+//! This module is only available with the `serde` cargo feature.
+
+use std::ffi::CString;
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::Deserialize;
+
+use crate::wire::{Argument, Message};
+
+/// How an argument of a message is serialized by the generated code
+#[derive(Copy, Clone)]
+pub enum ArgumentFormat {
+    /// An `int` argument
+    Int,
+    /// An `uint` argument
+    Uint,
+    /// A `fixed` argument, serialized as a float
+    Fixed,
+    /// A `string` argument, serialized as an option if it is nullable
+    Str {
+        /// Whether the argument is nullable
+        nullable: bool,
+    },
+    /// An `array` argument, serialized as an option if it is nullable
+    Array {
+        /// Whether the argument is nullable
+        nullable: bool,
+    },
+    /// An `object` argument, serialized by its id
+    Object {
+        /// Whether the argument is nullable
+        nullable: bool,
+    },
+    /// A `new_id` argument with an interface, serialized by its id
+    NewId {
+        /// Whether the argument is nullable
+        nullable: bool,
+    },
+    /// A `new_id` argument without interface, serialized as the name and version of the
+    /// interface, followed by the id if it is known
+    UntypedNewId {
+        /// Whether the id is serialized
+        with_id: bool,
+    },
+    /// A `new_id` argument of a client request, which is not serialized
+    ///
+    /// It is deserialized as a placeholder id of 0, like the one of `MessageGroup::into_raw()`.
+    NewIdPlaceholder,
+    /// A file descriptor, serialized by its number
+    ///
+    /// Its number does not give ownership of the file descriptor, such arguments fail
+    /// to deserialize.
+    Fd,
+    /// An `int` or `uint` argument of an enum, see `NamedEnum`
+    Enum {
+        /// Whether the argument is an `int`
+        signed: bool,
+        /// Whether the enum is a bitfield
+        bitfield: bool,
+        /// The value of an entry of the enum from its name
+        value_of: fn(&str) -> Option<u32>,
+    },
+}
+
+/// How a message is serialized by the generated code
+#[derive(Copy, Clone)]
+pub struct MessageFormat {
+    /// The name of the message, used as the name of its variant
+    pub name: &'static str,
+    /// The names of the serialized fields of the message
+    ///
+    /// `ArgumentFormat::NewIdPlaceholder` arguments do not have a field.
+    pub fields: &'static [&'static str],
+    /// The formats of the arguments of the message, in the order of the wire
+    pub args: &'static [ArgumentFormat],
+}
+
+/// An enum of a protocol, serialized by the names of its entries
+///
+/// Bitfields are serialized as the list of the names of their bits. This trait is implemented
+/// by the generated code with serde support, for the enums of the arguments of the messages
+/// to be deserialized even if they are defined by another protocol.
+pub trait NamedEnum {
+    /// Whether the enum is a bitfield
+    const BITFIELD: bool;
+    /// The value of an entry of the enum from its name
+    fn value_of(name: &str) -> Option<u32>;
+}
+
+/// Deserialize a message of a group into its raw representation
+///
+/// `group` and `names` are the name of the generated enum and the names of its variants,
+/// and `messages` the formats of its messages, in the order of their opcodes.
+pub fn deserialize_message<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    group: &'static str,
+    names: &'static [&'static str],
+    messages: &'static [MessageFormat],
+    sender_id: u32,
+) -> Result<Message, D::Error> {
+    let (opcode, args) =
+        deserializer.deserialize_enum(group, names, GroupVisitor { names, messages })?;
+    Ok(Message { sender_id, opcode, args: args.into() })
+}
+
+struct GroupVisitor {
+    names: &'static [&'static str],
+    messages: &'static [MessageFormat],
+}
+
+impl<'de> Visitor<'de> for GroupVisitor {
+    type Value = (u16, Vec<Argument>);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a wayland message")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (opcode, variant) = data.variant_seed(OpcodeSeed { names: self.names })?;
+        let message = &self.messages[opcode as usize];
+        let args = if message.args.is_empty() {
+            variant.unit_variant()?;
+            Vec::new()
+        } else {
+            variant.struct_variant(message.fields, ArgumentsVisitor { message })?
+        };
+        Ok((opcode, args))
+    }
+}
+
+// the opcode of a message, from the index or the name of its variant
+struct OpcodeSeed {
+    names: &'static [&'static str],
+}
+
+impl<'de> DeserializeSeed<'de> for OpcodeSeed {
+    type Value = u16;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<u16, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for OpcodeSeed {
+    type Value = u16;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the opcode or the name of a message")
+    }
+
+    fn visit_u64<E: de::Error>(self, opcode: u64) -> Result<u16, E> {
+        if opcode < self.names.len() as u64 {
+            Ok(opcode as u16)
+        } else {
+            Err(E::invalid_value(de::Unexpected::Unsigned(opcode), &self))
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<u16, E> {
+        match self.names.iter().position(|&n| n == name) {
+            Some(opcode) => Ok(opcode as u16),
+            None => Err(E::unknown_variant(name, self.names)),
+        }
+    }
+
+    fn visit_bytes<E: de::Error>(self, name: &[u8]) -> Result<u16, E> {
+        match std::str::from_utf8(name) {
+            Ok(name) => self.visit_str(name),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(name), &self)),
+        }
+    }
+}
+
+struct ArgumentsVisitor {
+    message: &'static MessageFormat,
+}
+
+impl<'de> Visitor<'de> for ArgumentsVisitor {
+    type Value = Vec<Argument>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the arguments of message `{}`", self.message.name)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Argument>, A::Error> {
+        let mut args = Vec::with_capacity(self.message.args.len());
+        let mut field = 0;
+        for &format in self.message.args {
+            if let ArgumentFormat::NewIdPlaceholder = format {
+                args.push(Argument::NewId(0));
+                continue;
+            }
+            match seq.next_element_seed(ArgumentSeed { format })? {
+                Some(values) => args.extend(values),
+                None => return Err(de::Error::invalid_length(field, &self)),
+            }
+            field += 1;
+        }
+        Ok(args)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<Argument>, A::Error> {
+        let fields = self.message.fields;
+        // the arguments of the fields, the placeholders do not have one
+        let mut field_args = Vec::with_capacity(fields.len());
+        for (i, format) in self.message.args.iter().enumerate() {
+            if let ArgumentFormat::NewIdPlaceholder = format {
+                continue;
+            }
+            field_args.push(i);
+        }
+
+        let mut values = self.message.args.iter().map(|_| None).collect::<Vec<_>>();
+        while let Some(key) = map.next_key::<String>()? {
+            let field = match fields.iter().position(|&f| f == key) {
+                Some(field) => field,
+                None => return Err(de::Error::unknown_field(&key, fields)),
+            };
+            let arg = field_args[field];
+            if values[arg].is_some() {
+                return Err(de::Error::duplicate_field(fields[field]));
+            }
+            let format = self.message.args[arg];
+            values[arg] = Some(map.next_value_seed(ArgumentSeed { format })?);
+        }
+
+        let mut args = Vec::with_capacity(self.message.args.len());
+        for (i, value) in values.into_iter().enumerate() {
+            match value {
+                Some(value) => args.extend(value),
+                None => match self.message.args[i] {
+                    ArgumentFormat::NewIdPlaceholder => args.push(Argument::NewId(0)),
+                    _ => {
+                        let field = field_args.iter().position(|&arg| arg == i).unwrap();
+                        return Err(de::Error::missing_field(fields[field]));
+                    }
+                },
+            }
+        }
+        Ok(args)
+    }
+}
+
+// the wire arguments of a field, several for the new_id arguments without interface
+struct ArgumentSeed {
+    format: ArgumentFormat,
+}
+
+fn cstring<E: de::Error>(string: String) -> Result<Box<CString>, E> {
+    CString::new(string).map(Box::new).map_err(E::custom)
+}
+
+fn entry_value<E: de::Error>(name: &str, value_of: fn(&str) -> Option<u32>) -> Result<u32, E> {
+    value_of(name).ok_or_else(|| {
+        E::invalid_value(de::Unexpected::Str(name), &"the name of an entry of the enum")
+    })
+}
+
+fn enum_argument(value: u32, signed: bool) -> Argument {
+    if signed {
+        Argument::Int(value as i32)
+    } else {
+        Argument::Uint(value)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ArgumentSeed {
+    type Value = Vec<Argument>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Argument>, D::Error> {
+        let arg = match self.format {
+            ArgumentFormat::Int => Argument::Int(i32::deserialize(deserializer)?),
+            ArgumentFormat::Uint => Argument::Uint(u32::deserialize(deserializer)?),
+            ArgumentFormat::Fixed => {
+                Argument::Fixed((f64::deserialize(deserializer)? * 256.) as i32)
+            }
+            ArgumentFormat::Str { nullable: false } => {
+                Argument::Str(cstring(String::deserialize(deserializer)?)?)
+            }
+            ArgumentFormat::Str { nullable: true } => Argument::Str(cstring(
+                Option::<String>::deserialize(deserializer)?.unwrap_or_else(String::new),
+            )?),
+            ArgumentFormat::Array { nullable: false } => {
+                Argument::Array(Box::new(Vec::<u8>::deserialize(deserializer)?))
+            }
+            ArgumentFormat::Array { nullable: true } => Argument::Array(Box::new(
+                Option::<Vec<u8>>::deserialize(deserializer)?.unwrap_or_else(Vec::new),
+            )),
+            ArgumentFormat::Object { nullable: false } => {
+                Argument::Object(u32::deserialize(deserializer)?)
+            }
+            ArgumentFormat::Object { nullable: true } => {
+                Argument::Object(Option::<u32>::deserialize(deserializer)?.unwrap_or(0))
+            }
+            ArgumentFormat::NewId { nullable: false } => {
+                Argument::NewId(u32::deserialize(deserializer)?)
+            }
+            ArgumentFormat::NewId { nullable: true } => {
+                Argument::NewId(Option::<u32>::deserialize(deserializer)?.unwrap_or(0))
+            }
+            ArgumentFormat::UntypedNewId { with_id } => {
+                let (interface, version, id) = if with_id {
+                    <(String, u32, u32)>::deserialize(deserializer)?
+                } else {
+                    let (interface, version) = <(String, u32)>::deserialize(deserializer)?;
+                    (interface, version, 0)
+                };
+                return Ok(vec![
+                    Argument::Str(cstring(interface)?),
+                    Argument::Uint(version),
+                    Argument::NewId(id),
+                ]);
+            }
+            ArgumentFormat::NewIdPlaceholder => Argument::NewId(0),
+            ArgumentFormat::Fd => {
+                return Err(de::Error::custom("file descriptors cannot be deserialized"));
+            }
+            ArgumentFormat::Enum { signed, bitfield: false, value_of } => {
+                let name = String::deserialize(deserializer)?;
+                enum_argument(entry_value(&name, value_of)?, signed)
+            }
+            ArgumentFormat::Enum { signed, bitfield: true, value_of } => {
+                let mut value = 0;
+                for name in Vec::<String>::deserialize(deserializer)? {
+                    value |= entry_value::<D::Error>(&name, value_of)?;
+                }
+                enum_argument(value, signed)
+            }
+        };
+        Ok(vec![arg])
+    }
+}
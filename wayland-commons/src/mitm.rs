@@ -0,0 +1,758 @@
+//! Man-in-the-middle proxying of wayland connections
+//!
+//! A `Mitm` sits between a client and a compositor: it receives the messages of the client
+//! connection, forwards them to an upstream connection to the compositor, and conversely.
+//! Along the way, a `MitmHook` can inspect, rewrite or drop each message, and inject new
+//! ones. A typical use is to restrict what a sandboxed application can access, for example
+//! by hiding some globals from its `wl_registry`.
+//!
+//! To be able to parse the messages, the proxy needs the description of all the interfaces
+//! used on the connection, in the form of `decode::InterfaceInfo`s. They can be created from
+//! generated code with `InterfaceInfo::from_interface()`, or parsed from protocol files with
+//! the `interfaces_from_xml()` function of `wayland-scanner`. The `wl_registry` globals whose
+//! interface is not known to the proxy are automatically hidden from the client.
+//!
+//! The proxy tracks the objects of the connection in two object maps, one for the objects
+//! known by the client and one for the objects known by the compositor. When a message
+//! creating an object is dropped, the object only exists on the side that sent it, and the
+//! messages later sent to this object are dropped as well.
+//!
+//! File descriptors are passed through: the proxy takes ownership of the file descriptors
+//! of the messages it receives, as well as of the ones of the injected messages, and closes
+//! them once they are forwarded or dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::io;
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+
+use nix::poll::{poll, PollFd, PollFlags};
+
+use crate::decode::{InterfaceInfo, MessageInfo};
+use crate::map::{Object, ObjectMap, ObjectMetadata};
+use crate::smallvec;
+use crate::socket::{BufferedSocket, Socket};
+use crate::wire::{Argument, ArgumentType, Message, MessageParseError};
+
+/// The sender of a message going through the proxy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The message is a request sent by the client
+    Client,
+    /// The message is an event sent by the compositor
+    Server,
+}
+
+/// What to do with an intercepted message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Forward the message, possibly rewritten, to its destination
+    Forward,
+    /// Drop the message
+    Drop,
+}
+
+/// A hook intercepting the messages going through a `Mitm`
+///
+/// Both methods forward the messages unchanged by default.
+///
+/// A hook can rewrite the message it is given, as long as it keeps it consistent with the
+/// signature of the message.
+pub trait MitmHook {
+    /// Process a request sent by the client
+    fn request(&mut self, request: &mut Message, ctx: &mut Context<'_>) -> Verdict {
+        let _ = (request, ctx);
+        Verdict::Forward
+    }
+
+    /// Process an event sent by the compositor
+    fn event(&mut self, event: &mut Message, ctx: &mut Context<'_>) -> Verdict {
+        let _ = (event, ctx);
+        Verdict::Forward
+    }
+}
+
+/// A hook forwarding everything unchanged
+impl MitmHook for () {}
+
+/// The state of the proxy, as seen by a `MitmHook`
+pub struct Context<'a> {
+    origin: Origin,
+    interfaces: &'a HashMap<String, InterfaceEntry>,
+    client_map: &'a ObjectMap<MitmMeta>,
+    server_map: &'a ObjectMap<MitmMeta>,
+    to_client: &'a mut Vec<Message>,
+    to_server: &'a mut Vec<Message>,
+}
+
+impl<'a> Context<'a> {
+    /// The sender of the message being processed
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// The interface of an object, as known by the sender of the message being processed
+    pub fn interface(&self, id: u32) -> Option<&'a InterfaceInfo> {
+        let map = match self.origin {
+            Origin::Client => self.client_map,
+            Origin::Server => self.server_map,
+        };
+        let interfaces = self.interfaces;
+        map.find(id)
+            .and_then(|obj| obj.meta.interface)
+            .and_then(|name| interfaces.get(&*name))
+            .map(|entry| &entry.info)
+    }
+
+    /// The description of a message sent by the sender of the message being processed
+    pub fn message_info(&self, msg: &Message) -> Option<&'a MessageInfo> {
+        let interface = self.interface(msg.sender_id)?;
+        match self.origin {
+            Origin::Client => interface.requests.get(msg.opcode as usize),
+            Origin::Server => interface.events.get(msg.opcode as usize),
+        }
+    }
+
+    /// Inject an event, to be sent to the client
+    ///
+    /// The injected messages are sent after the message being processed, in order. They
+    /// do not go through the hook. The proxy does not translate object ids, as such
+    /// creating objects with injected messages requires care to not conflict with the ids
+    /// allocated by the other peer.
+    pub fn send_to_client(&mut self, event: Message) {
+        self.to_client.push(event);
+    }
+
+    /// Inject a request, to be sent to the compositor
+    ///
+    /// See `send_to_client()` for details.
+    pub fn send_to_server(&mut self, request: Message) {
+        self.to_server.push(request);
+    }
+}
+
+struct InterfaceEntry {
+    info: InterfaceInfo,
+    requests: Vec<Vec<ArgumentType>>,
+    events: Vec<Vec<ArgumentType>>,
+}
+
+#[derive(Clone)]
+struct MitmMeta {
+    interface: Option<Arc<str>>,
+}
+
+impl ObjectMetadata for MitmMeta {
+    fn child(&self) -> MitmMeta {
+        self.clone()
+    }
+}
+
+fn new_object(interface: Option<&str>, version: u32) -> Object<MitmMeta> {
+    Object { version, ..Object::placeholder(MitmMeta { interface: interface.map(Into::into) }) }
+}
+
+fn nix_to_io(e: nix::Error) -> io::Error {
+    match e {
+        nix::Error::Sys(errno) => errno.into(),
+        other => io::Error::new(io::ErrorKind::InvalidInput, other),
+    }
+}
+
+struct Peer {
+    socket: BufferedSocket,
+    fd: RawFd,
+    map: ObjectMap<MitmMeta>,
+}
+
+impl Peer {
+    fn new(stream: UnixStream) -> io::Result<Peer> {
+        stream.set_nonblocking(false)?;
+        let fd = stream.into_raw_fd();
+        let mut map = ObjectMap::new();
+        map.insert_at(1, new_object(Some("wl_display"), 1)).unwrap();
+        Ok(Peer { socket: BufferedSocket::new(unsafe { Socket::from_raw_fd(fd) }), fd, map })
+    }
+}
+
+/// A man-in-the-middle proxy between a client and a compositor
+///
+/// See the module-level documentation for details.
+pub struct Mitm<H: MitmHook> {
+    client: Peer,
+    server: Peer,
+    interfaces: HashMap<String, InterfaceEntry>,
+    // the (registry id, global name) of the globals hidden from the client
+    hidden_globals: HashSet<(u32, u32)>,
+    hook: H,
+}
+
+impl<H: MitmHook> Mitm<H> {
+    /// Create a new proxy
+    ///
+    /// - `client` is the connection to the client
+    /// - `server` is the connection to the compositor, which must not have been used yet
+    /// - `interfaces` are the descriptions of the interfaces that can be used on the
+    ///   connection, they must at least include `wl_display` and `wl_registry`
+    /// - `hook` will be invoked for each message going through the proxy
+    pub fn new<I>(
+        client: UnixStream,
+        server: UnixStream,
+        interfaces: I,
+        hook: H,
+    ) -> io::Result<Mitm<H>>
+    where
+        I: IntoIterator<Item = InterfaceInfo>,
+    {
+        let interfaces = interfaces
+            .into_iter()
+            .map(|info| {
+                let entry = InterfaceEntry {
+                    requests: info.requests.iter().map(MessageInfo::wire_signature).collect(),
+                    events: info.events.iter().map(MessageInfo::wire_signature).collect(),
+                    info,
+                };
+                (entry.info.name.clone(), entry)
+            })
+            .collect();
+        Ok(Mitm {
+            client: Peer::new(client)?,
+            server: Peer::new(server)?,
+            interfaces,
+            hidden_globals: HashSet::new(),
+            hook,
+        })
+    }
+
+    /// Access the hook of this proxy
+    pub fn hook(&mut self) -> &mut H {
+        &mut self.hook
+    }
+
+    /// The file descriptor of the client connection
+    ///
+    /// When it becomes readable, `process_client()` must be invoked.
+    pub fn client_fd(&self) -> RawFd {
+        self.client.fd
+    }
+
+    /// The file descriptor of the compositor connection
+    ///
+    /// When it becomes readable, `process_server()` must be invoked.
+    pub fn server_fd(&self) -> RawFd {
+        self.server.fd
+    }
+
+    /// Run the proxy until one of the two connections is closed
+    ///
+    /// This blocks the current thread. It returns `Ok(())` once one of the peers closes
+    /// its connection, and an error if a protocol or I/O error occurs.
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            let mut fds = [
+                PollFd::new(self.client.fd, PollFlags::POLLIN),
+                PollFd::new(self.server.fd, PollFlags::POLLIN),
+            ];
+            match poll(&mut fds, -1) {
+                Ok(_) => {}
+                Err(nix::Error::Sys(nix::errno::Errno::EINTR)) => continue,
+                Err(e) => return Err(nix_to_io(e)),
+            }
+            let ready = |fd: &PollFd| fd.revents().map(|r| !r.is_empty()).unwrap_or(false);
+            let (client_ready, server_ready) = (ready(&fds[0]), ready(&fds[1]));
+
+            let mut ret = Ok(());
+            if server_ready {
+                ret = self.process_server();
+            }
+            if client_ready && ret.is_ok() {
+                ret = self.process_client();
+            }
+            // always forward what could be processed
+            let flushed = self.flush();
+            match ret.and(flushed) {
+                Ok(()) => {}
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::BrokenPipe
+                        || e.kind() == io::ErrorKind::ConnectionReset =>
+                {
+                    return Ok(())
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Flush the messages forwarded to both peers
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.server.socket.flush().map_err(nix_to_io)?;
+        self.client.socket.flush().map_err(nix_to_io)
+    }
+
+    /// Read the requests sent by the client and forward them
+    ///
+    /// This reads from the client connection once, blocking if there is nothing to read,
+    /// and processes all the complete messages received. The forwarded messages are not
+    /// flushed to the compositor.
+    ///
+    /// Returns a `BrokenPipe` error if the connection was closed.
+    pub fn process_client(&mut self) -> io::Result<()> {
+        self.process(Origin::Client)
+    }
+
+    /// Read the events sent by the compositor and forward them
+    ///
+    /// See `process_client()` for details.
+    pub fn process_server(&mut self) -> io::Result<()> {
+        self.process(Origin::Server)
+    }
+
+    fn process(&mut self, origin: Origin) -> io::Result<()> {
+        let source = match origin {
+            Origin::Client => &mut self.client,
+            Origin::Server => &mut self.server,
+        };
+        source.socket.fill_incoming_buffers().map_err(nix_to_io)?;
+        self.process_buffered(origin)
+    }
+
+    // process the messages remaining in the incoming buffers, without reading the socket
+    fn process_buffered(&mut self, origin: Origin) -> io::Result<()> {
+        loop {
+            let interfaces = &self.interfaces;
+            let source = match origin {
+                Origin::Client => &mut self.client,
+                Origin::Server => &mut self.server,
+            };
+            let map = &source.map;
+            let ret = source.socket.read_one_message(|id, opcode| {
+                let name = map.find(id)?.meta.interface?;
+                let entry = interfaces.get(&*name)?;
+                match origin {
+                    Origin::Client => entry.requests.get(opcode as usize),
+                    Origin::Server => entry.events.get(opcode as usize),
+                }
+                .map(|signature| &signature[..])
+            });
+            match ret {
+                Ok(msg) => self.handle(origin, msg)?,
+                Err(MessageParseError::MissingData) | Err(MessageParseError::MissingFD) => {
+                    return Ok(())
+                }
+                Err(MessageParseError::Malformed) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Failed to parse a message from the {}: malformed message, \
+                             unknown object or unknown interface.",
+                            if origin == Origin::Client { "client" } else { "compositor" }
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, origin: Origin, mut msg: Message) -> io::Result<()> {
+        self.handle_message(origin, &mut msg)
+    }
+
+    fn handle_message(&mut self, origin: Origin, msg: &mut Message) -> io::Result<()> {
+        // the sender knows about the effects of its own message
+        match origin {
+            Origin::Client => create_objects(&mut self.client.map, &self.interfaces, msg, true),
+            Origin::Server => create_objects(&mut self.server.map, &self.interfaces, msg, false),
+        }
+
+        let registry =
+            self.sender_interface(origin, msg.sender_id).as_deref() == Some("wl_registry");
+        let mut verdict = Verdict::Forward;
+        if registry && origin == Origin::Server && msg.opcode == 0 {
+            // hide the globals whose messages we would not be able to parse
+            if let Some(Argument::Str(ref interface)) = msg.args.get(1) {
+                if !self.interfaces.contains_key(&*interface.to_string_lossy()) {
+                    verdict = Verdict::Drop;
+                }
+            }
+        }
+        if registry && origin == Origin::Client && msg.opcode == 0 {
+            if let Some(&Argument::Uint(name)) = msg.args.first() {
+                if self.hidden_globals.contains(&(msg.sender_id, name)) {
+                    return self.bind_hidden_global(msg.sender_id, name);
+                }
+            }
+        }
+
+        let mut to_client = Vec::new();
+        let mut to_server = Vec::new();
+        if verdict == Verdict::Forward {
+            let mut ctx = Context {
+                origin,
+                interfaces: &self.interfaces,
+                client_map: &self.client.map,
+                server_map: &self.server.map,
+                to_client: &mut to_client,
+                to_server: &mut to_server,
+            };
+            verdict = match origin {
+                Origin::Client => self.hook.request(msg, &mut ctx),
+                Origin::Server => self.hook.event(msg, &mut ctx),
+            };
+        }
+
+        if registry && origin == Origin::Server {
+            if let Some(&Argument::Uint(name)) = msg.args.first() {
+                match msg.opcode {
+                    // global
+                    0 if verdict == Verdict::Drop => {
+                        self.hidden_globals.insert((msg.sender_id, name));
+                    }
+                    // global_remove
+                    1 if self.hidden_globals.remove(&(msg.sender_id, name)) => {
+                        verdict = Verdict::Drop;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        match origin {
+            Origin::Client => {
+                destroy_objects(&mut self.client.map, &self.interfaces, msg, true, false)
+            }
+            Origin::Server => {
+                destroy_objects(&mut self.server.map, &self.interfaces, msg, false, true)
+            }
+        }
+
+        // messages to objects that the destination does not know about are dropped
+        let destination = match origin {
+            Origin::Client => &mut self.server,
+            Origin::Server => &mut self.client,
+        };
+        if verdict == Verdict::Forward && destination.map.find(msg.sender_id).is_some() {
+            apply(
+                &mut destination.map,
+                &self.interfaces,
+                msg,
+                origin == Origin::Client,
+                origin == Origin::Client,
+            );
+            // the fds of the message are moved to the socket
+            let args = ::std::mem::take(&mut msg.args);
+            let msg = Message { sender_id: msg.sender_id, opcode: msg.opcode, args };
+            destination.socket.write_message(msg).map_err(nix_to_io)?;
+        }
+
+        for request in to_server {
+            apply(&mut self.server.map, &self.interfaces, &request, true, true);
+            self.server.socket.write_message(request).map_err(nix_to_io)?;
+        }
+        for event in to_client {
+            apply(&mut self.client.map, &self.interfaces, &event, false, false);
+            self.client.socket.write_message(event).map_err(nix_to_io)?;
+        }
+        Ok(())
+    }
+
+    fn sender_interface(&self, origin: Origin, id: u32) -> Option<Arc<str>> {
+        let map = match origin {
+            Origin::Client => &self.client.map,
+            Origin::Server => &self.server.map,
+        };
+        map.find(id).and_then(|obj| obj.meta.interface)
+    }
+
+    // the client tried to bind a hidden global, kill it like the compositor would do
+    fn bind_hidden_global(&mut self, registry: u32, name: u32) -> io::Result<()> {
+        let error = Message {
+            sender_id: 1,
+            opcode: 0,
+            args: smallvec![
+                Argument::Object(registry),
+                // wl_display.error.invalid_object
+                Argument::Uint(0),
+                Argument::Str(Box::new(CString::new(format!("invalid global {}", name)).unwrap())),
+            ],
+        };
+        self.client.socket.write_message(error).map_err(nix_to_io)?;
+        self.client.socket.flush().map_err(nix_to_io)?;
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("The client tried to bind the hidden global {}.", name),
+        ))
+    }
+}
+
+// apply the effects of a message on an object map: object creations and destructions
+fn apply(
+    map: &mut ObjectMap<MitmMeta>,
+    interfaces: &HashMap<String, InterfaceEntry>,
+    msg: &Message,
+    is_request: bool,
+    is_server_map: bool,
+) {
+    create_objects(map, interfaces, msg, is_request);
+    destroy_objects(map, interfaces, msg, is_request, is_server_map);
+}
+
+fn message_info<'a>(
+    map: &ObjectMap<MitmMeta>,
+    interfaces: &'a HashMap<String, InterfaceEntry>,
+    msg: &Message,
+    is_request: bool,
+) -> Option<(Object<MitmMeta>, &'a MessageInfo)> {
+    let sender = map.find(msg.sender_id)?;
+    let info = &interfaces.get(&**sender.meta.interface.as_ref()?)?.info;
+    let msg_info =
+        if is_request { &info.requests } else { &info.events }.get(msg.opcode as usize)?;
+    Some((sender, msg_info))
+}
+
+fn create_objects(
+    map: &mut ObjectMap<MitmMeta>,
+    interfaces: &HashMap<String, InterfaceEntry>,
+    msg: &Message,
+    is_request: bool,
+) {
+    let (sender, msg_info) = match message_info(map, interfaces, msg, is_request) {
+        Some(info) => info,
+        None => return,
+    };
+    let mut args = msg.args.iter();
+    for arg in &msg_info.args {
+        let value = if arg.kind == ArgumentType::NewId && arg.interface.is_none() {
+            match (args.next(), args.next(), args.next()) {
+                (Some(Argument::Str(interface)), Some(&Argument::Uint(version)), Some(value)) => {
+                    Some((value, Some(interface.to_string_lossy().into_owned()), version))
+                }
+                _ => return,
+            }
+        } else {
+            args.next().map(|value| (value, arg.interface.clone(), sender.version))
+        };
+        if let Some((&Argument::NewId(id), interface, version)) = value {
+            if id != 0 {
+                let object = new_object(interface.as_deref(), version);
+                if map.insert_at(id, object.clone()).is_err() {
+                    map.remove(id);
+                    let _ = map.insert_at(id, object);
+                }
+            }
+        }
+    }
+}
+
+fn destroy_objects(
+    map: &mut ObjectMap<MitmMeta>,
+    interfaces: &HashMap<String, InterfaceEntry>,
+    msg: &Message,
+    is_request: bool,
+    is_server_map: bool,
+) {
+    let destructor = match message_info(map, interfaces, msg, is_request) {
+        Some((_, msg_info)) => msg_info.destructor,
+        None => return,
+    };
+    if destructor && !(is_request && is_server_map) {
+        // the compositor keeps the objects destroyed by the client until it acknowledges
+        // their destruction with wl_display.delete_id
+        map.remove(msg.sender_id);
+    }
+    if !is_request && msg.sender_id == 1 && msg.opcode == 1 {
+        // wl_display.delete_id
+        if let Some(&Argument::Uint(id)) = msg.args.first() {
+            map.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::{ArgInfo, MessageInfo};
+
+    fn msg(name: &str, destructor: bool, args: &[(ArgumentType, Option<&str>)]) -> MessageInfo {
+        MessageInfo {
+            name: name.into(),
+            since: 1,
+            destructor,
+            args: args
+                .iter()
+                .map(|&(kind, interface)| ArgInfo {
+                    name: String::new(),
+                    kind,
+                    interface: interface.map(Into::into),
+                    enum_: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn interface(
+        name: &str,
+        requests: Vec<MessageInfo>,
+        events: Vec<MessageInfo>,
+    ) -> InterfaceInfo {
+        InterfaceInfo { name: name.into(), version: 1, requests, events, enums: Vec::new() }
+    }
+
+    fn interfaces() -> Vec<InterfaceInfo> {
+        use ArgumentType::*;
+        vec![
+            interface(
+                "wl_display",
+                vec![
+                    msg("sync", false, &[(NewId, Some("wl_callback"))]),
+                    msg("get_registry", false, &[(NewId, Some("wl_registry"))]),
+                ],
+                vec![
+                    msg("error", false, &[(Object, None), (Uint, None), (Str, None)]),
+                    msg("delete_id", false, &[(Uint, None)]),
+                ],
+            ),
+            interface(
+                "wl_registry",
+                vec![msg("bind", false, &[(Uint, None), (NewId, None)])],
+                vec![
+                    msg("global", false, &[(Uint, None), (Str, None), (Uint, None)]),
+                    msg("global_remove", false, &[(Uint, None)]),
+                ],
+            ),
+            interface("wl_callback", vec![], vec![msg("done", true, &[(Uint, None)])]),
+            interface("wl_compositor", vec![], vec![]),
+            interface("wl_output", vec![msg("release", true, &[])], vec![]),
+        ]
+    }
+
+    // the signatures of the messages exchanged in the tests, as seen by the peers
+    fn signature(id: u32, opcode: u16) -> Option<&'static [ArgumentType]> {
+        use ArgumentType::*;
+        match (id, opcode) {
+            (1, 0) => Some(&[Object, Uint, Str]),
+            (1, 1) => Some(&[Uint]),
+            (2, 0) => Some(&[Uint, Str, Uint]),
+            (2, 1) => Some(&[Uint]),
+            (3, 0) => Some(&[Uint]),
+            _ => None,
+        }
+    }
+
+    fn peer(stream: UnixStream) -> BufferedSocket {
+        BufferedSocket::new(unsafe { Socket::from_raw_fd(stream.into_raw_fd()) })
+    }
+
+    fn send(socket: &mut BufferedSocket, sender_id: u32, opcode: u16, args: Vec<Argument>) {
+        socket
+            .write_message(Message { sender_id, opcode, args: args.into_iter().collect() })
+            .unwrap();
+        socket.flush().unwrap();
+    }
+
+    fn receive(
+        socket: &mut BufferedSocket,
+        signature: fn(u32, u16) -> Option<&'static [ArgumentType]>,
+    ) -> Message {
+        loop {
+            match socket.read_one_message(signature) {
+                Ok(msg) => return msg,
+                Err(MessageParseError::MissingData) => socket.fill_incoming_buffers().unwrap(),
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+    }
+
+    fn string(s: &str) -> Argument {
+        Argument::Str(Box::new(CString::new(s).unwrap()))
+    }
+
+    struct HideOutputs {
+        requests: usize,
+    }
+
+    impl MitmHook for HideOutputs {
+        fn request(&mut self, _: &mut Message, _: &mut Context<'_>) -> Verdict {
+            self.requests += 1;
+            Verdict::Forward
+        }
+
+        fn event(&mut self, event: &mut Message, ctx: &mut Context<'_>) -> Verdict {
+            let info = ctx.message_info(event).unwrap();
+            match (&ctx.interface(event.sender_id).unwrap().name[..], &info.name[..]) {
+                ("wl_registry", "global") if event.args[1] == string("wl_output") => {
+                    return Verdict::Drop;
+                }
+                ("wl_callback", "done") => {
+                    event.args[0] = Argument::Uint(42);
+                    ctx.send_to_client(Message {
+                        sender_id: 2,
+                        opcode: 1,
+                        args: smallvec![Argument::Uint(7)],
+                    });
+                }
+                _ => {}
+            }
+            Verdict::Forward
+        }
+    }
+
+    #[test]
+    fn mitm_forward_and_hide() {
+        let (client, client_mitm) = UnixStream::pair().unwrap();
+        let (server, server_mitm) = UnixStream::pair().unwrap();
+        let (mut client, mut server) = (peer(client), peer(server));
+        let mut mitm =
+            Mitm::new(client_mitm, server_mitm, interfaces(), HideOutputs { requests: 0 }).unwrap();
+
+        // wl_display.get_registry(2), wl_display.sync(3)
+        send(&mut client, 1, 1, vec![Argument::NewId(2)]);
+        send(&mut client, 1, 0, vec![Argument::NewId(3)]);
+        mitm.process_client().unwrap();
+        mitm.flush().unwrap();
+        let msg = receive(&mut server, |_, _| Some(&[ArgumentType::NewId]));
+        assert_eq!((msg.sender_id, msg.opcode, &msg.args[0]), (1, 1, &Argument::NewId(2)));
+        receive(&mut server, |_, _| Some(&[ArgumentType::NewId]));
+        assert_eq!(mitm.hook().requests, 2);
+
+        // the wl_output global is hidden by the hook, the wl_seat one because it is unknown
+        send(&mut server, 2, 0, vec![Argument::Uint(1), string("wl_output"), Argument::Uint(1)]);
+        send(&mut server, 2, 0, vec![Argument::Uint(2), string("wl_seat"), Argument::Uint(1)]);
+        send(
+            &mut server,
+            2,
+            0,
+            vec![Argument::Uint(3), string("wl_compositor"), Argument::Uint(1)],
+        );
+        send(&mut server, 2, 1, vec![Argument::Uint(1)]);
+        send(&mut server, 2, 1, vec![Argument::Uint(3)]);
+        // the callback event is rewritten and followed by an injected global_remove
+        send(&mut server, 3, 0, vec![Argument::Uint(0)]);
+        mitm.process_server().unwrap();
+        mitm.flush().unwrap();
+
+        let msg = receive(&mut client, signature);
+        assert_eq!((msg.sender_id, msg.opcode), (2, 0));
+        assert_eq!(msg.args[1], string("wl_compositor"));
+        let msg = receive(&mut client, signature);
+        assert_eq!((msg.sender_id, msg.opcode, &msg.args[0]), (2, 1, &Argument::Uint(3)));
+        let msg = receive(&mut client, signature);
+        assert_eq!((msg.sender_id, msg.opcode, &msg.args[0]), (3, 0, &Argument::Uint(42)));
+        let msg = receive(&mut client, signature);
+        assert_eq!((msg.sender_id, msg.opcode, &msg.args[0]), (2, 1, &Argument::Uint(7)));
+
+        // binding a hidden global kills the client
+        send(
+            &mut client,
+            2,
+            0,
+            vec![Argument::Uint(2), string("wl_seat"), Argument::Uint(1), Argument::NewId(4)],
+        );
+        let err = mitm.process_client().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let msg = receive(&mut client, signature);
+        assert_eq!((msg.sender_id, msg.opcode, &msg.args[0]), (1, 0, &Argument::Object(2)));
+    }
+}
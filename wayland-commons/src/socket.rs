@@ -1,5 +1,6 @@
 //! Wayland socket manipulation
 
+use std::collections::VecDeque;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 
 use nix::{
@@ -7,11 +8,17 @@ use nix::{
     Result as NixResult,
 };
 
-use crate::wire::{ArgumentType, Message, MessageParseError, MessageWriteError};
+use crate::capture::{CaptureHook, Direction, Record};
+use crate::wire::{
+    Argument, ArgumentType, Message, MessageParseError, MessageView, MessageWriteError,
+};
 
 /// Maximum number of FD that can be sent in a single socket message
 pub const MAX_FDS_OUT: usize = 28;
 /// Maximum number of bytes that can be sent in a single socket message
+///
+/// This is the default buffer size of a `BufferedSocket`, see
+/// `BufferedSocket::set_max_buffer_size()`.
 pub const MAX_BYTES_OUT: usize = 4096;
 
 /*
@@ -29,17 +36,19 @@ impl Socket {
     /// A single socket message can contain several wayland messages
     ///
     /// The `fds` slice should not be longer than `MAX_FDS_OUT`, and the `bytes`
-    /// slice should not be longer than `MAX_BYTES_OUT` otherwise the receiving
-    /// end may lose some data.
-    pub fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> NixResult<()> {
+    /// slice should not be longer than the buffers of the receiving end, otherwise
+    /// it may lose some data.
+    ///
+    /// Returns the number of bytes sent, which can be less than the length of `bytes`
+    /// if the socket buffer is full. The fds are sent along with the first byte.
+    pub fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> NixResult<usize> {
         let iov = [uio::IoVec::from_slice(bytes)];
         if !fds.is_empty() {
             let cmsgs = [socket::ControlMessage::ScmRights(fds)];
-            socket::sendmsg(self.fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)?;
+            socket::sendmsg(self.fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)
         } else {
-            socket::sendmsg(self.fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)?;
-        };
-        Ok(())
+            socket::sendmsg(self.fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)
+        }
     }
 
     /// Receive a single message from the socket
@@ -103,26 +112,111 @@ impl Drop for Socket {
 
 /// An adapter around a raw Socket that directly handles buffering and
 /// conversion from/to wayland messages
+///
+/// If the socket cannot accept more data when the outgoing buffer is full, its contents
+/// are moved to an outgoing queue, which is sent by the next calls to `flush()`. This queue
+/// is unbounded by default, see `set_max_pending_bytes()`.
 pub struct BufferedSocket {
     socket: Socket,
     in_data: Buffer<u32>,
+    // number of bytes of an incomplete word received, stored right after the contents of in_data
+    in_partial_bytes: usize,
     in_fds: Buffer<RawFd>,
     out_data: Buffer<u32>,
     out_fds: Buffer<RawFd>,
+    out_queue: VecDeque<PendingMessage>,
+    out_queue_bytes: usize,
+    max_pending_bytes: Option<usize>,
+    capture: Option<Box<dyn CaptureHook>>,
+}
+
+// contents of the outgoing buffer that could not be sent yet
+struct PendingMessage {
+    bytes: Vec<u8>,
+    // the fds are cleared once sent along with the first bytes
+    fds: Vec<RawFd>,
+    sent: usize,
 }
 
 impl BufferedSocket {
     /// Wrap a Socket into a Buffered Socket
     pub fn new(socket: Socket) -> BufferedSocket {
+        BufferedSocket::with_max_buffer_size(socket, MAX_BYTES_OUT)
+    }
+
+    /// Wrap a Socket into a Buffered Socket with given buffer size
+    ///
+    /// See `set_max_buffer_size()` for the meaning of `size`.
+    pub fn with_max_buffer_size(socket: Socket, size: usize) -> BufferedSocket {
+        let words = buffer_words(size);
         BufferedSocket {
             socket,
-            in_data: Buffer::new(2 * MAX_BYTES_OUT / 4), // Incoming buffers are twice as big in order to be
-            in_fds: Buffer::new(2 * MAX_FDS_OUT),        // able to store leftover data if needed
-            out_data: Buffer::new(MAX_BYTES_OUT / 4),
+            in_data: Buffer::new(2 * words), // Incoming buffers are twice as big in order to be
+            in_partial_bytes: 0,
+            in_fds: Buffer::new(2 * MAX_FDS_OUT), // able to store leftover data if needed
+            out_data: Buffer::new(words),
             out_fds: Buffer::new(MAX_FDS_OUT),
+            out_queue: VecDeque::new(),
+            out_queue_bytes: 0,
+            max_pending_bytes: None,
+            capture: None,
         }
     }
 
+    /// Set the size of the buffers of this socket
+    ///
+    /// The size is in bytes, and is the maximum size of a message that can be sent or received.
+    /// It is rounded up to a multiple of 4, and sizes smaller than `MAX_BYTES_OUT` are raised
+    /// to `MAX_BYTES_OUT`. Both ends of a connection need to be able to handle the largest
+    /// messages exchanged.
+    ///
+    /// The contents of the buffers are preserved, if they are larger than the new size, the
+    /// buffers are only shrunk to fit them.
+    pub fn set_max_buffer_size(&mut self, size: usize) {
+        let words = buffer_words(size);
+        self.move_incoming_to_front();
+        // the bytes of an incomplete word are stored in the word following the contents
+        let partial_words = if self.in_partial_bytes > 0 { 1 } else { 0 };
+        self.in_data.resize(::std::cmp::max(2 * words, self.in_data.occupied + partial_words));
+        self.out_data.resize(words);
+    }
+
+    /// Get the size of the buffers of this socket, in bytes
+    ///
+    /// This is the maximum size of a message that can be sent or received.
+    pub fn max_buffer_size(&self) -> usize {
+        self.out_data.storage.len() * 4
+    }
+
+    /// Set the maximum size of the outgoing queue, in bytes
+    ///
+    /// When the outgoing buffer is full and cannot be flushed, `write_message()` moves its
+    /// contents to the outgoing queue. If this would make the queue larger than this size, it
+    /// instead fails with `Error::Sys(ENOBUFS)`. `None`, the default, means no limit.
+    pub fn set_max_pending_bytes(&mut self, max: Option<usize>) {
+        self.max_pending_bytes = max;
+    }
+
+    /// Get the number of bytes written to this socket but not sent yet
+    ///
+    /// This includes the contents of the outgoing buffer and of the outgoing queue.
+    pub fn pending_outgoing_bytes(&self) -> usize {
+        self.out_queue_bytes + self.out_data.get_contents().len() * 4
+    }
+
+    /// Set the hook capturing the traffic of this socket
+    ///
+    /// The hook will be given the raw content of all socket messages successfully sent
+    /// or received from now on. Passing `None` stops the capture.
+    ///
+    /// Returns the previously set hook, if any.
+    pub fn set_capture_hook(
+        &mut self,
+        hook: Option<Box<dyn CaptureHook>>,
+    ) -> Option<Box<dyn CaptureHook>> {
+        ::std::mem::replace(&mut self.capture, hook)
+    }
+
     /// Get direct access to the underlying socket
     pub fn get_socket(&mut self) -> &mut Socket {
         &mut self.socket
@@ -135,9 +229,37 @@ impl BufferedSocket {
         self.socket
     }
 
-    /// Flush the contents of the outgoing buffer into the socket
+    /// Flush the contents of the outgoing queue and buffer into the socket
+    ///
+    /// Errors with `WouldBlock` if the socket could not accept all the data, in which case
+    /// the remaining contents are kept for the next call.
     pub fn flush(&mut self) -> NixResult<()> {
-        {
+        // first send the contents of the queue, which are older than the buffer
+        while let Some(pending) = self.out_queue.front_mut() {
+            let fds = ::std::mem::take(&mut pending.fds);
+            let ret = send_and_capture(
+                &self.socket,
+                &mut self.capture,
+                &pending.bytes[pending.sent..],
+                &fds,
+            );
+            let sent = match ret {
+                Ok(sent) => sent,
+                Err(e) => {
+                    pending.fds = fds;
+                    return Err(e);
+                }
+            };
+            pending.sent += sent;
+            self.out_queue_bytes -= sent;
+            if pending.sent < pending.bytes.len() {
+                // the socket is full
+                return Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN));
+            }
+            self.out_queue.pop_front();
+        }
+
+        let sent = {
             let words = self.out_data.get_contents();
             if words.is_empty() {
                 return Ok(());
@@ -146,12 +268,48 @@ impl BufferedSocket {
                 ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4)
             };
             let fds = self.out_fds.get_contents();
-            self.socket.send_msg(bytes, fds)?;
-            for &fd in fds {
-                // once the fds are sent, we can close them
-                let _ = ::nix::unistd::close(fd);
+            let sent = send_and_capture(&self.socket, &mut self.capture, bytes, fds)?;
+            if sent < bytes.len() {
+                // the socket is full, keep the rest for later
+                self.out_queue_bytes += bytes.len() - sent;
+                self.out_queue.push_back(PendingMessage {
+                    bytes: bytes[sent..].to_vec(),
+                    fds: Vec::new(),
+                    sent: 0,
+                });
             }
+            sent == bytes.len()
+        };
+        self.out_data.clear();
+        self.out_fds.clear();
+        if sent {
+            Ok(())
+        } else {
+            Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN))
         }
+    }
+
+    // internal method
+    //
+    // moves the contents of the outgoing buffer to the outgoing queue
+    fn queue_out_buffer(&mut self) -> NixResult<()> {
+        let words = self.out_data.get_contents();
+        if words.is_empty() {
+            return Ok(());
+        }
+        let len = words.len() * 4;
+        if let Some(max) = self.max_pending_bytes {
+            if self.out_queue_bytes + len > max {
+                return Err(::nix::Error::Sys(::nix::errno::Errno::ENOBUFS));
+            }
+        }
+        let bytes = unsafe { ::std::slice::from_raw_parts(words.as_ptr() as *const u8, len) };
+        self.out_queue.push_back(PendingMessage {
+            bytes: bytes.to_vec(),
+            fds: self.out_fds.get_contents().to_vec(),
+            sent: 0,
+        });
+        self.out_queue_bytes += len;
         self.out_data.clear();
         self.out_fds.clear();
         Ok(())
@@ -164,7 +322,7 @@ impl BufferedSocket {
     //
     // if false is returned, it means there is not enough space
     // in the buffer
-    fn attempt_write_message(&mut self, msg: &Message) -> NixResult<bool> {
+    fn attempt_write_message(&mut self, msg: &Message) -> bool {
         match msg.write_to_buffers(
             self.out_data.get_writable_storage(),
             self.out_fds.get_writable_storage(),
@@ -172,67 +330,143 @@ impl BufferedSocket {
             Ok((bytes_out, fds_out)) => {
                 self.out_data.advance(bytes_out);
                 self.out_fds.advance(fds_out);
-                Ok(true)
+                true
             }
-            Err(MessageWriteError::BufferTooSmall) => Ok(false),
-            Err(MessageWriteError::DupFdFailed(e)) => Err(e),
+            Err(MessageWriteError::BufferTooSmall) => false,
         }
     }
 
+    // internal method
+    //
+    // checks if a message could be written in the internal out buffers once they are empty
+    fn fits_in_empty_buffers(&self, msg: &Message) -> bool {
+        // the header, then one word per argument, plus the contents of strings and arrays
+        let mut words = 2;
+        let mut fds = 0;
+        for arg in &msg.args {
+            match *arg {
+                Argument::Str(ref s) => words += 1 + (s.as_bytes_with_nul().len() + 3) / 4,
+                Argument::Array(ref a) => words += 1 + (a.len() + 3) / 4,
+                Argument::Fd(_) => fds += 1,
+                _ => words += 1,
+            }
+        }
+        // the size of the message must fit in the 16 bits of the header
+        words <= self.out_data.storage.len()
+            && words * 4 <= 0xFFFF
+            && fds <= self.out_fds.storage.len()
+    }
+
     /// Write a message to the outgoing buffer
     ///
-    /// This method may flush the internal buffer if necessary (if it is full).
+    /// This method may flush the internal buffer if necessary (if it is full). If the
+    /// socket cannot accept more data, the contents of the buffer are moved to the outgoing
+    /// queue.
     ///
-    /// If the message is too big to fit in the buffer, the error `Error::Sys(E2BIG)`
-    /// will be returned.
-    pub fn write_message(&mut self, msg: &Message) -> NixResult<()> {
-        if !self.attempt_write_message(msg)? {
+    /// If the message is too big to fit in the buffer (see `set_max_buffer_size()`), the
+    /// error `Error::Sys(E2BIG)` will be returned, even if the outgoing queue is full.
+    /// Otherwise, if the outgoing queue is full (see `set_max_pending_bytes()`), the error
+    /// `Error::Sys(ENOBUFS)` will be returned.
+    ///
+    /// The file descriptors of the message are moved to the buffer without being duplicated,
+    /// they are closed once sent, or when the message is dropped if it could not be written.
+    pub fn write_message(&mut self, msg: Message) -> NixResult<()> {
+        if !self.attempt_write_message(&msg) {
             // the attempt failed, there is not enough space in the buffer
-            // we need to flush it
-            self.flush()?;
-            if !self.attempt_write_message(msg)? {
+            // if the message cannot fit in an empty buffer either, it is too
+            // big to be transmitted at all, whatever the state of the queue
+            if !self.fits_in_empty_buffers(&msg) {
+                return Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG));
+            }
+            // otherwise we need to flush it
+            match self.flush() {
+                Ok(()) => {}
+                Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => self.queue_out_buffer()?,
+                Err(e) => return Err(e),
+            }
+            if !self.attempt_write_message(&msg) {
                 // If this fails again, this means the message is too big
                 // to be transmitted at all
                 return Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG));
             }
         }
+        // the fds are now owned by the buffer
+        for arg in msg.args {
+            if let Argument::Fd(fd) = arg {
+                let _ = fd.into_raw_fd();
+            }
+        }
         Ok(())
     }
 
     /// Try to fill the incoming buffers of this socket, to prepare
     /// a new round of parsing.
     pub fn fill_incoming_buffers(&mut self) -> NixResult<()> {
-        // clear the buffers if they have no content
-        if !self.in_data.has_content() {
-            self.in_data.clear();
-        }
-        if !self.in_fds.has_content() {
-            self.in_fds.clear();
+        // make room for the new data, keeping any leftover content
+        self.move_incoming_to_front();
+        let partial = self.in_partial_bytes;
+        if self.in_data.get_writable_storage().len() * 4 <= partial {
+            // the buffer is full of an incomplete message, it is too large to be received
+            return Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG));
         }
         // receive a message
         let (in_bytes, in_fds) = {
             let words = self.in_data.get_writable_storage();
+            // the data is received after the bytes of any incomplete word
             let bytes = unsafe {
-                ::std::slice::from_raw_parts_mut(words.as_ptr() as *mut u8, words.len() * 4)
+                ::std::slice::from_raw_parts_mut(
+                    (words.as_mut_ptr() as *mut u8).add(partial),
+                    words.len() * 4 - partial,
+                )
             };
             let fds = self.in_fds.get_writable_storage();
-            self.socket.rcv_msg(bytes, fds)?
+            let (in_bytes, in_fds) = self.socket.rcv_msg(bytes, fds)?;
+            if let Some(ref mut capture) = self.capture {
+                if in_bytes > 0 {
+                    capture.capture(&Record {
+                        direction: Direction::Received,
+                        timestamp: ::std::time::SystemTime::now(),
+                        bytes: &bytes[..in_bytes],
+                        fds: in_fds,
+                    });
+                }
+            }
+            (in_bytes, in_fds)
         };
         if in_bytes == 0 {
             // the other end of the socket was closed
             return Err(::nix::Error::Sys(::nix::errno::Errno::EPIPE));
         }
-        // advance the storage
-        self.in_data.advance(in_bytes / 4 + if in_bytes % 4 > 0 { 1 } else { 0 });
+        // advance the storage, the data can end with an incomplete word if the other
+        // end could only partially write its message
+        let received = partial + in_bytes;
+        self.in_data.advance(received / 4);
+        self.in_partial_bytes = received & 3;
         self.in_fds.advance(in_fds);
         Ok(())
     }
 
+    // internal method
+    //
+    // moves the unread contents of the incoming buffers to their front, including the
+    // bytes of any incomplete word
+    fn move_incoming_to_front(&mut self) {
+        let partial = self.in_partial_bytes > 0;
+        if partial {
+            self.in_data.advance(1);
+        }
+        self.in_data.move_to_front();
+        if partial {
+            self.in_data.occupied -= 1;
+        }
+        self.in_fds.move_to_front();
+    }
+
     /// Read and deserialize a single message from the incoming buffers socket
     ///
     /// This method requires one closure that given an object id and an opcode,
     /// must provide the signature of the associated request/event, in the form of
-    /// a `&'static [ArgumentType]`. If it returns `None`, meaning that
+    /// a `&[ArgumentType]`. If it returns `None`, meaning that
     /// the couple object/opcode does not exist, an error will be returned.
     ///
     /// There are 3 possibilities of return value:
@@ -243,33 +477,48 @@ impl BufferedSocket {
     /// - `Err(e)`: an I/O error occurred reading from the socked, details are in `e`
     ///   (this can be a "wouldblock" error, which just means that no message is available
     ///   to read)
-    pub fn read_one_message<F>(&mut self, mut signature: F) -> Result<Message, MessageParseError>
+    pub fn read_one_message<'a, F>(&mut self, signature: F) -> Result<Message, MessageParseError>
     where
-        F: FnMut(u32, u16) -> Option<&'static [ArgumentType]>,
+        F: FnMut(u32, u16) -> Option<&'a [ArgumentType]>,
     {
-        let (msg, read_data, read_fd) = {
-            let data = self.in_data.get_contents();
-            let fds = self.in_fds.get_contents();
-            if data.len() < 2 {
-                return Err(MessageParseError::MissingData);
-            }
-            let object_id = data[0];
-            let opcode = (data[1] & 0x0000_FFFF) as u16;
-            if let Some(sig) = signature(object_id, opcode) {
-                match Message::from_raw(data, sig, fds) {
-                    Ok((msg, rest_data, rest_fds)) => {
-                        (msg, data.len() - rest_data.len(), fds.len() - rest_fds.len())
-                    }
-                    // TODO: gracefully handle wayland messages split across unix messages ?
-                    Err(e) => return Err(e),
-                }
-            } else {
-                // no signature found ?
-                return Err(MessageParseError::Malformed);
-            }
+        self.read_one_message_view(signature).map(MessageView::into_owned)
+    }
+
+    /// Read a single message from the incoming buffers socket, without copying its contents
+    ///
+    /// This behaves like `read_one_message()`, but the string and array arguments of the
+    /// returned `MessageView` borrow the internal buffer of the socket, so they are not
+    /// allocated until `MessageView::into_owned()` is called.
+    ///
+    /// The message is consumed from the buffers even if the view is dropped, its file
+    /// descriptors being owned by the view.
+    pub fn read_one_message_view<'a, F>(
+        &mut self,
+        mut signature: F,
+    ) -> Result<MessageView<'_>, MessageParseError>
+    where
+        F: FnMut(u32, u16) -> Option<&'a [ArgumentType]>,
+    {
+        // borrow the fields of the buffers separately, so that the storage can stay borrowed
+        // by the returned message while the offsets are advanced
+        let Buffer { storage: ref data_storage, occupied, offset: ref mut data_offset } =
+            self.in_data;
+        let data = &data_storage[*data_offset..occupied];
+        let fds = self.in_fds.get_contents();
+        if data.len() < 2 {
+            return Err(MessageParseError::MissingData);
+        }
+        let object_id = data[0];
+        let opcode = (data[1] & 0x0000_FFFF) as u16;
+        let sig = match signature(object_id, opcode) {
+            Some(sig) => sig,
+            // no signature found ?
+            None => return Err(MessageParseError::Malformed),
         };
+        let (msg, rest_data, rest_fds) = MessageView::from_raw(data, sig, fds)?;
+        let (read_data, read_fd) = (data.len() - rest_data.len(), fds.len() - rest_fds.len());
 
-        self.in_data.offset(read_data);
+        *data_offset += read_data;
         self.in_fds.offset(read_fd);
 
         Ok(msg)
@@ -332,8 +581,7 @@ impl BufferedSocket {
             }
 
             // copy back any leftover content to the front of the buffer
-            self.in_data.move_to_front();
-            self.in_fds.move_to_front();
+            self.move_incoming_to_front();
 
             if let Some(MessageParseError::Malformed) = err {
                 // early stop here
@@ -366,6 +614,37 @@ impl BufferedSocket {
     }
 }
 
+// send some data to the socket, and report it to the capture hook
+//
+// the fds are closed once sent
+fn send_and_capture(
+    socket: &Socket,
+    capture: &mut Option<Box<dyn CaptureHook>>,
+    bytes: &[u8],
+    fds: &[RawFd],
+) -> NixResult<usize> {
+    let sent = socket.send_msg(bytes, fds)?;
+    if let Some(ref mut capture) = *capture {
+        capture.capture(&Record {
+            direction: Direction::Sent,
+            timestamp: ::std::time::SystemTime::now(),
+            bytes: &bytes[..sent],
+            fds: fds.len(),
+        });
+    }
+    for &fd in fds {
+        // once the fds are sent, we can close them
+        let _ = ::nix::unistd::close(fd);
+    }
+    Ok(sent)
+}
+
+// number of words of the buffers for a given size in bytes
+fn buffer_words(size: usize) -> usize {
+    let size = ::std::cmp::max(size, MAX_BYTES_OUT);
+    size / 4 + if size & 3 != 0 { 1 } else { 0 }
+}
+
 /*
  * Buffer
  */
@@ -418,6 +697,10 @@ impl<T: Copy + Default> Buffer<T> {
     /// Move the unread contents of the buffer to the front, to ensure
     /// maximal write space availability
     fn move_to_front(&mut self) {
+        if !self.has_content() {
+            self.clear();
+            return;
+        }
         unsafe {
             ::std::ptr::copy(
                 &self.storage[self.offset] as *const T,
@@ -428,23 +711,52 @@ impl<T: Copy + Default> Buffer<T> {
         self.occupied -= self.offset;
         self.offset = 0;
     }
+
+    /// Change the size of the buffer, keeping its contents
+    ///
+    /// The buffer is not shrunk below the size of its contents.
+    fn resize(&mut self, size: usize) {
+        self.move_to_front();
+        self.storage.resize(::std::cmp::max(size, self.occupied), T::default());
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::wire::{Argument, ArgumentType, Message};
+    use crate::wire::{BorrowedFd, OwnedFd};
 
     use std::ffi::CString;
+    use std::io::Write;
 
     use smallvec::smallvec;
 
+    // an owned copy of one of the standard streams
+    fn dup_std(fd: RawFd) -> OwnedFd {
+        unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned().unwrap()
+    }
+
     fn same_file(a: RawFd, b: RawFd) -> bool {
         let stat1 = ::nix::sys::stat::fstat(a).unwrap();
         let stat2 = ::nix::sys::stat::fstat(b).unwrap();
         stat1.st_dev == stat2.st_dev && stat1.st_ino == stat2.st_ino
     }
 
+    // a copy of a message, with copies of its fds
+    fn copy_msg(msg: &Message) -> Message {
+        let args = msg.args.iter().map(|arg| match *arg {
+            Argument::Int(i) => Argument::Int(i),
+            Argument::Uint(u) => Argument::Uint(u),
+            Argument::Fixed(f) => Argument::Fixed(f),
+            Argument::Str(ref s) => Argument::Str(s.clone()),
+            Argument::Object(o) => Argument::Object(o),
+            Argument::NewId(n) => Argument::NewId(n),
+            Argument::Array(ref a) => Argument::Array(a.clone()),
+            Argument::Fd(ref fd) => Argument::Fd(fd.try_clone().unwrap()),
+        });
+        Message { sender_id: msg.sender_id, opcode: msg.opcode, args: args.collect() }
+    }
+
     // check if two messages are equal
     //
     // if arguments contain FDs, check that the fd point to
@@ -454,8 +766,8 @@ mod tests {
         assert_eq!(msg1.opcode, msg2.opcode);
         assert_eq!(msg1.args.len(), msg2.args.len());
         for (arg1, arg2) in msg1.args.iter().zip(msg2.args.iter()) {
-            if let (&Argument::Fd(fd1), &Argument::Fd(fd2)) = (arg1, arg2) {
-                assert!(same_file(fd1, fd2));
+            if let (&Argument::Fd(ref fd1), &Argument::Fd(ref fd2)) = (arg1, arg2) {
+                assert!(same_file(fd1.as_raw_fd(), fd2.as_raw_fd()));
             } else {
                 assert_eq!(arg1, arg2);
             }
@@ -482,7 +794,7 @@ mod tests {
         let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
         let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
 
-        client.write_message(&msg).unwrap();
+        client.write_message(copy_msg(&msg)).unwrap();
         client.flush().unwrap();
 
         static SIGNATURE: &'static [ArgumentType] = &[
@@ -521,8 +833,8 @@ mod tests {
             sender_id: 42,
             opcode: 7,
             args: smallvec![
-                Argument::Fd(1), // stdin
-                Argument::Fd(0), // stdout
+                Argument::Fd(dup_std(1)), // stdin
+                Argument::Fd(dup_std(0)), // stdout
             ],
         };
 
@@ -530,7 +842,7 @@ mod tests {
         let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
         let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
 
-        client.write_message(&msg).unwrap();
+        client.write_message(copy_msg(&msg)).unwrap();
         client.flush().unwrap();
 
         static SIGNATURE: &'static [ArgumentType] = &[ArgumentType::Fd, ArgumentType::Fd];
@@ -570,8 +882,8 @@ mod tests {
                 sender_id: 42,
                 opcode: 1,
                 args: smallvec![
-                    Argument::Fd(1), // stdin
-                    Argument::Fd(0), // stdout
+                    Argument::Fd(dup_std(1)), // stdin
+                    Argument::Fd(dup_std(0)), // stdout
                 ],
             },
             Message {
@@ -579,7 +891,7 @@ mod tests {
                 opcode: 2,
                 args: smallvec![
                     Argument::Uint(3),
-                    Argument::Fd(2), // stderr
+                    Argument::Fd(dup_std(2)), // stderr
                 ],
             },
         ];
@@ -595,7 +907,7 @@ mod tests {
         let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
 
         for msg in &messages {
-            client.write_message(msg).unwrap();
+            client.write_message(copy_msg(msg)).unwrap();
         }
         client.flush().unwrap();
 
@@ -640,7 +952,7 @@ mod tests {
         let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
         let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
 
-        client.write_message(&msg).unwrap();
+        client.write_message(copy_msg(&msg)).unwrap();
         client.flush().unwrap();
 
         static SIGNATURE: &'static [ArgumentType] =
@@ -665,4 +977,201 @@ mod tests {
 
         assert_eq!(ret, 1);
     }
+
+    #[test]
+    fn capture_both_directions() {
+        use crate::capture::{CaptureReader, CaptureWriter};
+        use std::sync::{Arc, Mutex};
+
+        struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+        impl ::std::io::Write for SharedWriter {
+            fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> ::std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let msg = Message {
+            sender_id: 42,
+            opcode: 7,
+            args: smallvec![Argument::Uint(3), Argument::Fd(dup_std(1))],
+        };
+
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+        let client_capture = Arc::new(Mutex::new(Vec::new()));
+        let server_capture = Arc::new(Mutex::new(Vec::new()));
+        client.set_capture_hook(Some(Box::new(
+            CaptureWriter::new(SharedWriter(client_capture.clone())).unwrap(),
+        )));
+        server.set_capture_hook(Some(Box::new(
+            CaptureWriter::new(SharedWriter(server_capture.clone())).unwrap(),
+        )));
+
+        client.write_message(msg).unwrap();
+        client.flush().unwrap();
+
+        static SIGNATURE: &[ArgumentType] = &[ArgumentType::Uint, ArgumentType::Fd];
+
+        let ret = server.read_messages(|_, _| Some(SIGNATURE), |_| true).unwrap().unwrap();
+        assert_eq!(ret, 1);
+
+        let sent = CaptureReader::new(&client_capture.lock().unwrap()[..])
+            .unwrap()
+            .collect::<::std::io::Result<Vec<_>>>()
+            .unwrap();
+        let received = CaptureReader::new(&server_capture.lock().unwrap()[..])
+            .unwrap()
+            .collect::<::std::io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(sent.len(), 1);
+        assert_eq!(received.len(), 1);
+        assert_eq!(sent[0].direction, Direction::Sent);
+        assert_eq!(received[0].direction, Direction::Received);
+        assert_eq!(sent[0].bytes, received[0].bytes);
+        assert_eq!(sent[0].bytes.len(), 12);
+        assert_eq!(sent[0].fds, 1);
+        assert_eq!(received[0].fds, 1);
+    }
+
+    #[test]
+    fn large_message() {
+        let msg = Message {
+            sender_id: 3,
+            opcode: 0,
+            args: smallvec![Argument::Str(Box::new(CString::new(vec![b'a'; 10_000]).unwrap()))],
+        };
+
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+        // the message does not fit in the default buffers
+        assert_eq!(
+            client.write_message(copy_msg(&msg)),
+            Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG))
+        );
+
+        client.set_max_buffer_size(16 * 1024);
+        server.set_max_buffer_size(16 * 1024);
+        assert_eq!(client.max_buffer_size(), 16 * 1024);
+
+        client.write_message(copy_msg(&msg)).unwrap();
+        client.flush().unwrap();
+
+        let ret = server
+            .read_messages(
+                |_, _| Some(&[ArgumentType::Str]),
+                |message| {
+                    assert_eq_msgs(&message, &msg);
+                    true
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(ret, 1);
+    }
+
+    #[test]
+    fn shrink_with_partial_message() {
+        let msg = Message {
+            sender_id: 3,
+            opcode: 0,
+            args: smallvec![Argument::Str(Box::new(CString::new(vec![b'a'; 10_000]).unwrap()))],
+        };
+        let mut bytes = vec![0u32; 4096];
+        let (len, _) = msg.write_to_buffers(&mut bytes, &mut []).unwrap();
+        let bytes = unsafe { ::std::slice::from_raw_parts(bytes.as_ptr() as *const u8, len * 4) };
+
+        let (mut client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+        server.set_max_buffer_size(16 * 1024);
+
+        // receive the message up to an incomplete word
+        let split = 9_999;
+        client.write_all(&bytes[..split]).unwrap();
+        server.fill_incoming_buffers().unwrap();
+        match server.read_one_message(|_, _| Some(&[ArgumentType::Str])) {
+            Err(MessageParseError::MissingData) => {}
+            e => panic!("Unexpected result: {:?}", e),
+        }
+
+        // shrinking the buffers below their contents must keep the incomplete word
+        server.set_max_buffer_size(MAX_BYTES_OUT);
+        server.set_max_buffer_size(16 * 1024);
+
+        client.write_all(&bytes[split..]).unwrap();
+        server.fill_incoming_buffers().unwrap();
+        let message = server.read_one_message(|_, _| Some(&[ArgumentType::Str])).unwrap();
+        assert_eq_msgs(&message, &msg);
+    }
+
+    #[test]
+    fn outgoing_queue() {
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+        let msg = |i| Message {
+            sender_id: 3,
+            opcode: 0,
+            args: smallvec![Argument::Uint(i), Argument::Array(Box::new(vec![0; 1000])),],
+        };
+
+        // write until the socket is full, the messages must be queued rather than failing
+        let mut written = 0;
+        while client.pending_outgoing_bytes() <= 2 * MAX_BYTES_OUT {
+            client.write_message(msg(written)).unwrap();
+            written += 1;
+        }
+        assert!(client.flush().is_err());
+
+        // a limit on the queue makes the writes fail once reached
+        client.set_max_pending_bytes(Some(client.pending_outgoing_bytes()));
+        loop {
+            match client.write_message(msg(written)) {
+                Ok(()) => written += 1,
+                Err(e) => {
+                    assert_eq!(e, ::nix::Error::Sys(::nix::errno::Errno::ENOBUFS));
+                    break;
+                }
+            }
+        }
+        // a message too big to be sent at all is reported as such even when the queue is full
+        let large_msg = Message {
+            sender_id: 3,
+            opcode: 0,
+            args: smallvec![Argument::Uint(0), Argument::Array(Box::new(vec![0; 10_000]))],
+        };
+        assert_eq!(
+            client.write_message(large_msg),
+            Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG))
+        );
+        client.set_max_pending_bytes(None);
+
+        // all the messages are received in order once the other end reads them
+        let mut received = 0;
+        while received < written {
+            let _ = client.flush();
+            let ret = server.read_messages(
+                |_, _| Some(&[ArgumentType::Uint, ArgumentType::Array]),
+                |message| {
+                    assert_eq!(message.args[0], Argument::Uint(received));
+                    received += 1;
+                    true
+                },
+            );
+            match ret {
+                Ok(Ok(_)) | Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => {}
+                e => panic!("Unexpected result: {:?}", e),
+            }
+        }
+        assert_eq!(client.pending_outgoing_bytes(), 0);
+    }
 }
@@ -1,7 +1,8 @@
 //! Types and routines used to manipulate arguments from the wire format
 
 use std::ffi::{CStr, CString};
-use std::os::unix::io::RawFd;
+use std::marker::PhantomData;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::ptr;
 
 use nix::errno::Errno;
@@ -47,12 +48,14 @@ pub enum ArgumentType {
     NewId,
     /// Vec<u8>
     Array,
-    /// RawFd
+    /// OwnedFd
     Fd,
 }
 
 /// Enum of possible argument as recognized by the wire, including values
-#[derive(Clone, PartialEq, Debug)]
+///
+/// An `Fd` argument owns its file descriptor, which is closed when the argument is dropped.
+#[derive(PartialEq, Debug)]
 #[allow(clippy::box_vec)]
 pub enum Argument {
     /// i32
@@ -75,8 +78,8 @@ pub enum Argument {
     /// The value is boxed to reduce the stack size of Argument. The performance
     /// impact is negligible as `array` arguments are pretty rare in the protocol.
     Array(Box<Vec<u8>>),
-    /// RawFd
-    Fd(RawFd),
+    /// OwnedFd
+    Fd(OwnedFd),
 }
 
 impl Argument {
@@ -105,13 +108,13 @@ impl std::fmt::Display for Argument {
             Argument::Object(value) => write!(f, "{}", value),
             Argument::NewId(value) => write!(f, "{}", value),
             Argument::Array(value) => write!(f, "{:?}", value),
-            Argument::Fd(value) => write!(f, "{}", value),
+            Argument::Fd(ref value) => write!(f, "{}", value.as_raw_fd()),
         }
     }
 }
 
 /// A wire message
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct Message {
     /// ID of the object sending this message
     pub sender_id: u32,
@@ -121,13 +124,96 @@ pub struct Message {
     pub args: SmallVec<[Argument; INLINE_ARGS]>,
 }
 
+/// Argument of a `MessageView`, borrowing its contents from the parsed buffer
+#[derive(PartialEq, Debug)]
+pub enum ArgumentView<'a> {
+    /// i32
+    Int(i32),
+    /// u32
+    Uint(u32),
+    /// fixed point, 1/256 precision
+    Fixed(i32),
+    /// CStr
+    Str(&'a CStr),
+    /// id of a wayland object
+    Object(u32),
+    /// id of a newly created wayland object
+    NewId(u32),
+    /// [u8]
+    Array(&'a [u8]),
+    /// file descriptor, owned by the view
+    Fd(OwnedFd),
+}
+
+impl<'a> ArgumentView<'a> {
+    /// Retrieve the type of a given argument instance
+    pub fn get_type(&self) -> ArgumentType {
+        match *self {
+            ArgumentView::Int(_) => ArgumentType::Int,
+            ArgumentView::Uint(_) => ArgumentType::Uint,
+            ArgumentView::Fixed(_) => ArgumentType::Fixed,
+            ArgumentView::Str(_) => ArgumentType::Str,
+            ArgumentView::Object(_) => ArgumentType::Object,
+            ArgumentView::NewId(_) => ArgumentType::NewId,
+            ArgumentView::Array(_) => ArgumentType::Array,
+            ArgumentView::Fd(_) => ArgumentType::Fd,
+        }
+    }
+
+    /// Copy the contents of this argument into an owned `Argument`
+    ///
+    /// The file descriptor of an `Fd` argument is moved into the returned `Argument`.
+    pub fn into_owned(self) -> Argument {
+        match self {
+            ArgumentView::Int(v) => Argument::Int(v),
+            ArgumentView::Uint(v) => Argument::Uint(v),
+            ArgumentView::Fixed(v) => Argument::Fixed(v),
+            ArgumentView::Str(v) => Argument::Str(Box::new(v.into())),
+            ArgumentView::Object(v) => Argument::Object(v),
+            ArgumentView::NewId(v) => Argument::NewId(v),
+            ArgumentView::Array(v) => Argument::Array(Box::new(v.into())),
+            ArgumentView::Fd(v) => Argument::Fd(v),
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for ArgumentView<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgumentView::Int(value) => write!(f, "{}", value),
+            ArgumentView::Uint(value) => write!(f, "{}", value),
+            ArgumentView::Fixed(value) => write!(f, "{}", value),
+            ArgumentView::Str(value) => write!(f, "{:?}", value),
+            ArgumentView::Object(value) => write!(f, "{}", value),
+            ArgumentView::NewId(value) => write!(f, "{}", value),
+            ArgumentView::Array(value) => write!(f, "{:?}", value),
+            ArgumentView::Fd(value) => write!(f, "{}", value.as_raw_fd()),
+        }
+    }
+}
+
+/// A wire message borrowing its string and array arguments from the buffer it was parsed from
+///
+/// This avoids allocating for these arguments until the message actually needs to outlive
+/// the buffer, at which point it can be converted into a `Message` using `into_owned()`.
+///
+/// The file descriptors of the message are not borrowed: as with `Message`, they are owned
+/// by the view, and closed when it is dropped.
+#[derive(Debug, PartialEq)]
+pub struct MessageView<'a> {
+    /// ID of the object sending this message
+    pub sender_id: u32,
+    /// Opcode of the message
+    pub opcode: u16,
+    /// Arguments of the message
+    pub args: SmallVec<[ArgumentView<'a>; INLINE_ARGS]>,
+}
+
 /// Error generated when trying to serialize a message into buffers
 #[derive(Debug, Clone)]
 pub enum MessageWriteError {
     /// The buffer is too small to hold the message contents
     BufferTooSmall,
-    /// The message contains a FD that could not be dup-ed
-    DupFdFailed(::nix::Error),
 }
 
 impl std::error::Error for MessageWriteError {}
@@ -138,9 +224,6 @@ impl std::fmt::Display for MessageWriteError {
             MessageWriteError::BufferTooSmall => {
                 f.write_str("The provided buffer is too small to hold message content.")
             }
-            MessageWriteError::DupFdFailed(_) => {
-                f.write_str("The message contains a file descriptor that could not be dup()-ed.")
-            }
         }
     }
 }
@@ -174,12 +257,40 @@ impl std::fmt::Display for MessageParseError {
     }
 }
 
+/// Error generated when a message is too large to be sent on a connection
+///
+/// A message must fit in the outgoing buffer of the connection, whose size can be
+/// configured, and its size cannot exceed 65535 bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTooLarge {
+    /// Interface of the object sending the message
+    pub interface: &'static str,
+    /// Name of the message
+    pub message: &'static str,
+    /// Size of the buffer of the connection, in bytes
+    pub buffer_size: usize,
+}
+
+impl std::error::Error for MessageTooLarge {}
+
+impl std::fmt::Display for MessageTooLarge {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(
+            f,
+            "The message {}.{} is too large to be sent with a buffer of {} bytes.",
+            self.interface, self.message, self.buffer_size
+        )
+    }
+}
+
 impl Message {
     /// Serialize the contents of this message into provided buffers
     ///
     /// Returns the number of elements written in each buffer
     ///
-    /// Any serialized Fd will be `dup()`-ed in the process
+    /// The file descriptors are written without being duplicated, so they are still owned
+    /// by the message. `BufferedSocket::write_message()` takes their ownership once the
+    /// message is written.
     pub fn write_to_buffers(
         &self,
         payload: &mut [u32],
@@ -224,10 +335,6 @@ impl Message {
 
         let (header, mut payload) = payload.split_at_mut(2);
 
-        // we store all fds we dup-ed in this, which will auto-close
-        // them on drop, if any of the `?` early-returns
-        let mut pending_fds = FdStore::new();
-
         // write the contents in the buffer
         for arg in &self.args {
             // Just to make the borrow checker happy
@@ -244,21 +351,20 @@ impl Message {
                 Argument::Array(ref a) => {
                     payload = write_array_to_payload(&a, old_payload)?;
                 }
-                Argument::Fd(fd) => {
+                Argument::Fd(ref fd) => {
                     let old_fds = fds;
-                    let dup_fd = dup_fd_cloexec(fd).map_err(MessageWriteError::DupFdFailed)?;
-                    pending_fds.push(dup_fd);
-                    fds = write_buf(dup_fd, old_fds)?;
+                    fds = write_buf(fd.as_raw_fd(), old_fds)?;
                     payload = old_payload;
                 }
             }
         }
 
-        // we reached here, all writing was successful
-        // no FD needs to be closed
-        pending_fds.clear();
-
         let wrote_size = (free_size - payload.len()) * 4;
+        // the size of the message must fit in the 16 bits of the header
+        if wrote_size > 0xFFFF {
+            return Err(MessageWriteError::BufferTooSmall);
+        }
+
         header[0] = self.sender_id;
         header[1] = ((wrote_size as u32) << 16) | u32::from(self.opcode);
         Ok((orig_payload_len - payload.len(), orig_fds_len - fds.len()))
@@ -271,11 +377,39 @@ impl Message {
     /// the returned slices should thus be empty.
     ///
     /// Errors if the message is malformed.
+    ///
+    /// The file descriptors consumed from `fds` are owned by the returned message, and will
+    /// be closed when it is dropped.
+    ///
+    /// This copies the string and array arguments of the message, see `MessageView::from_raw`
+    /// for a variant borrowing them from `raw`.
     pub fn from_raw<'a, 'b>(
         raw: &'a [u32],
         signature: &[ArgumentType],
         fds: &'b [RawFd],
     ) -> Result<(Message, &'a [u32], &'b [RawFd]), MessageParseError> {
+        MessageView::from_raw(raw, signature, fds)
+            .map(|(msg, rest, fds)| (msg.into_owned(), rest, fds))
+    }
+}
+
+impl<'a> MessageView<'a> {
+    /// Attempts to parse a single wayland message with the given signature, without copying
+    /// its contents.
+    ///
+    /// If the buffers contains several messages, only the first one will be parsed,
+    /// and the unused tail of the buffers is returned. If a single message was present,
+    /// the returned slices should thus be empty.
+    ///
+    /// Errors if the message is malformed.
+    ///
+    /// The file descriptors consumed from `fds` are owned by the returned view, and will
+    /// be closed when it is dropped. They are left untouched if parsing fails.
+    pub fn from_raw<'b>(
+        raw: &'a [u32],
+        signature: &[ArgumentType],
+        fds: &'b [RawFd],
+    ) -> Result<(MessageView<'a>, &'a [u32], &'b [RawFd]), MessageParseError> {
         // helper function to read arrays
         fn read_array_from_payload(
             array_len: usize,
@@ -301,59 +435,86 @@ impl Message {
         let opcode = (word_2 & 0x0000_FFFF) as u16;
         let len = (word_2 >> 16) as usize / 4;
 
-        if len < 2 || len > raw.len() {
+        if len < 2 {
             return Err(MessageParseError::Malformed);
+        } else if len > raw.len() {
+            // the rest of the message has not been received yet
+            return Err(MessageParseError::MissingData);
         }
 
         let (mut payload, rest) = raw.split_at(len);
         payload = &payload[2..];
         let mut fds = fds;
 
-        let arguments = signature
-            .iter()
-            .map(|argtype| {
-                if let ArgumentType::Fd = *argtype {
-                    // don't consume input but fd
-                    if let Some((&front, tail)) = fds.split_first() {
-                        fds = tail;
-                        Ok(Argument::Fd(front))
-                    } else {
-                        Err(MessageParseError::MissingFD)
+        let mut parse_argument = |argtype: &ArgumentType| {
+            if let ArgumentType::Fd = *argtype {
+                // don't consume input but fd
+                if let Some((&front, tail)) = fds.split_first() {
+                    fds = tail;
+                    Ok(ArgumentView::Fd(unsafe { OwnedFd::from_raw_fd(front) }))
+                } else {
+                    Err(MessageParseError::MissingFD)
+                }
+            } else if let Some((&front, mut tail)) = payload.split_first() {
+                let arg = match *argtype {
+                    ArgumentType::Int => Ok(ArgumentView::Int(front as i32)),
+                    ArgumentType::Uint => Ok(ArgumentView::Uint(front)),
+                    ArgumentType::Fixed => Ok(ArgumentView::Fixed(front as i32)),
+                    ArgumentType::Str => {
+                        read_array_from_payload(front as usize, tail).and_then(|(v, rest)| {
+                            tail = rest;
+                            match CStr::from_bytes_with_nul(v) {
+                                Ok(s) => Ok(ArgumentView::Str(s)),
+                                Err(_) => Err(MessageParseError::Malformed),
+                            }
+                        })
                     }
-                } else if let Some((&front, mut tail)) = payload.split_first() {
-                    let arg = match *argtype {
-                        ArgumentType::Int => Ok(Argument::Int(front as i32)),
-                        ArgumentType::Uint => Ok(Argument::Uint(front)),
-                        ArgumentType::Fixed => Ok(Argument::Fixed(front as i32)),
-                        ArgumentType::Str => read_array_from_payload(front as usize, tail)
-                            .and_then(|(v, rest)| {
-                                tail = rest;
-                                match CStr::from_bytes_with_nul(v) {
-                                    Ok(s) => Ok(Argument::Str(Box::new(s.into()))),
-                                    Err(_) => Err(MessageParseError::Malformed),
-                                }
-                            }),
-                        ArgumentType::Object => Ok(Argument::Object(front)),
-                        ArgumentType::NewId => Ok(Argument::NewId(front)),
-                        ArgumentType::Array => {
-                            read_array_from_payload(front as usize, tail).map(|(v, rest)| {
-                                tail = rest;
-                                Argument::Array(Box::new(v.into()))
-                            })
+                    ArgumentType::Object => Ok(ArgumentView::Object(front)),
+                    ArgumentType::NewId => Ok(ArgumentView::NewId(front)),
+                    ArgumentType::Array => {
+                        read_array_from_payload(front as usize, tail).map(|(v, rest)| {
+                            tail = rest;
+                            ArgumentView::Array(v)
+                        })
+                    }
+                    ArgumentType::Fd => unreachable!(),
+                };
+                payload = tail;
+                arg
+            } else {
+                Err(MessageParseError::MissingData)
+            }
+        };
+
+        let mut arguments = SmallVec::with_capacity(signature.len());
+        for argtype in signature {
+            match parse_argument(argtype) {
+                Ok(arg) => arguments.push(arg),
+                Err(e) => {
+                    // the file descriptors stay in the buffer of the caller, which may parse
+                    // this message again once more data is available
+                    for arg in arguments {
+                        if let ArgumentView::Fd(fd) = arg {
+                            let _ = fd.into_raw_fd();
                         }
-                        ArgumentType::Fd => unreachable!(),
-                    };
-                    payload = tail;
-                    arg
-                } else {
-                    Err(MessageParseError::MissingData)
+                    }
+                    return Err(e);
                 }
-            })
-            .collect::<Result<SmallVec<_>, MessageParseError>>()?;
+            }
+        }
 
-        let msg = Message { sender_id, opcode, args: arguments };
+        let msg = MessageView { sender_id, opcode, args: arguments };
         Ok((msg, rest, fds))
     }
+
+    /// Copy the contents of this message into an owned `Message`
+    pub fn into_owned(self) -> Message {
+        Message {
+            sender_id: self.sender_id,
+            opcode: self.opcode,
+            args: self.args.into_iter().map(ArgumentView::into_owned).collect(),
+        }
+    }
 }
 
 /// Duplicate a `RawFd` and set the CLOEXEC flag on the copy
@@ -386,36 +547,112 @@ pub fn dup_fd_cloexec(fd: RawFd) -> NixResult<RawFd> {
     }
 }
 
-/*
- * utility struct that closes every FD it contains on drop
- */
+/// An owned file descriptor
+///
+/// The file descriptor is closed when this value is dropped, unless ownership is taken
+/// back using `into_raw_fd()`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedFd {
+    fd: RawFd,
+}
 
-struct FdStore {
-    fds: Vec<RawFd>,
+impl OwnedFd {
+    /// Duplicate this file descriptor
+    ///
+    /// The copy has the CLOEXEC flag set.
+    pub fn try_clone(&self) -> NixResult<OwnedFd> {
+        dup_fd_cloexec(self.fd).map(|fd| OwnedFd { fd })
+    }
+
+    /// Borrow this file descriptor
+    pub fn borrow_fd(&self) -> BorrowedFd<'_> {
+        BorrowedFd::new(self)
+    }
 }
 
-impl FdStore {
-    fn new() -> FdStore {
-        FdStore { fds: Vec::new() }
+impl AsRawFd for OwnedFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
     }
-    fn push(&mut self, fd: RawFd) {
-        self.fds.push(fd);
+}
+
+impl IntoRawFd for OwnedFd {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        ::std::mem::forget(self);
+        fd
     }
-    fn clear(&mut self) {
-        self.fds.clear();
+}
+
+impl FromRawFd for OwnedFd {
+    unsafe fn from_raw_fd(fd: RawFd) -> OwnedFd {
+        OwnedFd { fd }
+    }
+}
+
+impl From<::std::fs::File> for OwnedFd {
+    fn from(file: ::std::fs::File) -> OwnedFd {
+        OwnedFd { fd: file.into_raw_fd() }
     }
 }
 
-impl Drop for FdStore {
+impl From<OwnedFd> for ::std::fs::File {
+    fn from(fd: OwnedFd) -> ::std::fs::File {
+        unsafe { ::std::fs::File::from_raw_fd(fd.into_raw_fd()) }
+    }
+}
+
+impl Drop for OwnedFd {
     fn drop(&mut self) {
-        use nix::unistd::close;
-        for fd in self.fds.drain(..) {
-            // not much can be done if we can't close that anyway...
-            let _ = close(fd);
+        // not much can be done if we can't close that anyway...
+        let _ = ::nix::unistd::close(self.fd);
+    }
+}
+
+/// A borrowed file descriptor
+///
+/// This is used to send a file descriptor that remains owned by the caller: it is
+/// `dup()`-ed when the message is created.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BorrowedFd<'a> {
+    fd: RawFd,
+    _source: PhantomData<&'a OwnedFd>,
+}
+
+impl<'a> BorrowedFd<'a> {
+    /// Borrow the file descriptor of an object
+    pub fn new<T: AsRawFd + ?Sized>(source: &'a T) -> BorrowedFd<'a> {
+        BorrowedFd { fd: source.as_raw_fd(), _source: PhantomData }
+    }
+
+    /// Borrow a raw file descriptor
+    ///
+    /// # Safety
+    ///
+    /// The file descriptor must remain open for the lifetime `'a`.
+    pub unsafe fn borrow_raw(fd: RawFd) -> BorrowedFd<'a> {
+        BorrowedFd { fd, _source: PhantomData }
+    }
+
+    /// Duplicate this file descriptor into an owned one
+    ///
+    /// The copy has the CLOEXEC flag set. This fails if the process cannot open more file
+    /// descriptors, which is reported as an `std::io::Error` for the generated code to
+    /// return it to its callers.
+    pub fn try_clone_to_owned(&self) -> std::io::Result<OwnedFd> {
+        match dup_fd_cloexec(self.fd) {
+            Ok(fd) => Ok(OwnedFd { fd }),
+            Err(e) => Err(e.as_errno().unwrap_or(Errno::EINVAL).into()),
         }
     }
 }
 
+impl<'a> AsRawFd for BorrowedFd<'a> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -458,4 +695,106 @@ mod tests {
         .unwrap();
         assert_eq!(rebuilt, msg);
     }
+
+    #[test]
+    fn view_borrows_buffer() {
+        let mut bytes_buffer = vec![0; 1024];
+        let mut fd_buffer = vec![0; 10];
+
+        let msg = Message {
+            sender_id: 3,
+            opcode: 1,
+            args: smallvec![
+                Argument::Str(Box::new(CString::new(&b"text-input"[..]).unwrap())),
+                Argument::Array(vec![7, 8, 9].into()),
+                Argument::Uint(12),
+            ],
+        };
+        let (words, _) = msg.write_to_buffers(&mut bytes_buffer[..], &mut fd_buffer[..]).unwrap();
+        let raw = &bytes_buffer[..words];
+        let (view, rest, _) = MessageView::from_raw(
+            raw,
+            &[ArgumentType::Str, ArgumentType::Array, ArgumentType::Uint],
+            &fd_buffer[..],
+        )
+        .unwrap();
+        assert!(rest.is_empty());
+        let in_raw = |ptr: *const u8| {
+            let start = raw.as_ptr() as usize;
+            (start..start + raw.len() * 4).contains(&(ptr as usize))
+        };
+        match view.args[..] {
+            [ArgumentView::Str(s), ArgumentView::Array(a), ArgumentView::Uint(12)] => {
+                assert_eq!(s.to_bytes(), b"text-input");
+                assert_eq!(a, &[7, 8, 9]);
+                assert!(in_raw(s.as_ptr() as *const u8));
+                assert!(in_raw(a.as_ptr()));
+            }
+            _ => panic!("Unexpected arguments: {:?}", view.args),
+        }
+        assert_eq!(view.into_owned(), msg);
+    }
+
+    #[test]
+    fn owned_fd_ownership() {
+        use nix::unistd::{close, pipe, read, write};
+
+        let (reader, writer) = pipe().unwrap();
+        // the message owns the fd, and writing it to the buffers does not dup it
+        let mut msg = Message {
+            sender_id: 1,
+            opcode: 0,
+            args: smallvec![Argument::Fd(unsafe { OwnedFd::from_raw_fd(writer) })],
+        };
+        let mut bytes_buffer = vec![0; 16];
+        let mut fd_buffer = vec![0; 1];
+        msg.write_to_buffers(&mut bytes_buffer[..], &mut fd_buffer[..]).unwrap();
+        assert_eq!(fd_buffer[0], writer);
+
+        // into_raw_fd() gives ownership back without closing
+        let fd = match msg.args.pop() {
+            Some(Argument::Fd(fd)) => fd.into_raw_fd(),
+            _ => unreachable!(),
+        };
+        drop(msg);
+        write(fd, b"ok").unwrap();
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        // both ends have been closed, the pipe reports EOF after the contents
+        let mut buffer = [0; 4];
+        assert_eq!(read(reader, &mut buffer).unwrap(), 2);
+        assert_eq!(read(reader, &mut buffer).unwrap(), 0);
+        close(reader).unwrap();
+    }
+
+    #[test]
+    fn view_fd_ownership() {
+        use nix::unistd::{close, pipe, read, write};
+
+        let (reader, writer) = pipe().unwrap();
+        let signature = [ArgumentType::Fd, ArgumentType::Uint];
+        // header of a message with a single uint argument
+        let raw = [1, (12 << 16) | 2, 42];
+        let fds = [writer];
+
+        // a failed parse does not take ownership of the fds
+        assert!(MessageView::from_raw(&raw[..2], &signature, &fds).is_err());
+        write(writer, b"ok").unwrap();
+
+        // the view owns the fd, and closes it when dropped
+        let (view, _, rest_fds) = MessageView::from_raw(&raw, &signature, &fds).unwrap();
+        assert!(rest_fds.is_empty());
+        match view.args[..] {
+            [ArgumentView::Fd(ref fd), ArgumentView::Uint(42)] => {
+                assert_eq!(fd.as_raw_fd(), writer)
+            }
+            _ => panic!("Unexpected arguments: {:?}", view.args),
+        }
+        drop(view);
+
+        let mut buffer = [0; 4];
+        assert_eq!(read(reader, &mut buffer).unwrap(), 2);
+        assert_eq!(read(reader, &mut buffer).unwrap(), 0);
+        close(reader).unwrap();
+    }
 }
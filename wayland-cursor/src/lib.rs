@@ -43,7 +43,7 @@ use std::env;
 use std::fs::File;
 use std::io::{Error as IoError, Read, Result as IoResult, Seek, SeekFrom, Write};
 use std::ops::{Deref, Index};
-use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::io::{FromRawFd, RawFd};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use nix::errno::Errno;
@@ -56,7 +56,7 @@ use {nix::sys::memfd, std::ffi::CStr};
 use wayland_client::protocol::wl_buffer::WlBuffer;
 use wayland_client::protocol::wl_shm::{Format, WlShm};
 use wayland_client::protocol::wl_shm_pool::WlShmPool;
-use wayland_client::{Attached, Main};
+use wayland_client::{Attached, BorrowedFd, Main};
 
 use xcursor::parser as xparser;
 use xcursor::CursorTheme as XCursorTheme;
@@ -115,7 +115,9 @@ impl CursorTheme {
         // Flush to ensure the compositor has access to the buffer when it tries to map it.
         file.flush().expect("Flush on shm fd failed");
 
-        let pool = shm.create_pool(file.as_raw_fd(), INITIAL_POOL_SIZE);
+        let pool = shm
+            .create_pool(BorrowedFd::new(&file), INITIAL_POOL_SIZE)
+            .expect("Failed to duplicate the shm fd");
 
         let name = String::from(name);
 
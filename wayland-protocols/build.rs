@@ -1,7 +1,7 @@
 extern crate wayland_scanner;
 
 use std::env::var;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use wayland_scanner::*;
 
 type StableProtocol<'a> = (&'a str, &'a [(&'a str, &'a str)]);
@@ -59,34 +59,72 @@ fn generate_protocol(
     name: &str,
     protocol_file: &Path,
     out_dir: &Path,
-    client: bool,
-    server: bool,
+    registries: &[InterfaceRegistry],
     dest_events: &[(&str, &str)],
-) {
+) -> bool {
     println!("cargo:rerun-if-changed={}", protocol_file.display());
 
-    if client {
-        generate_code_with_destructor_events(
+    for registry in registries {
+        let side = match registry.side() {
+            Side::Client => "client",
+            Side::Server => "server",
+        };
+        let options = GenerationOptions::new(registry.side())
+            .registry(registry.clone())
+            .destructor_events(dest_events)
+            .serde(enabled(&format!("{}-serde", side)))
+            .cargo_warnings(true);
+        if let Err(e) = try_generate_code_with_options(
             &protocol_file,
-            out_dir.join(&format!("{}_client_api.rs", name)),
-            Side::Client,
-            dest_events,
-        );
+            out_dir.join(&format!("{}_{}_api.rs", name, side)),
+            &options,
+        ) {
+            panic!(
+                "Unable to generate code for protocol file `{}`: {}",
+                protocol_file.display(),
+                e
+            );
+        }
     }
-    if server {
-        generate_code_with_destructor_events(
-            &protocol_file,
-            out_dir.join(&format!("{}_server_api.rs", name)),
-            Side::Server,
-            dest_events,
-        );
+
+    // whether the generated code uses `bitflags`
+    !registries.is_empty() && has_bitfield(protocol_file)
+}
+
+fn has_bitfield(protocol_file: &Path) -> bool {
+    let contents = std::fs::read_to_string(protocol_file).unwrap_or_else(|e| {
+        panic!("Unable to read protocol file `{}`: {}", protocol_file.display(), e)
+    });
+    contents.contains("bitfield=\"true\"")
+}
+
+// the interfaces other protocols can depend on: the core protocol, and the stable
+// and misc protocols
+fn registry(side: Side, stable_files: &[(String, PathBuf)]) -> InterfaceRegistry {
+    let side_name = match side {
+        Side::Client => "client",
+        Side::Server => "server",
+    };
+    let mut registry = InterfaceRegistry::new(side);
+    for (module, file) in stable_files {
+        registry
+            .add_protocol(file, &format!("crate::{}::{}", module, side_name))
+            .unwrap_or_else(|e| panic!("Unable to read protocol file `{}`: {}", file.display(), e));
     }
+    registry
+}
+
+// whether the cargo feature of a protocol is enabled
+fn enabled(feature: &str) -> bool {
+    var(format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"))).is_ok()
 }
 
 fn main() {
-    println!("cargo:rerun-if-changed-env=CARGO_FEATURE_CLIENT");
-    println!("cargo:rerun-if-changed-env=CARGO_FEATURE_SERVER");
-    println!("cargo:rerun-if-changed-env=CARGO_FEATURE_UNSTABLE_PROTOCOLS");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_CLIENT");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_SERVER");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_UNSTABLE_PROTOCOLS");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_CLIENT_SERDE");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_SERVER_SERDE");
 
     let out_dir_str = var("OUT_DIR").unwrap();
     let out_dir = Path::new(&out_dir_str);
@@ -94,57 +132,91 @@ fn main() {
     let client = var("CARGO_FEATURE_CLIENT").ok().is_some();
     let server = var("CARGO_FEATURE_SERVER").ok().is_some();
 
-    for &(name, dest_events) in STABLE_PROTOCOLS {
-        let file = format!("{name}/{name}.xml", name = name);
-        generate_protocol(
-            name,
-            &Path::new("./protocols/stable").join(&file),
-            out_dir,
-            client,
-            server,
-            dest_events,
-        );
+    let stable_files = STABLE_PROTOCOLS
+        .iter()
+        .map(|&(name, _)| {
+            let file = format!("{name}/{name}.xml", name = name);
+            (name.replace('-', "_"), Path::new("./protocols/stable").join(&file))
+        })
+        .chain(MISC_PROTOCOLS.iter().map(|&(name, _)| {
+            let file = format!("{name}.xml", name = name);
+            (format!("misc::{}", name.replace('-', "_")), Path::new("./misc").join(&file))
+        }))
+        .collect::<Vec<_>>();
+
+    let mut registries = Vec::new();
+    if client {
+        registries.push(registry(Side::Client, &stable_files));
+    }
+    if server {
+        registries.push(registry(Side::Server, &stable_files));
+    }
+
+    let mut bitfields = false;
+    for (&(name, dest_events), (_, file)) in
+        STABLE_PROTOCOLS.iter().chain(MISC_PROTOCOLS).zip(&stable_files)
+    {
+        if enabled(name) {
+            bitfields |= generate_protocol(name, file, out_dir, &registries, dest_events);
+        }
     }
 
-    for &(name, dest_events) in MISC_PROTOCOLS {
-        let file = format!("{name}.xml", name = name);
-        generate_protocol(
-            name,
-            &Path::new("./misc").join(&file),
-            out_dir,
-            client,
-            server,
-            dest_events,
-        );
+    // the `unstable` modules are only compiled if they contain a protocol, and the
+    // protocol macros and reexports only if they are used
+    println!("cargo:rustc-check-cfg=cfg(protocols_enabled)");
+    println!("cargo:rustc-check-cfg=cfg(stable_protocols_enabled)");
+    println!("cargo:rustc-check-cfg=cfg(unstable_protocols_enabled)");
+    println!("cargo:rustc-check-cfg=cfg(wlr_unstable_protocols_enabled)");
+    println!("cargo:rustc-check-cfg=cfg(bitfields_enabled)");
+    let stable = STABLE_PROTOCOLS.iter().any(|&(name, _)| enabled(name));
+    let misc = MISC_PROTOCOLS.iter().any(|&(name, _)| enabled(name));
+    let unstable =
+        UNSTABLE_PROTOCOLS.iter().any(|&(name, _)| enabled(&format!("unstable-{}", name)));
+    let wlr_unstable = WLR_UNSTABLE_PROTOCOLS.iter().any(|&(name, _)| enabled(name));
+    if stable || misc || unstable || wlr_unstable {
+        println!("cargo:rustc-cfg=protocols_enabled");
+    }
+    if stable {
+        println!("cargo:rustc-cfg=stable_protocols_enabled");
+    }
+    if unstable {
+        println!("cargo:rustc-cfg=unstable_protocols_enabled");
+    }
+    if wlr_unstable {
+        println!("cargo:rustc-cfg=wlr_unstable_protocols_enabled");
     }
 
-    if var("CARGO_FEATURE_UNSTABLE_PROTOCOLS").ok().is_some() {
-        for &(name, versions) in UNSTABLE_PROTOCOLS {
+    for &(name, versions) in UNSTABLE_PROTOCOLS {
+        if enabled(&format!("unstable-{}", name)) {
             for &(version, dest_events) in versions {
                 let file =
                     format!("{name}/{name}-unstable-{version}.xml", name = name, version = version);
-                generate_protocol(
+                bitfields |= generate_protocol(
                     &format!("{name}-{version}", name = name, version = version),
                     &Path::new("./protocols/unstable").join(file),
                     out_dir,
-                    client,
-                    server,
+                    &registries,
                     dest_events,
                 );
             }
         }
-        for &(name, versions) in WLR_UNSTABLE_PROTOCOLS {
+    }
+    for &(name, versions) in WLR_UNSTABLE_PROTOCOLS {
+        if enabled(name) {
             for &(version, dest_events) in versions {
                 let file = format!("{name}-unstable-{version}.xml", name = name, version = version);
-                generate_protocol(
+                bitfields |= generate_protocol(
                     &format!("{name}-{version}", name = name, version = version),
                     &Path::new("./wlr-protocols/unstable").join(file),
                     out_dir,
-                    client,
-                    server,
+                    &registries,
                     dest_events,
                 );
             }
         }
     }
+
+    if bitfields {
+        println!("cargo:rustc-cfg=bitfields_enabled");
+    }
 }
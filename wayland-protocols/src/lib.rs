@@ -11,22 +11,38 @@
 //! to protocols that are not yet considered stable. As such, no stability guarantee is
 //! given for these protocols.
 //!
+//! Each protocol also has its own cargo feature, named after its XML file: `xdg-shell`,
+//! `viewporter`, `gtk-primary-selection`... The features of the unstable protocols of
+//! wayland-protocols are prefixed with `unstable-`, like `unstable-xdg-decoration`, and the
+//! ones of wlr-protocols are named like `wlr-layer-shell`. The `stable_protocols` feature,
+//! enabled by default, enables all the stable and misc protocols, and `unstable_protocols`
+//! enables all the unstable ones. To only compile the protocols you use, disable the default
+//! features and enable the features of these protocols, along with `client` or `server`.
+//! Protocols depending on other protocols enable their features.
+//!
+//! The cargo features `client-serde` and `server-serde` implement `Serialize` for the `Request`
+//! and `Event` enums of the protocols of each side, along with their `deserialize_raw()` method,
+//! and `Serialize` and `Deserialize` for their other enums. They enable the `serde` feature of
+//! `wayland-client` or `wayland-server`, as the core protocol needs it as well.
+//!
 //! Some protocols require unstable rust features, the inclusion of them is controlled
 //! by the cargo feature `nightly`.
 
 #![warn(missing_docs)]
 
+#[cfg(bitfields_enabled)]
 #[macro_use]
 extern crate bitflags;
 
 #[macro_use]
 mod protocol_macro;
 
-#[cfg(feature = "unstable_protocols")]
+#[cfg(unstable_protocols_enabled)]
 pub mod unstable;
 
 pub mod misc;
 pub mod wlr;
 
 mod stable;
+#[cfg(stable_protocols_enabled)]
 pub use stable::*;
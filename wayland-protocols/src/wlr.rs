@@ -5,7 +5,7 @@
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
-#[cfg(feature = "unstable_protocols")]
+#[cfg(wlr_unstable_protocols_enabled)]
 pub mod unstable {
     //! Unstable protocols from wlr-protocols
     //!
@@ -21,88 +21,67 @@ pub mod unstable {
     //! interface names are removed and the interface version number is
     //! reset.
 
+    #[cfg(feature = "wlr-data-control")]
     pub mod data_control {
         //! Control data devices, particularly the clipboard.
         //!
         //! An interface to control data devices, particularly to manage the current selection and
         //! take the role of a clipboard manager.
 
-        wayland_protocol_versioned!("wlr-data-control", [v1], [(wl_seat, wl_seat_interface)], []);
+        wayland_protocol_versioned!("wlr-data-control", [v1]);
     }
 
+    #[cfg(feature = "wlr-export-dmabuf")]
     pub mod export_dmabuf {
         //! A protocol for low overhead screen content capturing
         //!
         //! An interface to capture surfaces in an efficient way by exporting DMA-BUFs.
 
-        wayland_protocol_versioned!(
-            "wlr-export-dmabuf",
-            [v1],
-            [(wl_output, wl_output_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-export-dmabuf", [v1]);
     }
 
+    #[cfg(feature = "wlr-foreign-toplevel-management")]
     pub mod foreign_toplevel {
         //! List and control opened apps
         //!
         //! Use for creating taskbars and docks.
 
-        wayland_protocol_versioned!(
-            "wlr-foreign-toplevel-management",
-            [v1],
-            [
-                (wl_seat, wl_seat_interface),
-                (wl_surface, wl_surface_interface),
-                (wl_output, wl_output_interface)
-            ],
-            []
-        );
+        wayland_protocol_versioned!("wlr-foreign-toplevel-management", [v1]);
     }
 
+    #[cfg(feature = "wlr-gamma-control")]
     pub mod gamma_control {
         //! Manage gamma tables of outputs.
         //!
         //! This protocol allows a privileged client to set the gamma tables for outputs.
 
-        wayland_protocol_versioned!(
-            "wlr-gamma-control",
-            [v1],
-            [(wl_output, wl_output_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-gamma-control", [v1]);
     }
 
+    #[cfg(feature = "wlr-input-inhibitor")]
     pub mod input_inhibitor {
         //! Inhibits input events to other clients
 
-        wayland_protocol_versioned!("wlr-input-inhibitor", [v1], [], []);
+        wayland_protocol_versioned!("wlr-input-inhibitor", [v1]);
     }
 
+    #[cfg(feature = "wlr-layer-shell")]
     pub mod layer_shell {
         //! Layered shell protocol
 
-        wayland_protocol_versioned!(
-            "wlr-layer-shell",
-            [v1],
-            [(wl_output, wl_output_interface), (wl_surface, wl_surface_interface)],
-            [(xdg_shell, xdg_popup, xdg_popup_interface)]
-        );
+        wayland_protocol_versioned!("wlr-layer-shell", [v1]);
     }
 
+    #[cfg(feature = "wlr-output-management")]
     pub mod output_management {
         //! Output management protocol
         //!
         //! This protocol exposes interfaces to obtain and modify output device configuration.
 
-        wayland_protocol_versioned!(
-            "wlr-output-management",
-            [v1],
-            [(wl_output, wl_output_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-output-management", [v1]);
     }
 
+    #[cfg(feature = "wlr-output-power-management")]
     pub mod output_power_management {
         //! Output power management protocol
         //!
@@ -111,40 +90,27 @@ pub mod unstable {
         //! intent is to allow special clients like desktop shells to power
         //! down outputs when the system is idle.
 
-        wayland_protocol_versioned!(
-            "wlr-output-power-management",
-            [v1],
-            [(wl_output, wl_output_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-output-power-management", [v1]);
     }
 
+    #[cfg(feature = "wlr-screencopy")]
     pub mod screencopy {
         //! Screen content capturing on client buffers
         //!
         //! This protocol allows clients to ask the compositor to copy part of the
         //! screen content to a client buffer.
 
-        wayland_protocol_versioned!(
-            "wlr-screencopy",
-            [v1],
-            [(wl_buffer, wl_buffer_interface), (wl_output, wl_output_interface), (wl_shm, wl_shm_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-screencopy", [v1]);
     }
 
+    #[cfg(feature = "wlr-virtual-pointer")]
     pub mod virtual_pointer {
         //! Virtual pointer protocol
         //!
         //! This protocol allows clients to emulate a physical pointer device. The
         //! requests are mostly mirror opposites of those specified in wl_pointer.
 
-        wayland_protocol_versioned!(
-            "wlr-virtual-pointer",
-            [v1],
-            [(wl_seat, wl_seat_interface), (wl_output, wl_output_interface), (wl_pointer, wl_pointer_interface)],
-            []
-        );
+        wayland_protocol_versioned!("wlr-virtual-pointer", [v1]);
     }
 
 
@@ -0,0 +1,57 @@
+// Built with `--no-default-features --features "client server unstable_protocols"`: the
+// umbrella feature must enable every protocol of its group, along with the stable ones.
+#![cfg(all(feature = "client", feature = "server", feature = "unstable_protocols"))]
+
+extern crate wayland_client;
+extern crate wayland_protocols;
+extern crate wayland_server;
+
+use wayland_client::Interface as ClientInterface;
+use wayland_server::Interface as ServerInterface;
+
+use wayland_protocols::{misc, presentation_time, unstable, viewporter, wlr, xdg_shell};
+
+#[test]
+fn stable_protocols() {
+    assert_eq!(<xdg_shell::client::xdg_wm_base::XdgWmBase as ClientInterface>::NAME, "xdg_wm_base");
+    assert_eq!(
+        <presentation_time::server::wp_presentation::WpPresentation as ServerInterface>::NAME,
+        "wp_presentation"
+    );
+    assert_eq!(
+        <viewporter::client::wp_viewporter::WpViewporter as ClientInterface>::NAME,
+        "wp_viewporter"
+    );
+    assert_eq!(
+        <misc::gtk_primary_selection::client::gtk_primary_selection_device_manager::GtkPrimarySelectionDeviceManager as ClientInterface>::NAME,
+        "gtk_primary_selection_device_manager"
+    );
+}
+
+#[test]
+fn unstable_protocols() {
+    assert_eq!(
+        <unstable::xdg_decoration::v1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1 as ClientInterface>::NAME,
+        "zxdg_decoration_manager_v1"
+    );
+    assert_eq!(
+        <unstable::tablet::v2::server::zwp_tablet_manager_v2::ZwpTabletManagerV2 as ServerInterface>::NAME,
+        "zwp_tablet_manager_v2"
+    );
+    assert_eq!(
+        <unstable::xdg_shell::v6::client::zxdg_shell_v6::ZxdgShellV6 as ClientInterface>::NAME,
+        "zxdg_shell_v6"
+    );
+}
+
+#[test]
+fn wlr_unstable_protocols() {
+    assert_eq!(
+        <wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1::ZwlrLayerShellV1 as ClientInterface>::NAME,
+        "zwlr_layer_shell_v1"
+    );
+    assert_eq!(
+        <wlr::unstable::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1 as ServerInterface>::NAME,
+        "zwlr_virtual_pointer_manager_v1"
+    );
+}
@@ -0,0 +1,223 @@
+//! Procedural macro generating the code of Wayland protocols
+//!
+//! This crate provides the `generate_protocol!` macro, which generates the code to use a
+//! protocol with the `wayland_client` or `wayland_server` crates directly in the module it
+//! is invoked in. It is an alternative to calling `wayland_scanner::generate_code` from a
+//! build script and including the generated file, and uses the same code generation.
+//!
+//! ```ignore
+//! // The generated code uses wayland_commons, and wayland_client or wayland_server
+//! // depending on the side, which need to be dependencies of your crate.
+//! pub mod my_protocol {
+//!     // If your protocol interacts with objects from other protocols, you only need to
+//!     // make their modules available in the module invoking the macro:
+//!     use wayland_protocols::xdg_shell::client::xdg_surface;
+//!
+//!     // The path of the XML file is relative to the `Cargo.toml` of your crate
+//!     wayland_scanner_macros::generate_protocol!("./my_protocol.xml", client);
+//! }
+//!
+//! // You can then use the types of the protocol as if they came from `wayland_client::protocol`
+//! use my_protocol::my_interface::MyInterface;
+//! ```
+//!
+//! Interfaces of the core protocol (like `wl_surface`) referenced by the protocol are imported
+//! automatically from the `protocol` module of `wayland_client` or `wayland_server`.
+//!
+//! Some events can be marked as destructors, as this information is not encoded in the
+//! protocol files (see `wayland_scanner::generate_code_with_destructor_events`):
+//!
+//! ```ignore
+//! wayland_scanner_macros::generate_protocol!(
+//!     "./my_protocol.xml",
+//!     server,
+//!     [("my_callback", "done")]
+//! );
+//! ```
+//!
+//! The protocol is checked with `wayland_scanner::lint_protocol`, errors are reported as
+//! compilation errors.
+
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+
+use std::path::PathBuf;
+
+use proc_macro::{Delimiter, Literal, TokenStream, TokenTree};
+
+use wayland_scanner::Side;
+
+/// Generate the code of a protocol for `wayland_client` or `wayland_server`
+///
+/// Takes the path of the protocol XML file, relative to the `Cargo.toml` of the crate, the side
+/// to generate the code for (`client` or `server`), and optionally a list of the events
+/// to be considered as destructors, as `("interface_name", "event_name")` pairs.
+///
+/// See the crate documentation for details.
+#[proc_macro]
+pub fn generate_protocol(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(tokens) => tokens,
+        Err(msg) => compile_error(&msg),
+    }
+}
+
+struct Args {
+    path: String,
+    side: Side,
+    events: Vec<(String, String)>,
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let args = parse_args(input)?;
+
+    let mut path = PathBuf::from(
+        std::env::var_os("CARGO_MANIFEST_DIR")
+            .ok_or_else(|| "CARGO_MANIFEST_DIR is not set".to_string())?,
+    );
+    path.push(&args.path);
+
+    let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Unable to open protocol file `{}`: {}", path.display(), e))?;
+    let events = args.events.iter().map(|(i, e)| (i.as_str(), e.as_str())).collect::<Vec<_>>();
+    let code =
+        wayland_scanner::generate_protocol_module(file, args.side, &events).map_err(|e| {
+            format!("Unable to generate code for protocol file `{}`: {}", path.display(), e)
+        })?;
+
+    // include the protocol file so that the crate is rebuilt when it changes
+    let mut output: TokenStream = "const _: &[u8] = include_bytes!".parse().unwrap();
+    output.extend(Some(TokenTree::Group(proc_macro::Group::new(
+        Delimiter::Parenthesis,
+        TokenTree::Literal(Literal::string(&path.to_string_lossy())).into(),
+    ))));
+    output.extend(";".parse::<TokenStream>().unwrap());
+    // the code generation produces fallback tokens, which we convert through their text form
+    output.extend(code.to_string().parse::<TokenStream>().map_err(|e| format!("{:?}", e))?);
+    Ok(output)
+}
+
+const USAGE: &str = "expected `\"path/to/protocol.xml\", client` or `\"path/to/protocol.xml\", \
+                     server`, optionally followed by `, [(\"interface\", \"event\"), ...]`";
+
+fn parse_args(input: TokenStream) -> Result<Args, String> {
+    let mut tokens = input.into_iter();
+
+    let path = match tokens.next() {
+        Some(TokenTree::Literal(lit)) => parse_string(&lit).ok_or_else(|| USAGE.to_string())?,
+        _ => return Err(USAGE.into()),
+    };
+    expect_comma(tokens.next())?;
+    let side = match tokens.next() {
+        Some(TokenTree::Ident(ref ident)) if ident.to_string() == "client" => Side::Client,
+        Some(TokenTree::Ident(ref ident)) if ident.to_string() == "server" => Side::Server,
+        _ => return Err(USAGE.into()),
+    };
+
+    let mut events = Vec::new();
+    match tokens.next() {
+        None => {}
+        Some(token) => {
+            expect_comma(Some(token))?;
+            match tokens.next() {
+                None => {}
+                Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Bracket => {
+                    events = parse_events(group.stream())?;
+                    match tokens.next() {
+                        None => {}
+                        token => expect_comma(token)?,
+                    }
+                }
+                _ => return Err(USAGE.into()),
+            }
+        }
+    }
+
+    if tokens.next().is_some() {
+        return Err(USAGE.into());
+    }
+
+    Ok(Args { path, side, events })
+}
+
+fn parse_events(input: TokenStream) -> Result<Vec<(String, String)>, String> {
+    let mut events = Vec::new();
+    let mut tokens = input.into_iter();
+    while let Some(token) = tokens.next() {
+        let pair = match token {
+            TokenTree::Group(ref group) if group.delimiter() == Delimiter::Parenthesis => {
+                let strings = group
+                    .stream()
+                    .into_iter()
+                    .filter_map(|token| match token {
+                        TokenTree::Punct(ref punct) if punct.as_char() == ',' => None,
+                        TokenTree::Literal(lit) => Some(parse_string(&lit)),
+                        _ => Some(None),
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match strings {
+                    Some(ref strings) if strings.len() == 2 => {
+                        (strings[0].clone(), strings[1].clone())
+                    }
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            _ => return Err(USAGE.into()),
+        };
+        events.push(pair);
+        match tokens.next() {
+            None => {}
+            token => expect_comma(token)?,
+        }
+    }
+    Ok(events)
+}
+
+fn expect_comma(token: Option<TokenTree>) -> Result<(), String> {
+    match token {
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => Ok(()),
+        _ => Err(USAGE.into()),
+    }
+}
+
+// the value of a string literal, plain or raw
+fn parse_string(lit: &Literal) -> Option<String> {
+    let repr = lit.to_string();
+    if repr.starts_with('r') {
+        // the content of a raw string is between its first and last quotes
+        let start = repr.find('"')? + 1;
+        let end = repr.rfind('"')?;
+        return if start <= end { Some(repr[start..end].to_string()) } else { None };
+    }
+    if repr.len() < 2 || !repr.starts_with('"') || !repr.ends_with('"') {
+        return None;
+    }
+    let mut value = String::new();
+    let mut chars = repr[1..repr.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            c @ '\\' | c @ '"' | c @ '\'' => value.push(c),
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+fn compile_error(msg: &str) -> TokenStream {
+    let mut output: TokenStream = "compile_error!".parse().unwrap();
+    output.extend(Some(TokenTree::Group(proc_macro::Group::new(
+        Delimiter::Parenthesis,
+        TokenTree::Literal(Literal::string(msg)).into(),
+    ))));
+    output.extend(";".parse::<TokenStream>().unwrap());
+    output
+}
@@ -0,0 +1,97 @@
+//! Print the decoded contents of a wayland capture file
+//!
+//! Usage: `wayland-decode [--server] [-p protocol.xml]... [-d interface.event]... capture-file`
+//!
+//! - `--server`: the capture was made by a server rather than by a client
+//! - `-p protocol.xml`: a protocol file describing interfaces used in the capture, can be given
+//!   several times. Defaults to the core protocol at `/usr/share/wayland/wayland.xml`.
+//! - `-d interface.event`: mark an event as being a destructor, can be given several times.
+//!   `wl_callback.done` is always considered as a destructor.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::exit;
+
+use wayland_commons::capture::CaptureReader;
+use wayland_commons::decode::{Decoder, Side};
+
+const USAGE: &str =
+    "usage: wayland-decode [--server] [-p protocol.xml]... [-d interface.event]... capture-file";
+const DEFAULT_PROTOCOL: &str = "/usr/share/wayland/wayland.xml";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("wayland-decode: {}", msg);
+    exit(1)
+}
+
+fn main() {
+    let mut side = Side::Client;
+    let mut protocols = Vec::new();
+    let mut destructors = vec![("wl_callback".to_owned(), "done".to_owned())];
+    let mut capture = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--server" => side = Side::Server,
+            "-p" => protocols.push(args.next().unwrap_or_else(|| fail(USAGE))),
+            "-d" => {
+                let event = args.next().unwrap_or_else(|| fail(USAGE));
+                let mut parts = event.splitn(2, '.');
+                match (parts.next(), parts.next()) {
+                    (Some(interface), Some(event)) => {
+                        destructors.push((interface.to_owned(), event.to_owned()))
+                    }
+                    _ => fail(&format!("invalid destructor event `{}`", event)),
+                }
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if capture.is_none() && !arg.starts_with('-') => capture = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+    let capture = capture.unwrap_or_else(|| fail(USAGE));
+    if protocols.is_empty() {
+        protocols.push(DEFAULT_PROTOCOL.to_owned());
+    }
+
+    let destructors = destructors.iter().map(|(i, e)| (i.as_str(), e.as_str())).collect::<Vec<_>>();
+    let mut interfaces = Vec::new();
+    for path in &protocols {
+        let file = File::open(path)
+            .unwrap_or_else(|e| fail(&format!("unable to open protocol file `{}`: {}", path, e)));
+        interfaces.extend(
+            wayland_scanner::interfaces_from_xml(file, &destructors).unwrap_or_else(|e| {
+                fail(&format!("unable to parse protocol file `{}`: {}", path, e))
+            }),
+        );
+    }
+
+    let file = File::open(&capture)
+        .unwrap_or_else(|e| fail(&format!("unable to open capture file `{}`: {}", capture, e)));
+    let reader = CaptureReader::new(BufReader::new(file))
+        .unwrap_or_else(|e| fail(&format!("unable to read capture file `{}`: {}", capture, e)));
+
+    let mut decoder = Decoder::new(side, interfaces);
+    decoder.set_swap_endianness(reader.swap_endianness());
+    for record in reader {
+        let record =
+            record.unwrap_or_else(|e| fail(&format!("unable to read capture file: {}", e)));
+        for msg in decoder.decode(&record.as_record()) {
+            println!("{}", msg);
+            for &(id, ref interface) in &msg.created {
+                println!("    created {}@{}", interface.as_deref().unwrap_or("[unknown]"), id);
+            }
+            if msg.destroyed {
+                println!(
+                    "    destroyed {}@{}",
+                    msg.interface.as_deref().unwrap_or("[unknown]"),
+                    msg.sender_id
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Check that a new version of a protocol file is backward compatible with a previous one
+//!
+//! Usage: `wayland-protocol-compat [--breaking-only] old.xml new.xml`
+//!
+//! Prints the changes between the two versions, each classified as compatible or breaking,
+//! and exits with status 1 if any change is breaking.
+//!
+//! - `--breaking-only`: only print the breaking changes
+
+use std::fs::File;
+use std::process::exit;
+
+use wayland_scanner::Compatibility;
+
+const USAGE: &str = "usage: wayland-protocol-compat [--breaking-only] old.xml new.xml";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("wayland-protocol-compat: {}", msg);
+    exit(2)
+}
+
+fn main() {
+    let mut breaking_only = false;
+    let mut files = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        match &arg[..] {
+            "--breaking-only" => breaking_only = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if !arg.starts_with('-') => files.push(arg),
+            _ => fail(USAGE),
+        }
+    }
+    if files.len() != 2 {
+        fail(USAGE);
+    }
+
+    let parse = |path: &str| {
+        let file = File::open(path)
+            .unwrap_or_else(|e| fail(&format!("unable to open protocol file `{}`: {}", path, e)));
+        wayland_scanner::parse_protocol(file)
+            .unwrap_or_else(|e| fail(&format!("unable to parse protocol file `{}`: {}", path, e)))
+    };
+    let changes = wayland_scanner::compare_protocols(&parse(&files[0]), &parse(&files[1]));
+
+    let mut breaking = false;
+    for change in &changes {
+        if change.compatibility == Compatibility::Breaking {
+            breaking = true;
+        } else if breaking_only {
+            continue;
+        }
+        println!("{}", change);
+    }
+    if breaking {
+        exit(1);
+    }
+}
@@ -0,0 +1,115 @@
+//! Generate the rust code of a wayland protocol, for build systems that cannot run build scripts
+//!
+//! Usage: `wayland-scanner [--server] [-d interface.event]... [-i interface]... [--serde]
+//! [--no-rustfmt] [-o output.rs] protocol.xml`
+//!
+//! - `--server`: generate the code for `wayland_server` rather than `wayland_client`
+//! - `-d interface.event`: mark an event as being a destructor, can be given several times
+//! - `-i interface`: only generate the code of this interface and of the interfaces it depends
+//!   on, can be given several times
+//! - `--serde`: generate the implementations of the `serde` traits
+//! - `--no-rustfmt`: do not format the generated code with `rustfmt`
+//! - `-o output.rs`: the file to write the code to, defaults to the standard output
+//!
+//! The code written to a file is the same as the one generated by `generate_code` in a build
+//! script with the same options. The warnings of the protocol are printed to the standard error,
+//! even if the build system sets the `OUT_DIR` environment variable like cargo does.
+
+use std::fs::File;
+use std::io::Write;
+use std::process::{exit, Command, Stdio};
+
+use wayland_scanner::{GenerationOptions, Side};
+
+const USAGE: &str = "usage: wayland-scanner [--server] [-d interface.event]... [-i interface]... \
+                     [--serde] [--no-rustfmt] [-o output.rs] protocol.xml";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("wayland-scanner: {}", msg);
+    exit(1)
+}
+
+// format code with rustfmt reading from its standard input, if it can be run
+fn rustfmt(code: &[u8]) -> Option<Vec<u8>> {
+    let mut child =
+        Command::new("rustfmt").stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().ok()?;
+    child.stdin.take()?.write_all(code).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if output.status.success() {
+        Some(output.stdout)
+    } else {
+        None
+    }
+}
+
+fn main() {
+    let mut side = Side::Client;
+    let mut destructors = Vec::new();
+    let mut interfaces = Vec::new();
+    let mut serde = false;
+    let mut format = true;
+    let mut output = None;
+    let mut protocol = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--server" => side = Side::Server,
+            "-d" => {
+                let event = args.next().unwrap_or_else(|| fail(USAGE));
+                let mut parts = event.splitn(2, '.');
+                match (parts.next(), parts.next()) {
+                    (Some(interface), Some(event)) => {
+                        destructors.push((interface.to_owned(), event.to_owned()))
+                    }
+                    _ => fail(&format!("invalid destructor event `{}`", event)),
+                }
+            }
+            "-i" => interfaces.push(args.next().unwrap_or_else(|| fail(USAGE))),
+            "--serde" => serde = true,
+            "--no-rustfmt" => format = false,
+            "-o" => output = Some(args.next().unwrap_or_else(|| fail(USAGE))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if protocol.is_none() && !arg.starts_with('-') => protocol = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+    let protocol = protocol.unwrap_or_else(|| fail(USAGE));
+
+    let destructors = destructors.iter().map(|(i, e)| (i.as_str(), e.as_str())).collect::<Vec<_>>();
+    let mut options = GenerationOptions::new(side)
+        .destructor_events(&destructors)
+        .serde(serde)
+        .rustfmt(format)
+        .cargo_warnings(false);
+    if !interfaces.is_empty() {
+        let interfaces = interfaces.iter().map(|i| i.as_str()).collect::<Vec<_>>();
+        options = options.interfaces(&interfaces);
+    }
+
+    let generation_error =
+        |e| fail(&format!("unable to generate code for protocol file `{}`: {}", protocol, e));
+    match output {
+        Some(output) => {
+            wayland_scanner::try_generate_code_with_options(&protocol, &output, &options)
+                .unwrap_or_else(generation_error)
+        }
+        None => {
+            let file = File::open(&protocol).unwrap_or_else(|e| {
+                fail(&format!("unable to open protocol file `{}`: {}", protocol, e))
+            });
+            let mut code = Vec::new();
+            wayland_scanner::try_generate_code_streams_with_options(file, &mut code, &options)
+                .unwrap_or_else(generation_error);
+            if format {
+                code = rustfmt(&code).unwrap_or(code);
+            }
+            std::io::stdout()
+                .write_all(&code)
+                .unwrap_or_else(|e| fail(&format!("unable to write the code: {}", e)));
+        }
+    }
+}
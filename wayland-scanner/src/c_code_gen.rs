@@ -5,10 +5,11 @@ use quote::quote;
 
 use crate::common_gen::*;
 use crate::protocol::*;
+use crate::serde_gen::*;
 use crate::util::*;
 use crate::Side;
 
-pub(crate) fn generate_protocol_client(protocol: Protocol) -> TokenStream {
+pub(crate) fn generate_protocol_client(protocol: Protocol, serde: bool) -> TokenStream {
     // Force the fallback to work around https://github.com/alexcrichton/proc-macro2/issues/218
     proc_macro2::fallback::force();
 
@@ -37,6 +38,25 @@ pub(crate) fn generate_protocol_client(protocol: Protocol) -> TokenStream {
             Some(messagegroup_c_addon(&ident, &iface_name, Side::Client, true, &iface.events)),
         );
 
+        let serde_impls = if serde {
+            let enums = iface.enums.iter().map(gen_enum_serde);
+            let requests = gen_messagegroup_serde(
+                &Ident::new("Request", Span::call_site()),
+                Side::Client,
+                false,
+                &iface.requests,
+            );
+            let events = gen_messagegroup_serde(
+                &Ident::new("Event", Span::call_site()),
+                Side::Client,
+                true,
+                &iface.events,
+            );
+            Some(quote!(#(#enums)* #requests #events))
+        } else {
+            None
+        };
+
         let interface = gen_interface(
             &iface_name,
             &iface.name,
@@ -56,6 +76,7 @@ pub(crate) fn generate_protocol_client(protocol: Protocol) -> TokenStream {
                 use super::{
                     Proxy, AnonymousObject, Interface, MessageGroup, MessageDesc, ArgumentType,
                     Object, Message, Argument, ObjectMetadata, types_null, NULLPTR, Main, smallvec,
+                    OwnedFd, BorrowedFd,
                 };
                 use super::sys::common::{wl_interface, wl_array, wl_argument, wl_message};
                 use super::sys::client::*;
@@ -63,6 +84,7 @@ pub(crate) fn generate_protocol_client(protocol: Protocol) -> TokenStream {
                 #(#enums)*
                 #requests
                 #events
+                #serde_impls
                 #interface
                 #object_methods
                 #sinces
@@ -80,7 +102,7 @@ pub(crate) fn generate_protocol_client(protocol: Protocol) -> TokenStream {
     }
 }
 
-pub(crate) fn generate_protocol_server(protocol: Protocol) -> TokenStream {
+pub(crate) fn generate_protocol_server(protocol: Protocol, serde: bool) -> TokenStream {
     // Force the fallback to work around https://github.com/alexcrichton/proc-macro2/issues/218
     proc_macro2::fallback::force();
 
@@ -126,6 +148,25 @@ pub(crate) fn generate_protocol_server(protocol: Protocol) -> TokenStream {
                 )),
             );
 
+            let serde_impls = if serde {
+                let enums = iface.enums.iter().map(gen_enum_serde);
+                let requests = gen_messagegroup_serde(
+                    &Ident::new("Request", Span::call_site()),
+                    Side::Server,
+                    true,
+                    &iface.requests,
+                );
+                let events = gen_messagegroup_serde(
+                    &Ident::new("Event", Span::call_site()),
+                    Side::Server,
+                    false,
+                    &iface.events,
+                );
+                Some(quote!(#(#enums)* #requests #events))
+            } else {
+                None
+            };
+
             let interface = gen_interface(
                 &Ident::new(&snake_to_camel(&iface.name), Span::call_site()),
                 &iface.name,
@@ -143,7 +184,8 @@ pub(crate) fn generate_protocol_server(protocol: Protocol) -> TokenStream {
                     use std::os::raw::c_char;
                     use super::{
                         Resource, AnonymousObject, Interface, MessageGroup, MessageDesc, Main, smallvec,
-                        ArgumentType, Object, Message, Argument, ObjectMetadata, types_null, NULLPTR
+                        ArgumentType, Object, Message, Argument, ObjectMetadata, types_null, NULLPTR,
+                        OwnedFd, BorrowedFd,
                     };
                     use super::sys::common::{wl_argument, wl_interface, wl_array, wl_message};
                     use super::sys::server::*;
@@ -151,6 +193,7 @@ pub(crate) fn generate_protocol_server(protocol: Protocol) -> TokenStream {
                     #(#enums)*
                     #requests
                     #events
+                    #serde_impls
                     #interface
                     #object_methods
                     #sinces
@@ -242,7 +285,7 @@ fn messagegroup_c_addon(
                                     array_conversion
                                 }
                             }
-                            Type::Fd => quote!(_args[#idx].h),
+                            Type::Fd => quote!(<OwnedFd as ::std::os::unix::io::FromRawFd>::from_raw_fd(_args[#idx].h)),
                             Type::Object => {
                                 let object_name = side.object_name();
                                 let object_conversion = if let Some(ref iface) = arg.interface {
@@ -449,7 +492,7 @@ fn messagegroup_c_addon(
                         }
                     }
                     Type::Fd => quote! {
-                        _args_array[#idx].h = #arg_name;
+                        _args_array[#idx].h = ::std::os::unix::io::AsRawFd::as_raw_fd(&#arg_name);
                     },
                     Type::Object => {
                         if arg.allow_null {
@@ -205,7 +205,7 @@ pub(crate) fn gen_messagegroup(
                         Type::Fixed => quote!(f64),
                         Type::String => quote!(String),
                         Type::Array => quote!(Vec<u8>),
-                        Type::Fd => quote!(::std::os::unix::io::RawFd),
+                        Type::Fd => quote!(OwnedFd),
                         Type::Object => {
                             if let Some(ref iface) = arg.interface {
                                 let iface_mod = Ident::new(&iface, Span::call_site());
@@ -795,6 +795,12 @@ pub fn method_prototype<'a>(
     } else {
         quote!(())
     };
+    // duplicating the file descriptors can fail
+    let return_type = if msg.args.iter().any(|arg| arg.typ == Type::Fd) {
+        quote!(::std::io::Result<#return_type>)
+    } else {
+        return_type
+    };
 
     let prototype = quote! {
         pub fn #fn_name#(<#generics>)*(&self, #(#args),*) -> #return_type
@@ -824,6 +830,11 @@ pub(crate) fn gen_object_methods(name: &Ident, messages: &[Message], side: Side)
             docs += &format!("\nOnly available since version {} of the interface.", msg.since);
         }
 
+        let fds = msg.args.iter().any(|arg| arg.typ == Type::Fd);
+        if fds {
+            docs += "\nFails if the file descriptors cannot be duplicated.";
+        }
+
         let doc_attr = to_doc_attr(&docs);
 
         let msg_name = Ident::new(&snake_to_camel(&msg.name), Span::call_site());
@@ -859,6 +870,7 @@ pub(crate) fn gen_object_methods(name: &Ident, messages: &[Message], side: Side)
                             quote!(#arg_name.clone())
                         }
                     }
+                    (Type::Fd, _) => quote!(#arg_name.try_clone_to_owned()?),
                     _ => quote!(#arg_name),
                 };
 
@@ -880,6 +892,12 @@ pub(crate) fn gen_object_methods(name: &Ident, messages: &[Message], side: Side)
             }
         };
 
+        let send_stmt = match (fds, return_type) {
+            (true, Some(_)) => quote!(Ok(#send_stmt)),
+            (true, None) => quote! { #send_stmt Ok(()) },
+            (false, _) => send_stmt,
+        };
+
         quote! {
             #doc_attr
             #proto {
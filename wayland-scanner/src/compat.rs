@@ -0,0 +1,314 @@
+use std::fmt;
+use std::io::Read;
+
+use crate::parse::{self, ParseError};
+use crate::protocol::{Arg, Enum, Interface, Message, Protocol, Type};
+
+/// Whether a change to a protocol is backward compatible
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    /// Programs written for the previous version of the protocol keep working
+    Compatible,
+    /// Programs written for the previous version of the protocol can break
+    Breaking,
+}
+
+/// A difference between two versions of a protocol found by `compare_protocols`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolChange {
+    /// Whether the change is backward compatible
+    pub compatibility: Compatibility,
+    /// The element of the protocol that changed, like `wl_surface.attach`
+    pub location: String,
+    /// A description of the change
+    pub message: String,
+}
+
+impl fmt::Display for ProtocolChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let compatibility = match self.compatibility {
+            Compatibility::Compatible => "compatible",
+            Compatibility::Breaking => "breaking",
+        };
+        write!(f, "{}: {}: {}", compatibility, self.location, self.message)
+    }
+}
+
+/// Compare two versions of a protocol file
+///
+/// Parses both protocol files and compares them with `compare_protocols`.
+pub fn check_compatibility<R1: Read, R2: Read>(
+    old: R1,
+    new: R2,
+) -> Result<Vec<ProtocolChange>, ParseError> {
+    Ok(compare_protocols(&parse::parse_stream(old)?, &parse::parse_stream(new)?))
+}
+
+/// Compare two versions of a protocol, and classify their differences
+///
+/// The following changes are breaking:
+///
+/// - removing an interface, a message, an enum or an enum entry
+/// - decreasing the version of an interface
+/// - changing the opcode of a message, by reordering or renaming messages
+/// - changing the signature of a message: the number, types, interfaces, nullability or enums
+///   of its arguments, its `since` or whether it is a destructor
+/// - adding a message, an enum or an enum entry whose `since` is not greater than the previous
+///   version of its interface, which needs to be bumped, or is greater than the new version
+///   of its interface
+/// - changing the value of an enum entry, or whether an enum is a bitfield
+///
+/// The following changes are compatible:
+///
+/// - adding an interface
+/// - increasing the version of an interface
+/// - adding messages after the existing ones, with a proper `since`
+/// - adding enums or enum entries, with a proper `since`
+/// - renaming arguments, as they are not part of the wire format
+///
+/// Descriptions are not compared. The changes are returned in the order of the interfaces of
+/// the previous version, followed by the new interfaces.
+pub fn compare_protocols(old: &Protocol, new: &Protocol) -> Vec<ProtocolChange> {
+    let mut changes = Vec::new();
+
+    for old_interface in &old.interfaces {
+        match new.interfaces.iter().find(|i| i.name == old_interface.name) {
+            Some(new_interface) => compare_interfaces(old_interface, new_interface, &mut changes),
+            None => changes.push(ProtocolChange {
+                compatibility: Compatibility::Breaking,
+                location: old_interface.name.clone(),
+                message: "interface removed".into(),
+            }),
+        }
+    }
+
+    for new_interface in &new.interfaces {
+        if !old.interfaces.iter().any(|i| i.name == new_interface.name) {
+            changes.push(ProtocolChange {
+                compatibility: Compatibility::Compatible,
+                location: new_interface.name.clone(),
+                message: format!("interface added with version {}", new_interface.version),
+            });
+        }
+    }
+
+    changes
+}
+
+fn compare_interfaces(old: &Interface, new: &Interface, changes: &mut Vec<ProtocolChange>) {
+    let mut push = |compatibility, location: String, message: String| {
+        changes.push(ProtocolChange { compatibility, location, message })
+    };
+
+    if new.version < old.version {
+        push(
+            Compatibility::Breaking,
+            old.name.clone(),
+            format!("version decreased from {} to {}", old.version, new.version),
+        );
+    } else if new.version > old.version {
+        push(
+            Compatibility::Compatible,
+            old.name.clone(),
+            format!("version increased from {} to {}", old.version, new.version),
+        );
+    }
+
+    for (old_messages, new_messages, kind) in
+        &[(&old.requests, &new.requests, "request"), (&old.events, &new.events, "event")]
+    {
+        compare_messages(old, new, old_messages, new_messages, kind, &mut push);
+    }
+
+    for old_enum in &old.enums {
+        let location = format!("{}.{}", old.name, old_enum.name);
+        match new.enums.iter().find(|e| e.name == old_enum.name) {
+            Some(new_enum) => compare_enums(old, new, old_enum, new_enum, &location, &mut push),
+            None => push(Compatibility::Breaking, location, "enum removed".into()),
+        }
+    }
+    for new_enum in &new.enums {
+        if old.enums.iter().any(|e| e.name == new_enum.name) {
+            continue;
+        }
+        let location = format!("{}.{}", old.name, new_enum.name);
+        let (compatibility, message) = check_added(old, new, "enum", new_enum.since.into());
+        push(compatibility, location, message);
+    }
+}
+
+// an element added to an interface must be introduced in a version between the previous
+// version of the interface, excluded, and its new version
+fn check_added(
+    old: &Interface,
+    new: &Interface,
+    kind: &str,
+    since: u32,
+) -> (Compatibility, String) {
+    if since <= old.version {
+        (
+            Compatibility::Breaking,
+            format!(
+                "{} added with since {}, which is not greater than the previous version {}",
+                kind, since, old.version
+            ),
+        )
+    } else if since > new.version {
+        (
+            Compatibility::Breaking,
+            format!(
+                "{} added with since {}, which is greater than the version {} of the interface",
+                kind, since, new.version
+            ),
+        )
+    } else {
+        (Compatibility::Compatible, format!("{} added in version {}", kind, since))
+    }
+}
+
+fn compare_messages<F: FnMut(Compatibility, String, String)>(
+    interface: &Interface,
+    new_interface: &Interface,
+    old: &[Message],
+    new: &[Message],
+    kind: &str,
+    push: &mut F,
+) {
+    // the opcodes of the messages are their indices
+    for (opcode, old_msg) in old.iter().enumerate() {
+        let location = format!("{}.{}", interface.name, old_msg.name);
+        match new.get(opcode) {
+            Some(new_msg) if new_msg.name == old_msg.name => {
+                compare_signatures(old_msg, new_msg, &location, push)
+            }
+            _ => {
+                let message = match new.iter().position(|msg| msg.name == old_msg.name) {
+                    Some(new_opcode) => {
+                        format!("{} moved from opcode {} to {}", kind, opcode, new_opcode)
+                    }
+                    None => format!("{} removed from opcode {}", kind, opcode),
+                };
+                push(Compatibility::Breaking, location, message);
+            }
+        }
+    }
+
+    for new_msg in new.iter().skip(old.len()) {
+        if old.iter().any(|msg| msg.name == new_msg.name) {
+            // already reported as moved
+            continue;
+        }
+        let location = format!("{}.{}", interface.name, new_msg.name);
+        let (compatibility, message) = check_added(interface, new_interface, kind, new_msg.since);
+        push(compatibility, location, message);
+    }
+}
+
+fn compare_signatures<F: FnMut(Compatibility, String, String)>(
+    old: &Message,
+    new: &Message,
+    location: &str,
+    push: &mut F,
+) {
+    if old.since != new.since {
+        push(
+            Compatibility::Breaking,
+            location.into(),
+            format!("since changed from {} to {}", old.since, new.since),
+        );
+    }
+    let destructor = |msg: &Message| msg.typ == Some(Type::Destructor);
+    if destructor(old) != destructor(new) {
+        let message =
+            if destructor(new) { "became a destructor" } else { "no longer a destructor" };
+        push(Compatibility::Breaking, location.into(), message.into());
+    }
+
+    if old.args.len() != new.args.len() {
+        push(
+            Compatibility::Breaking,
+            location.into(),
+            format!("number of arguments changed from {} to {}", old.args.len(), new.args.len()),
+        );
+        return;
+    }
+    for (old_arg, new_arg) in old.args.iter().zip(new.args.iter()) {
+        compare_args(old_arg, new_arg, &format!("{}.{}", location, old_arg.name), push);
+    }
+}
+
+fn compare_args<F: FnMut(Compatibility, String, String)>(
+    old: &Arg,
+    new: &Arg,
+    location: &str,
+    push: &mut F,
+) {
+    let mut breaking = |message: String| push(Compatibility::Breaking, location.into(), message);
+    if old.typ != new.typ {
+        breaking(format!("type changed from {:?} to {:?}", old.typ, new.typ));
+    }
+    if old.interface != new.interface {
+        breaking(format!(
+            "interface changed from {} to {}",
+            old.interface.as_ref().map(|i| &i[..]).unwrap_or("none"),
+            new.interface.as_ref().map(|i| &i[..]).unwrap_or("none")
+        ));
+    }
+    if old.allow_null != new.allow_null {
+        breaking(format!("allow-null changed from {} to {}", old.allow_null, new.allow_null));
+    }
+    if old.enum_ != new.enum_ {
+        breaking(format!(
+            "enum changed from {} to {}",
+            old.enum_.as_ref().map(|e| &e[..]).unwrap_or("none"),
+            new.enum_.as_ref().map(|e| &e[..]).unwrap_or("none")
+        ));
+    }
+    if old.name != new.name {
+        push(
+            Compatibility::Compatible,
+            location.into(),
+            format!("argument renamed to `{}`", new.name),
+        );
+    }
+}
+
+fn compare_enums<F: FnMut(Compatibility, String, String)>(
+    old_interface: &Interface,
+    new_interface: &Interface,
+    old: &Enum,
+    new: &Enum,
+    location: &str,
+    push: &mut F,
+) {
+    if old.bitfield != new.bitfield {
+        push(
+            Compatibility::Breaking,
+            location.into(),
+            format!("bitfield changed from {} to {}", old.bitfield, new.bitfield),
+        );
+    }
+    for old_entry in &old.entries {
+        let entry_location = format!("{}.{}", location, old_entry.name);
+        match new.entries.iter().find(|e| e.name == old_entry.name) {
+            Some(new_entry) if new_entry.value != old_entry.value => push(
+                Compatibility::Breaking,
+                entry_location,
+                format!("value changed from {} to {}", old_entry.value, new_entry.value),
+            ),
+            Some(_) => {}
+            None => push(Compatibility::Breaking, entry_location, "entry removed".into()),
+        }
+    }
+    for new_entry in &new.entries {
+        if !old.entries.iter().any(|e| e.name == new_entry.name) {
+            let (compatibility, message) =
+                check_added(old_interface, new_interface, "entry", new_entry.since.into());
+            push(
+                compatibility,
+                format!("{}.{}", location, new_entry.name),
+                format!("{}, with value {}", message, new_entry.value),
+            );
+        }
+    }
+}
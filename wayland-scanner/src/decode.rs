@@ -0,0 +1,81 @@
+//! Interface descriptions for the offline decoder of `wayland-commons`
+
+use std::io::Read;
+
+use wayland_commons::decode::{ArgInfo, EnumInfo, InterfaceInfo, MessageInfo};
+use wayland_commons::wire::ArgumentType;
+
+use crate::parse::{self, ParseError};
+use crate::protocol::{Message, Type};
+
+/// Parse a protocol XML file into interface descriptions for the offline decoder
+///
+/// The returned descriptions can be given to `wayland_commons::decode::Decoder`.
+///
+/// As for `generate_code_with_destructor_events`, some events (in the format
+/// `("interface_name", "event_name")`) can be specified as being destructors.
+///
+/// Fails if the protocol file is ill-formed.
+pub fn interfaces_from_xml<R: Read>(
+    protocol: R,
+    destructor_events: &[(&str, &str)],
+) -> Result<Vec<InterfaceInfo>, ParseError> {
+    let protocol = parse::parse_stream(protocol)?;
+    Ok(protocol
+        .interfaces
+        .into_iter()
+        .map(|interface| {
+            let events = interface
+                .events
+                .iter()
+                .map(|msg| {
+                    let destructor = destructor_events.contains(&(&interface.name, &msg.name));
+                    message_info(msg, destructor)
+                })
+                .collect();
+            InterfaceInfo {
+                requests: interface.requests.iter().map(|msg| message_info(msg, false)).collect(),
+                events,
+                enums: interface
+                    .enums
+                    .into_iter()
+                    .map(|enu| EnumInfo {
+                        name: enu.name,
+                        bitfield: enu.bitfield,
+                        entries: enu.entries.into_iter().map(|e| (e.name, e.value)).collect(),
+                    })
+                    .collect(),
+                name: interface.name,
+                version: interface.version,
+            }
+        })
+        .collect())
+}
+
+fn message_info(msg: &Message, destructor: bool) -> MessageInfo {
+    MessageInfo {
+        name: msg.name.clone(),
+        since: msg.since,
+        destructor: destructor || msg.typ == Some(Type::Destructor),
+        args: msg
+            .args
+            .iter()
+            .map(|arg| ArgInfo {
+                name: arg.name.clone(),
+                kind: match arg.typ {
+                    Type::Int => ArgumentType::Int,
+                    Type::Uint => ArgumentType::Uint,
+                    Type::Fixed => ArgumentType::Fixed,
+                    Type::String => ArgumentType::Str,
+                    Type::Object => ArgumentType::Object,
+                    Type::NewId => ArgumentType::NewId,
+                    Type::Array => ArgumentType::Array,
+                    Type::Fd => ArgumentType::Fd,
+                    Type::Destructor => unreachable!("Destructor is not a valid argument type."),
+                },
+                interface: arg.interface.clone(),
+                enum_: arg.enum_.clone(),
+            })
+            .collect(),
+    }
+}
@@ -72,6 +72,7 @@
 //!         pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
 //!         pub(crate) use wayland_commons::{Interface, MessageGroup};
 //!         pub(crate) use wayland_commons::wire::{Argument, MessageDesc, ArgumentType, Message};
+//!         pub(crate) use wayland_commons::wire::{BorrowedFd, OwnedFd};
 //!         pub(crate) use wayland_commons::smallvec;
 //!         pub(crate) use wayland_client::protocol::{$($import),*};
 //!         pub(crate) use wayland_client::sys;
@@ -82,12 +83,72 @@
 //!     }
 //! }
 //! ```
+//!
+//! Rather than importing these modules by hand, you can describe where the code of the protocols
+//! yours depends on was generated with an `InterfaceRegistry`, and generate your code with
+//! `try_generate_code_with_registry`. The generated code then starts with these imports, and
+//! the interfaces missing from the registry are reported when generating it.
+//!
+//! Alternatively, the `generate_protocol!` macro of the `wayland-scanner-macros` crate
+//! generates the same code directly in your crate, without a build script, and imports the
+//! interfaces the protocol depends on for you.
+//!
+//! ## Using the scanner without a build script
+//!
+//! For build systems that cannot run build scripts, the `wayland-scanner` binary of this crate
+//! generates the same code as `generate_code` from the command line:
+//!
+//! ```text
+//! wayland-scanner [--server] [-d interface.event]... [-i interface]... [--serde] [--no-rustfmt]
+//!     [-o output.rs] protocol.xml
+//! ```
+//!
+//! Its options match the ones of `GenerationOptions`: `-d` marks an event as a destructor,
+//! `-i` only generates the code of some interfaces and their dependencies, and the code is formatted with `rustfmt`
+//! unless `--no-rustfmt` is given. Without `-o`, the code is written to the standard output.
+//!
+//! ## Decoding captured traffic
+//!
+//! With the `decoder` cargo feature, this crate provides the `interfaces_from_xml` function,
+//! which parses a protocol file into the interface descriptions used by the offline decoder
+//! of `wayland_commons::decode`. It also builds the `wayland-decode` binary, which prints
+//! the decoded contents of a capture file written by `wayland_commons::capture::CaptureWriter`:
+//!
+//! ```text
+//! wayland-decode [--server] [-p protocol.xml]... [-d interface.event]... capture-file
+//! ```
+//!
+//! ## Reading protocol files
+//!
+//! The `parse_protocol` function parses a protocol file into the types of the `protocol`
+//! module, with its interfaces, messages, arguments, enums and their descriptions, for tools
+//! that need the definitions of protocols. With the `serde` cargo feature these types are
+//! serializable, and the `json` cargo feature provides `dump_protocol_json`, writing them
+//! as JSON:
+//!
+//! ```ignore
+//! let file = std::fs::File::open("./my_protocol.xml").unwrap();
+//! wayland_scanner::dump_protocol_json(file, &mut std::io::stdout()).unwrap();
+//! ```
+//!
+//! ## Checking the compatibility of protocol versions
+//!
+//! The `compare_protocols` function compares two versions of a protocol, and classifies their
+//! differences as compatible or breaking: reordered opcodes, changed signatures or new messages
+//! without a version bump would break the programs written for the previous version. The
+//! `wayland-protocol-compat` binary prints these differences for two protocol files, and exits
+//! with an error if some of them are breaking:
+//!
+//! ```text
+//! wayland-protocol-compat [--breaking-only] old.xml new.xml
+//! ```
 
 #![warn(missing_docs)]
 // disable clippy lints that are not compatible with rust 1.41
 #![allow(clippy::match_like_matches_macro)]
 
-use std::fs::{File, OpenOptions};
+use std::collections::HashSet;
+use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 use std::process::Command;
@@ -95,17 +156,204 @@ use std::process::Command;
 mod c_code_gen;
 mod c_interface_gen;
 mod common_gen;
+mod compat;
+#[cfg(feature = "decoder")]
+mod decode;
+mod lint;
+mod macro_gen;
+mod options;
 mod parse;
-mod protocol;
+pub mod protocol;
+mod registry;
+mod serde_gen;
 mod side;
 mod util;
 
+pub use compat::{check_compatibility, compare_protocols, Compatibility, ProtocolChange};
+pub use lint::{lint_protocol, LintLevel, LintMessage};
+pub use options::GenerationOptions;
+pub use parse::ParseError;
+pub use registry::InterfaceRegistry;
 pub use side::Side;
 
-fn load_xml<P: AsRef<Path>>(prot: P) -> protocol::Protocol {
-    let pfile = File::open(prot.as_ref())
-        .unwrap_or_else(|_| panic!("Unable to open protocol file `{}`.", prot.as_ref().display()));
-    parse::parse_stream(pfile)
+#[cfg(feature = "decoder")]
+pub use decode::interfaces_from_xml;
+
+/// An error preventing the generation of the code for a protocol
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the protocol or writing the generated code failed
+    Io(std::io::Error),
+    /// The protocol file is ill-formed
+    Parse(ParseError),
+    /// The protocol has errors that would make the generated code invalid, see `lint_protocol`
+    Lint(Vec<LintMessage>),
+    /// The protocol refers to interfaces that are not in the `InterfaceRegistry`, or the
+    /// interfaces given to `GenerationOptions::interfaces` are not defined by the protocol
+    UnresolvedInterfaces(Vec<LintMessage>),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Parse(ref e) => Some(e),
+            Error::Lint(_) | Error::UnresolvedInterfaces(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::Parse(ref e) => std::fmt::Display::fmt(e, f),
+            Error::Lint(ref lints) => {
+                write!(f, "Invalid protocol:")?;
+                for lint in lints {
+                    write!(f, "\n{}", lint)?;
+                }
+                Ok(())
+            }
+            Error::UnresolvedInterfaces(ref lints) => {
+                write!(f, "Unresolved interfaces:")?;
+                for lint in lints {
+                    write!(f, "\n{}", lint)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+
+// print the warnings of the protocol, and fail if it has errors
+//
+// with `cargo_warnings`, warnings are printed so that cargo shows them for a build script
+fn check_protocol(protocol: &protocol::Protocol, cargo_warnings: bool) -> Result<(), Error> {
+    let (errors, warnings): (Vec<_>, Vec<_>) =
+        lint::lint(protocol).into_iter().partition(|lint| lint.level == LintLevel::Error);
+    for warning in warnings {
+        if cargo_warnings {
+            println!("cargo:warning={} (protocol {})", warning, protocol.name);
+        } else {
+            eprintln!("{} (protocol {})", warning, protocol.name);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Lint(errors))
+    }
+}
+
+fn mark_destructor_events(protocol: &mut protocol::Protocol, events: &[(String, String)]) {
+    for interface in &mut protocol.interfaces {
+        let name = &interface.name;
+        for event in &mut interface.events {
+            if events.iter().any(|(iface, evt)| iface == name && *evt == event.name) {
+                event.typ = Some(crate::protocol::Type::Destructor);
+            }
+        }
+    }
+}
+
+// generate the code of a protocol file into a file, and format it
+//
+// the code is generated in memory first, so that the target is left untouched if the
+// generation fails
+fn generate_file(prot: &Path, target: &Path, options: &GenerationOptions) -> Result<(), Error> {
+    let mut code = Vec::new();
+    generate(File::open(prot)?, &mut code, options)?;
+    std::fs::write(target, &code)?;
+
+    if options.rustfmt {
+        let _ = Command::new("rustfmt").arg(target).status();
+    }
+    Ok(())
+}
+
+// only keep the interfaces of the allow-list, along with the interfaces of the protocol they
+// depend on
+fn filter_interfaces(
+    protocol: &mut protocol::Protocol,
+    interfaces: &[String],
+) -> Result<(), Error> {
+    let errors = interfaces
+        .iter()
+        .filter(|name| !protocol.interfaces.iter().any(|i| i.name == **name))
+        .map(|name| LintMessage {
+            level: LintLevel::Error,
+            location: name.clone(),
+            message: format!("interface `{}` is not defined by this protocol", name),
+        })
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(Error::UnresolvedInterfaces(errors));
+    }
+
+    let mut kept = interfaces.iter().map(|name| &name[..]).collect::<HashSet<_>>();
+    let mut pending = kept.iter().cloned().collect::<Vec<_>>();
+    while let Some(name) = pending.pop() {
+        let interface = match protocol.interfaces.iter().find(|i| i.name == name) {
+            Some(interface) => interface,
+            // interfaces of other protocols
+            None => continue,
+        };
+        for (dependency, _) in registry::referenced_interfaces(interface) {
+            if kept.insert(dependency) {
+                pending.push(dependency);
+            }
+        }
+    }
+
+    let kept = kept.into_iter().map(|name| name.to_owned()).collect::<HashSet<_>>();
+    protocol.interfaces.retain(|i| kept.contains(&i.name));
+    Ok(())
+}
+
+// generate the code of a protocol, preceded by the imports of its dependencies if a
+// registry is given
+fn generate<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    options: &GenerationOptions,
+) -> Result<(), Error> {
+    let mut protocol = parse::parse_stream(protocol)?;
+    check_protocol(&protocol, options.cargo_warnings)?;
+    mark_destructor_events(&mut protocol, &options.destructor_events);
+    if let Some(ref interfaces) = options.interfaces {
+        filter_interfaces(&mut protocol, interfaces)?;
+    }
+
+    if let Some(ref registry) = options.registry {
+        let (imports, unresolved) = registry.resolve(&protocol);
+        if !unresolved.is_empty() {
+            return Err(Error::UnresolvedInterfaces(unresolved));
+        }
+        for (module, interface) in imports {
+            writeln!(target, "use {}::{};", module, interface)?;
+        }
+    }
+
+    let output = match options.side {
+        Side::Client => c_code_gen::generate_protocol_client(protocol, options.serde),
+        Side::Server => c_code_gen::generate_protocol_server(protocol, options.serde),
+    };
+
+    write!(target, "{}", output)?;
+    Ok(())
 }
 
 /// Generate the code for a protocol
@@ -118,6 +366,12 @@ fn load_xml<P: AsRef<Path>>(prot: P) -> protocol::Protocol {
 ///   the build script using this function.
 /// - `target`: the path of the file to store the code in.
 /// - `side`: the side (client or server) to generate code for.
+///
+/// The protocol is checked with `lint_protocol`: warnings are printed as cargo warnings,
+/// and errors make the generation fail.
+///
+/// Panics if the protocol file cannot be read, is ill-formed or has lint errors, see
+/// `try_generate_code` for a fallible version.
 pub fn generate_code<P1: AsRef<Path>, P2: AsRef<Path>>(prot: P1, target: P2, side: Side) {
     generate_code_with_destructor_events(prot, target, side, &[]);
 }
@@ -134,29 +388,68 @@ pub fn generate_code_with_destructor_events<P1: AsRef<Path>, P2: AsRef<Path>>(
     side: Side,
     events: &[(&str, &str)],
 ) {
-    let mut protocol = load_xml(prot);
-
-    for interface in &mut protocol.interfaces {
-        for event in &mut interface.events {
-            if events.contains(&(&interface.name, &event.name)) {
-                event.typ = Some(crate::protocol::Type::Destructor);
-            }
-        }
+    if let Err(e) = try_generate_code_with_destructor_events(&prot, target, side, events) {
+        panic!("Unable to generate code for protocol file `{}`: {}", prot.as_ref().display(), e);
     }
+}
 
-    {
-        let mut out =
-            OpenOptions::new().write(true).truncate(true).create(true).open(&target).unwrap();
+/// Generate the code for a protocol, returning an error on failure
+///
+/// Same as `generate_code`, but returns an error rather than panicking if the protocol
+/// file cannot be read, is ill-formed or has lint errors, or if the code cannot be written.
+pub fn try_generate_code<P1: AsRef<Path>, P2: AsRef<Path>>(
+    prot: P1,
+    target: P2,
+    side: Side,
+) -> Result<(), Error> {
+    try_generate_code_with_destructor_events(prot, target, side, &[])
+}
 
-        let output = match side {
-            Side::Client => c_code_gen::generate_protocol_client(protocol),
-            Side::Server => c_code_gen::generate_protocol_server(protocol),
-        };
+/// Generate the code for a protocol with aditionnal destructor events, returning an error
+/// on failure
+///
+/// Same as `generate_code_with_destructor_events`, but returns an error rather than
+/// panicking.
+pub fn try_generate_code_with_destructor_events<P1: AsRef<Path>, P2: AsRef<Path>>(
+    prot: P1,
+    target: P2,
+    side: Side,
+    events: &[(&str, &str)],
+) -> Result<(), Error> {
+    let options = GenerationOptions::new(side).destructor_events(events).cargo_warnings(true);
+    generate_file(prot.as_ref(), target.as_ref(), &options)
+}
 
-        write!(&mut out, "{}", output).unwrap();
-    }
+/// Generate the code for a protocol, importing the interfaces it depends on
+///
+/// Same as `try_generate_code_with_destructor_events`, but the interfaces of other protocols
+/// the protocol refers to are imported at the top of the generated code from their modules
+/// in `registry`, so the module including the code does not need to import them. The code is
+/// generated for the side of the registry.
+///
+/// Fails with `Error::UnresolvedInterfaces` if some of these interfaces are not in the registry.
+pub fn try_generate_code_with_registry<P1: AsRef<Path>, P2: AsRef<Path>>(
+    prot: P1,
+    target: P2,
+    registry: &InterfaceRegistry,
+    events: &[(&str, &str)],
+) -> Result<(), Error> {
+    let options = GenerationOptions::new(registry.side())
+        .registry(registry.clone())
+        .destructor_events(events)
+        .cargo_warnings(true);
+    generate_file(prot.as_ref(), target.as_ref(), &options)
+}
 
-    let _ = Command::new("rustfmt").arg(target.as_ref()).status();
+/// Generate the code for a protocol with the given options
+///
+/// Same as `try_generate_code`, with the options described by `GenerationOptions`.
+pub fn try_generate_code_with_options<P1: AsRef<Path>, P2: AsRef<Path>>(
+    prot: P1,
+    target: P2,
+    options: &GenerationOptions,
+) -> Result<(), Error> {
+    generate_file(prot.as_ref(), target.as_ref(), options)
 }
 
 /// Generate the code for a protocol from/to IO streams
@@ -168,6 +461,9 @@ pub fn generate_code_with_destructor_events<P1: AsRef<Path>, P2: AsRef<Path>>(
 /// - `protocol`: an object `Read`-able containing the XML protocol file
 /// - `target`: a `Write`-able object to which the generated code will be outputted to
 /// - `side`: the side (client or server) to generate code for.
+///
+/// Panics if the protocol is ill-formed or has lint errors, see `try_generate_code_streams`
+/// for a fallible version.
 pub fn generate_code_streams<P1: Read, P2: Write>(protocol: P1, target: &mut P2, side: Side) {
     generate_code_streams_with_destructor_events(protocol, target, side, &[])
 }
@@ -184,20 +480,146 @@ pub fn generate_code_streams_with_destructor_events<P1: Read, P2: Write>(
     side: Side,
     events: &[(&str, &str)],
 ) {
-    let mut protocol = parse::parse_stream(protocol);
+    if let Err(e) = try_generate_code_streams_with_destructor_events(protocol, target, side, events)
+    {
+        panic!("Unable to generate code: {}", e);
+    }
+}
 
-    for interface in &mut protocol.interfaces {
-        for event in &mut interface.events {
-            if events.contains(&(&interface.name, &event.name)) {
-                event.typ = Some(crate::protocol::Type::Destructor);
-            }
-        }
+/// Generate the code for a protocol from/to IO streams, returning an error on failure
+///
+/// Same as `generate_code_streams`, but returns an error rather than panicking if the
+/// protocol is ill-formed or has lint errors, or if the streams fail.
+pub fn try_generate_code_streams<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    side: Side,
+) -> Result<(), Error> {
+    try_generate_code_streams_with_destructor_events(protocol, target, side, &[])
+}
+
+/// Generate the code for a protocol from/to IO streams with aditionnal destructor events,
+/// returning an error on failure
+///
+/// Same as `generate_code_streams_with_destructor_events`, but returns an error rather
+/// than panicking.
+pub fn try_generate_code_streams_with_destructor_events<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    side: Side,
+    events: &[(&str, &str)],
+) -> Result<(), Error> {
+    let options = GenerationOptions::new(side).destructor_events(events);
+    generate(protocol, target, &options)
+}
+
+/// Generate the code of some interfaces of a protocol from/to IO streams
+///
+/// Same as `generate_code_streams`, but only generates the code of the `interfaces` of the
+/// protocol, and of the interfaces of the protocol they depend on, see
+/// `GenerationOptions::interfaces`.
+///
+/// Panics if the protocol is ill-formed, has lint errors or does not define one of the
+/// interfaces, see `try_generate_code_streams_with_interfaces` for a fallible version.
+pub fn generate_code_streams_with_interfaces<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    side: Side,
+    interfaces: &[&str],
+) {
+    if let Err(e) = try_generate_code_streams_with_interfaces(protocol, target, side, interfaces) {
+        panic!("Unable to generate code: {}", e);
     }
+}
 
-    let output = match side {
-        Side::Client => c_code_gen::generate_protocol_client(protocol),
-        Side::Server => c_code_gen::generate_protocol_server(protocol),
-    };
+/// Generate the code of some interfaces of a protocol from/to IO streams, returning an error
+/// on failure
+///
+/// Same as `generate_code_streams_with_interfaces`, but returns an error rather than
+/// panicking.
+pub fn try_generate_code_streams_with_interfaces<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    side: Side,
+    interfaces: &[&str],
+) -> Result<(), Error> {
+    let options = GenerationOptions::new(side).interfaces(interfaces);
+    generate(protocol, target, &options)
+}
 
-    write!(target, "{}", output).unwrap();
+/// Generate the code for a protocol from/to IO streams, importing the interfaces it depends on
+///
+/// Same as `try_generate_code_with_registry`, but takes IO streams rather than filenames.
+pub fn try_generate_code_streams_with_registry<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    registry: &InterfaceRegistry,
+    events: &[(&str, &str)],
+) -> Result<(), Error> {
+    let options = GenerationOptions::new(registry.side())
+        .registry(registry.clone())
+        .destructor_events(events);
+    generate(protocol, target, &options)
+}
+
+/// Generate the code for a protocol from/to IO streams with the given options
+///
+/// Same as `try_generate_code_streams`, with the options described by `GenerationOptions`.
+pub fn try_generate_code_streams_with_options<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    options: &GenerationOptions,
+) -> Result<(), Error> {
+    generate(protocol, target, options)
+}
+
+/// Generate the code for a protocol as a self-contained module
+///
+/// This is the code generation behind the `generate_protocol!` macro of the
+/// `wayland-scanner-macros` crate. Rather than the bare code written by `generate_code`, it
+/// returns a hidden module containing the generated code along with the imports it needs,
+/// followed by a re-export of its content:
+///
+/// - the interfaces of the core protocol referenced by the protocol are imported from the
+///   `protocol` module of `wayland_client` or `wayland_server`, as with an `InterfaceRegistry`,
+/// - the interfaces of other protocols are taken from the module the code is expanded in,
+///   so they only need to be in scope there.
+///
+/// The code refers to the `wayland_commons` crate and to `wayland_client` or `wayland_server`
+/// depending on the side, which must be dependencies of the crate using it.
+///
+/// The protocol is checked with `lint_protocol`: warnings are printed on the standard error
+/// output, and errors make the generation fail.
+pub fn generate_protocol_module<P: Read>(
+    protocol: P,
+    side: Side,
+    events: &[(&str, &str)],
+) -> Result<proc_macro2::TokenStream, Error> {
+    let options = GenerationOptions::new(side).destructor_events(events);
+    let mut protocol = parse::parse_stream(protocol)?;
+    check_protocol(&protocol, options.cargo_warnings)?;
+    mark_destructor_events(&mut protocol, &options.destructor_events);
+    Ok(macro_gen::generate_protocol_module(protocol, &InterfaceRegistry::new(side)))
+}
+
+/// Parse a protocol file
+///
+/// Returns the description of the protocol read from `protocol`, see the `protocol` module.
+/// Fails if the protocol is ill-formed, but does not check it with `lint_protocol`.
+pub fn parse_protocol<P: Read>(protocol: P) -> Result<protocol::Protocol, ParseError> {
+    parse::parse_stream(protocol)
+}
+
+/// Write the description of a protocol as JSON
+///
+/// Parses the protocol file read from `protocol` like `parse_protocol`, and writes the
+/// resulting `protocol::Protocol` to `target` as pretty-printed JSON.
+///
+/// This function is only available with the `json` cargo feature.
+#[cfg(feature = "json")]
+pub fn dump_protocol_json<P: Read, W: Write>(protocol: P, target: &mut W) -> Result<(), Error> {
+    let protocol = parse::parse_stream(protocol)?;
+    serde_json::to_writer_pretty(&mut *target, &protocol).map_err(std::io::Error::from)?;
+    writeln!(target)?;
+    Ok(())
 }
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Read;
+
+use crate::parse::{self, ParseError};
+use crate::protocol::{Interface, Message, Protocol, Type};
+
+/// The severity of a lint message
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    /// The protocol is likely wrong, but code can still be generated for it
+    Warning,
+    /// The code generated for this protocol would not compile
+    Error,
+}
+
+/// A problem found in a protocol file by `lint_protocol`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintMessage {
+    /// The severity of the problem
+    pub level: LintLevel,
+    /// The element of the protocol the problem was found in, like `wl_surface.attach`
+    pub location: String,
+    /// A description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for LintMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self.level {
+            LintLevel::Warning => "warning",
+            LintLevel::Error => "error",
+        };
+        write!(f, "{}: {}: {}", level, self.location, self.message)
+    }
+}
+
+/// Check a protocol file for common mistakes
+///
+/// The following problems are reported as errors, as the generated code would not compile:
+///
+/// - `enum` attributes referring to an enum that does not exist in the protocol (references
+///   to interfaces of other protocols are not checked)
+/// - duplicate names of interfaces, requests, events, arguments, enums or enum entries
+/// - duplicate values in an enum that is not a bitfield
+///
+/// The following problems are reported as warnings:
+///
+/// - `since` attributes greater than the version of their interface
+/// - `new_id` arguments without an interface in requests other than `bind`, or in events
+/// - bitfield entries that are neither a power of two nor a combination of the other entries
+///
+/// As opcodes are given by the order of the messages, duplicate opcodes appear as duplicate
+/// message names.
+///
+/// This check is run by `generate_code` and the associated functions, which print the
+/// warnings and fail on the errors.
+pub fn lint_protocol<R: Read>(protocol: R) -> Result<Vec<LintMessage>, ParseError> {
+    Ok(lint(&parse::parse_stream(protocol)?))
+}
+
+pub(crate) fn lint(protocol: &Protocol) -> Vec<LintMessage> {
+    let mut lints = Vec::new();
+
+    let mut interfaces = HashSet::new();
+    for interface in &protocol.interfaces {
+        if !interfaces.insert(&interface.name[..]) {
+            lints.push(LintMessage {
+                level: LintLevel::Error,
+                location: interface.name.clone(),
+                message: "duplicate interface name".into(),
+            });
+        }
+        lint_interface(protocol, interface, &mut lints);
+    }
+
+    lints
+}
+
+fn lint_interface(protocol: &Protocol, interface: &Interface, lints: &mut Vec<LintMessage>) {
+    let mut push = |level, location: String, message: String| {
+        lints.push(LintMessage { level, location, message })
+    };
+
+    for (messages, kind) in &[(&interface.requests, "request"), (&interface.events, "event")] {
+        let mut names = HashSet::new();
+        for msg in messages.iter() {
+            let location = format!("{}.{}", interface.name, msg.name);
+            if !names.insert(&msg.name[..]) {
+                push(LintLevel::Error, location.clone(), format!("duplicate {} name", kind));
+            }
+            if msg.since > interface.version {
+                push(
+                    LintLevel::Warning,
+                    location.clone(),
+                    format!(
+                        "since {} is greater than the interface version {}",
+                        msg.since, interface.version
+                    ),
+                );
+            }
+            lint_args(protocol, interface, msg, *kind == "request", &location, &mut push);
+        }
+    }
+
+    let mut enum_names = HashSet::new();
+    for enu in &interface.enums {
+        let location = format!("{}.{}", interface.name, enu.name);
+        if !enum_names.insert(&enu.name[..]) {
+            push(LintLevel::Error, location.clone(), "duplicate enum name".into());
+        }
+        if u32::from(enu.since) > interface.version {
+            push(
+                LintLevel::Warning,
+                location.clone(),
+                format!(
+                    "since {} is greater than the interface version {}",
+                    enu.since, interface.version
+                ),
+            );
+        }
+        // the bits of the single-bit entries, the other entries must be combinations of them
+        let flags = enu
+            .entries
+            .iter()
+            .filter(|entry| entry.value.is_power_of_two())
+            .fold(0, |flags, entry| flags | entry.value);
+        let mut entry_names = HashSet::new();
+        let mut entry_values = HashSet::new();
+        for entry in &enu.entries {
+            let entry_location = format!("{}.{}", location, entry.name);
+            if !entry_names.insert(&entry.name[..]) {
+                push(LintLevel::Error, entry_location.clone(), "duplicate entry name".into());
+            }
+            if !enu.bitfield && !entry_values.insert(entry.value) {
+                push(
+                    LintLevel::Error,
+                    entry_location.clone(),
+                    format!("duplicate value {} in an enum that is not a bitfield", entry.value),
+                );
+            }
+            if enu.bitfield && entry.value & !flags != 0 {
+                push(
+                    LintLevel::Warning,
+                    entry_location.clone(),
+                    format!(
+                        "bitfield entry value {:#x} is neither a power of two nor a combination \
+                        of other entries",
+                        entry.value
+                    ),
+                );
+            }
+            if u32::from(entry.since) > interface.version {
+                push(
+                    LintLevel::Warning,
+                    entry_location,
+                    format!(
+                        "since {} is greater than the interface version {}",
+                        entry.since, interface.version
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn lint_args<F: FnMut(LintLevel, String, String)>(
+    protocol: &Protocol,
+    interface: &Interface,
+    msg: &Message,
+    request: bool,
+    location: &str,
+    push: &mut F,
+) {
+    let mut names = HashSet::new();
+    for arg in &msg.args {
+        let location = format!("{}.{}", location, arg.name);
+        if !names.insert(&arg.name[..]) {
+            push(LintLevel::Error, location.clone(), "duplicate argument name".into());
+        }
+        if arg.typ == Type::NewId && arg.interface.is_none() && !(request && msg.name == "bind") {
+            push(
+                LintLevel::Warning,
+                location.clone(),
+                "new_id without an interface is only expected in bind requests".into(),
+            );
+        }
+        if let Some(ref enu) = arg.enum_ {
+            let mut split = enu.splitn(2, '.');
+            let (iface, enum_name) = match (split.next(), split.next()) {
+                (Some(iface), Some(enum_name)) => (iface, enum_name),
+                _ => (&interface.name[..], &enu[..]),
+            };
+            // if the interface is not in this protocol, the enum belongs to an other
+            // protocol, which we can't check
+            if let Some(target) = protocol.interfaces.iter().find(|i| i.name == iface) {
+                if !target.enums.iter().any(|e| e.name == enum_name) {
+                    push(LintLevel::Error, location, format!("unknown enum `{}`", enu));
+                }
+            }
+        }
+    }
+}
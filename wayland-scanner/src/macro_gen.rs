@@ -0,0 +1,76 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::protocol::*;
+use crate::registry::InterfaceRegistry;
+use crate::Side;
+
+/// Wrap the generated code of a protocol into a module importing everything it needs
+///
+/// Interfaces found in the registry are imported from their module, the other
+/// interfaces are taken from the module the code is generated in. The content of
+/// the module is re-exported in this enclosing module.
+pub(crate) fn generate_protocol_module(
+    protocol: Protocol,
+    registry: &InterfaceRegistry,
+) -> TokenStream {
+    // Force the fallback before creating any token, like the code generation does
+    proc_macro2::fallback::force();
+
+    let mod_name = Ident::new(
+        &format!(
+            "__wayland_protocol_{}",
+            protocol
+                .name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+        ),
+        Span::call_site(),
+    );
+
+    // the unresolved interfaces are left to the import of the enclosing module
+    let (imports, _) = registry.resolve(&protocol);
+    let imports = imports.into_iter().map(|(module, interface)| {
+        let module: TokenStream = module.parse().unwrap();
+        let interface = Ident::new(&interface, Span::call_site());
+        quote!(use #module::#interface;)
+    });
+
+    let prelude = match registry.side() {
+        Side::Client => quote! {
+            use ::wayland_client::{Main, Attached, Proxy, ProxyMap, AnonymousObject};
+            use ::wayland_client::sys;
+        },
+        Side::Server => quote! {
+            use ::wayland_server::{Main, AnonymousObject, Resource, ResourceMap};
+            use ::wayland_server::sys;
+        },
+    };
+
+    let code = match registry.side() {
+        Side::Client => crate::c_code_gen::generate_protocol_client(protocol, false),
+        Side::Server => crate::c_code_gen::generate_protocol_server(protocol, false),
+    };
+
+    quote! {
+        pub use self::#mod_name::*;
+
+        #[allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+        #[allow(non_upper_case_globals, non_snake_case, unused_imports)]
+        #[allow(missing_docs, clippy::all)]
+        mod #mod_name {
+            // interfaces of other protocols are taken from the enclosing module
+            use super::*;
+            #prelude
+            #(#imports)*
+            use ::wayland_commons::map::{Object, ObjectMetadata};
+            use ::wayland_commons::{Interface, MessageGroup};
+            use ::wayland_commons::wire::{Argument, MessageDesc, ArgumentType, Message};
+            use ::wayland_commons::wire::{BorrowedFd, OwnedFd};
+            use ::wayland_commons::smallvec;
+
+            #code
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::registry::InterfaceRegistry;
+use crate::Side;
+
+/// Options of the code generation
+///
+/// This gathers the options of the other code generation functions, along with those only
+/// available through `try_generate_code_with_options` and
+/// `try_generate_code_streams_with_options`:
+///
+/// ```no_run
+/// # use wayland_scanner::{GenerationOptions, Side};
+/// let options = GenerationOptions::new(Side::Client)
+///     .destructor_events(&[("my_callback", "done")])
+///     .serde(true);
+///
+/// wayland_scanner::try_generate_code_with_options(
+///     "./my_protocol.xml",
+///     "./my_protocol_api.rs",
+///     &options,
+/// )
+/// .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct GenerationOptions {
+    pub(crate) side: Side,
+    pub(crate) destructor_events: Vec<(String, String)>,
+    pub(crate) registry: Option<InterfaceRegistry>,
+    pub(crate) serde: bool,
+    pub(crate) interfaces: Option<Vec<String>>,
+    pub(crate) rustfmt: bool,
+    pub(crate) cargo_warnings: bool,
+}
+
+impl GenerationOptions {
+    /// Options to generate the code of a side, without any optional feature
+    pub fn new(side: Side) -> GenerationOptions {
+        GenerationOptions {
+            side,
+            destructor_events: Vec::new(),
+            registry: None,
+            serde: false,
+            interfaces: None,
+            rustfmt: true,
+            cargo_warnings: false,
+        }
+    }
+
+    /// Specify some events (in the format `("interface_name", "event_name")`) as being
+    /// destructors
+    ///
+    /// See `generate_code_with_destructor_events`.
+    pub fn destructor_events(mut self, events: &[(&str, &str)]) -> GenerationOptions {
+        self.destructor_events =
+            events.iter().map(|&(iface, event)| (iface.into(), event.into())).collect();
+        self
+    }
+
+    /// Import the interfaces of other protocols the protocol refers to from a registry
+    ///
+    /// See `try_generate_code_with_registry`. The code is then generated for the side of
+    /// the registry.
+    pub fn registry(mut self, registry: InterfaceRegistry) -> GenerationOptions {
+        self.side = registry.side();
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Generate implementations of the `serde` traits
+    ///
+    /// The `Request` and `Event` enums implement `Serialize`, with their objects serialized by
+    /// their id and their file descriptors by their number. As objects cannot be recreated
+    /// from an id, they do not implement `Deserialize`, but their `deserialize_raw()` method
+    /// deserializes a message into its raw `wire::Message`, identified by its opcode. The enums
+    /// of the protocol implement both `Serialize` and `Deserialize` by the names of their
+    /// entries, bitfields as lists of the names of their bits.
+    ///
+    /// The generated code uses serde through `wayland_commons`, whose `serde` cargo feature
+    /// must be enabled. The code of the protocols it depends on must support serde as well,
+    /// which the `serde` cargo features of `wayland-client` and `wayland-server` do for the
+    /// core protocol.
+    pub fn serde(mut self, enabled: bool) -> GenerationOptions {
+        self.serde = enabled;
+        self
+    }
+
+    /// Only generate the code of some interfaces of the protocol
+    ///
+    /// The interfaces of the protocol they depend on, through the interfaces of the objects
+    /// and the enums of their arguments, are generated as well, along with their own
+    /// dependencies. The generation fails with `Error::UnresolvedInterfaces` if one of these
+    /// interfaces is not defined by the protocol.
+    pub fn interfaces(mut self, interfaces: &[&str]) -> GenerationOptions {
+        self.interfaces = Some(interfaces.iter().map(|&name| name.into()).collect());
+        self
+    }
+
+    /// Whether to format the generated file with `rustfmt`, enabled by default
+    ///
+    /// This only applies to `try_generate_code_with_options`, the code written to streams is
+    /// never formatted. The generation does not fail if `rustfmt` cannot be run.
+    pub fn rustfmt(mut self, enabled: bool) -> GenerationOptions {
+        self.rustfmt = enabled;
+        self
+    }
+
+    /// Whether to print the lint warnings of the protocol as cargo warnings, disabled by default
+    ///
+    /// When enabled, the warnings are printed to the standard output in the `cargo:warning=`
+    /// format, for cargo to show them when it runs a build script. Otherwise they are printed
+    /// to the standard error. `generate_code` and the other functions taking paths without
+    /// options enable it, as they are meant to be used from build scripts.
+    pub fn cargo_warnings(mut self, enabled: bool) -> GenerationOptions {
+        self.cargo_warnings = enabled;
+        self
+    }
+}
@@ -1,118 +1,200 @@
 use crate::protocol::*;
+use std::fmt;
 use std::io::Read;
+use std::str::FromStr;
 use xml::attribute::OwnedAttribute;
+use xml::common::Position;
 use xml::reader::ParserConfig;
 use xml::reader::XmlEvent;
 use xml::EventReader;
 
-macro_rules! extract_from(
-    ($it: expr => $pattern: pat => $result: expr) => (
-        match $it.next() {
-            Ok($pattern) => { $result },
-            e => panic!("Ill-formed protocol file: {:?}", e)
-        }
-    )
-);
+/// An error encountered while parsing a protocol file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line of the error, starting at 1
+    pub line: u64,
+    /// The column of the error, starting at 1
+    pub column: u64,
+    /// A description of the error
+    pub message: String,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Ill-formed protocol file at line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+fn error<R: Read, T>(reader: &EventReader<R>, message: String) -> Result<T, ParseError> {
+    let pos = reader.position();
+    Err(ParseError { line: pos.row + 1, column: pos.column + 1, message })
+}
+
+fn next_event<R: Read>(reader: &mut EventReader<R>) -> Result<XmlEvent, ParseError> {
+    reader.next().map_err(|e| {
+        let pos = e.position();
+        ParseError { line: pos.row + 1, column: pos.column + 1, message: e.msg().into() }
+    })
+}
+
+fn parse_number<R: Read, T: FromStr>(
+    reader: &EventReader<R>,
+    attr: &OwnedAttribute,
+) -> Result<T, ParseError> {
+    match attr.value.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => error(
+            reader,
+            format!("invalid value `{}` for attribute `{}`", attr.value, attr.name.local_name),
+        ),
+    }
+}
+
+fn check_name<R: Read>(reader: &EventReader<R>, tag: &str, name: &str) -> Result<(), ParseError> {
+    if name.is_empty() {
+        return error(reader, format!("{} must have a name", tag));
+    }
+    Ok(())
+}
 
 macro_rules! extract_end_tag(
     ($it: expr => $tag: expr) => (
-        extract_from!($it => XmlEvent::EndElement { name } => {
-            assert!(name.local_name == $tag, "Ill-formed protocol file");
-        });
+        match next_event($it)? {
+            XmlEvent::EndElement { ref name } if name.local_name == $tag => {},
+            e => return error($it, format!("expected the end of `{}`, found {:?}", $tag, e)),
+        }
     )
 );
 
-pub fn parse_stream<S: Read>(stream: S) -> Protocol {
+pub fn parse_stream<S: Read>(stream: S) -> Result<Protocol, ParseError> {
     let mut reader =
         EventReader::new_with_config(stream, ParserConfig::new().trim_whitespace(true));
-    reader.next().expect("Could not read from event reader");
-    parse_protocol(reader)
+    match next_event(&mut reader)? {
+        XmlEvent::StartDocument { .. } => {}
+        e => return error(&reader, format!("expected the start of the document, found {:?}", e)),
+    }
+    parse_protocol(&mut reader)
 }
 
-fn parse_protocol<R: Read>(mut reader: EventReader<R>) -> Protocol {
-    let mut protocol = extract_from!(
-        reader => XmlEvent::StartElement { name, attributes, .. } => {
-            assert!(name.local_name == "protocol", "Missing protocol toplevel tag");
-            assert!(attributes[0].name.local_name == "name", "Protocol must have a name");
-            Protocol::new(attributes[0].value.clone())
+fn parse_protocol<R: Read>(reader: &mut EventReader<R>) -> Result<Protocol, ParseError> {
+    let mut protocol = loop {
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                if name.local_name != "protocol" {
+                    return error(reader, "missing protocol toplevel tag".into());
+                }
+                match attributes.into_iter().find(|attr| attr.name.local_name == "name") {
+                    Some(ref attr) if attr.value.is_empty() => {
+                        return error(reader, "protocol must have a name".into())
+                    }
+                    Some(attr) => break Protocol::new(attr.value),
+                    None => return error(reader, "protocol must have a name".into()),
+                }
+            }
+            XmlEvent::Comment(_) | XmlEvent::ProcessingInstruction { .. } => {}
+            e => return error(reader, format!("expected the protocol tag, found {:?}", e)),
         }
-    );
+    };
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => {
                 match &name.local_name[..] {
                     "copyright" => {
                         // parse the copyright
-                        let copyright = match reader.next() {
-                            Ok(XmlEvent::Characters(copyright))
-                            | Ok(XmlEvent::CData(copyright)) => copyright,
-                            e => panic!("Ill-formed protocol file: {:?}", e),
+                        let copyright = match next_event(reader)? {
+                            XmlEvent::Characters(copyright) | XmlEvent::CData(copyright) => {
+                                copyright
+                            }
+                            e => {
+                                return error(
+                                    reader,
+                                    format!("expected the copyright text, found {:?}", e),
+                                )
+                            }
                         };
 
                         extract_end_tag!(reader => "copyright");
                         protocol.copyright = Some(copyright);
                     }
                     "interface" => {
-                        protocol.interfaces.push(parse_interface(&mut reader, attributes));
+                        protocol.interfaces.push(parse_interface(reader, attributes)?);
                     }
                     "description" => {
-                        protocol.description = Some(parse_description(&mut reader, attributes));
+                        protocol.description = Some(parse_description(reader, attributes)?);
+                    }
+                    _ => {
+                        return error(
+                            reader,
+                            format!(
+                                "unexpected token `{}` in protocol {}",
+                                name.local_name, protocol.name
+                            ),
+                        )
                     }
-                    _ => panic!(
-                        "Ill-formed protocol file: unexpected token `{}` in protocol {}",
-                        name.local_name, protocol.name
-                    ),
                 }
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                assert!(
-                    name.local_name == "protocol",
-                    "Unexpected closing token `{}`",
-                    name.local_name
-                );
+            XmlEvent::EndElement { name } => {
+                if name.local_name != "protocol" {
+                    return error(
+                        reader,
+                        format!("unexpected closing token `{}`", name.local_name),
+                    );
+                }
                 break;
             }
-            e => panic!("Ill-formed protocol file: {:?}", e),
+            XmlEvent::Comment(_) | XmlEvent::ProcessingInstruction { .. } => {}
+            e => return error(reader, format!("unexpected {:?} in protocol", e)),
         }
     }
 
-    protocol
+    Ok(protocol)
 }
 
-fn parse_interface<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Interface {
+fn parse_interface<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Interface, ParseError> {
     let mut interface = Interface::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => interface.name = attr.value,
-            "version" => interface.version = attr.value.parse().unwrap(),
+            "version" => interface.version = parse_number(reader, &attr)?,
             _ => {}
         }
     }
+    check_name(reader, "interface", &interface.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
                 "description" => {
-                    interface.description = Some(parse_description(reader, attributes))
+                    interface.description = Some(parse_description(reader, attributes)?)
                 }
-                "request" => interface.requests.push(parse_request(reader, attributes)),
-                "event" => interface.events.push(parse_event(reader, attributes)),
-                "enum" => interface.enums.push(parse_enum(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+                "request" => interface.requests.push(parse_request(reader, attributes)?),
+                "event" => interface.events.push(parse_event(reader, attributes)?),
+                "enum" => interface.enums.push(parse_enum(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "interface" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "interface" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    interface
+    Ok(interface)
 }
 
 fn parse_description<R: Read>(
     reader: &mut EventReader<R>,
     attrs: Vec<OwnedAttribute>,
-) -> (String, String) {
+) -> Result<(String, String), ParseError> {
     let mut summary = String::new();
     for attr in attrs {
         if &attr.name.local_name[..] == "summary" {
@@ -120,50 +202,58 @@ fn parse_description<R: Read>(
         }
     }
 
-    let description = match reader.next() {
-        Ok(XmlEvent::Characters(txt)) => {
+    let description = match next_event(reader)? {
+        XmlEvent::Characters(txt) => {
             extract_end_tag!(reader => "description");
             txt
         }
-        Ok(XmlEvent::EndElement { ref name }) if name.local_name == "description" => String::new(),
-        e => panic!("Ill-formed protocol file: {:?}", e),
+        XmlEvent::EndElement { ref name } if name.local_name == "description" => String::new(),
+        e => return error(reader, format!("expected the description text, found {:?}", e)),
     };
 
-    (summary, description)
+    Ok((summary, description))
 }
 
-fn parse_request<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
+fn parse_request<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Message, ParseError> {
     let mut request = Message::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => request.name = attr.value,
-            "type" => request.typ = Some(parse_type(&attr.value)),
-            "since" => request.since = attr.value.parse().unwrap(),
+            "type" => request.typ = Some(parse_type(reader, &attr.value)?),
+            "since" => request.since = parse_number(reader, &attr)?,
             _ => {}
         }
     }
+    check_name(reader, "request", &request.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => request.description = Some(parse_description(reader, attributes)),
-                "arg" => request.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => request.description = Some(parse_description(reader, attributes)?),
+                "arg" => request.args.push(parse_arg(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "request" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "request" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    request
+    Ok(request)
 }
 
-fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Enum {
+fn parse_enum<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Enum, ParseError> {
     let mut enu = Enum::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => enu.name = attr.value,
-            "since" => enu.since = attr.value.parse().unwrap(),
+            "since" => enu.since = parse_number(reader, &attr)?,
             "bitfield" => {
                 if &attr.value[..] == "true" {
                     enu.bitfield = true
@@ -172,54 +262,71 @@ fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>)
             _ => {}
         }
     }
+    check_name(reader, "enum", &enu.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => enu.description = Some(parse_description(reader, attributes)),
-                "entry" => enu.entries.push(parse_entry(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => enu.description = Some(parse_description(reader, attributes)?),
+                "entry" => enu.entries.push(parse_entry(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "enum" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "enum" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    enu
+    Ok(enu)
 }
 
-fn parse_event<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
+fn parse_event<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Message, ParseError> {
     let mut event = Message::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => event.name = attr.value,
-            "type" => event.typ = Some(parse_type(&attr.value)),
-            "since" => event.since = attr.value.parse().unwrap(),
+            "type" => event.typ = Some(parse_type(reader, &attr.value)?),
+            "since" => event.since = parse_number(reader, &attr)?,
             _ => {}
         }
     }
+    check_name(reader, "event", &event.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => event.description = Some(parse_description(reader, attributes)),
-                "arg" => event.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => event.description = Some(parse_description(reader, attributes)?),
+                "arg" => event.args.push(parse_arg(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "event" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "event" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    event
+    Ok(event)
 }
 
-fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Arg {
+fn parse_arg<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Arg, ParseError> {
     let mut arg = Arg::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => arg.name = attr.value,
-            "type" => arg.typ = parse_type(&attr.value),
+            "type" => {
+                arg.typ = match parse_type(reader, &attr.value)? {
+                    Type::Destructor => {
+                        return error(reader, "`destructor` is not a valid argument type".into())
+                    }
+                    typ => typ,
+                }
+            }
             "summary" => {
                 arg.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
             }
@@ -233,23 +340,25 @@ fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -
             _ => {}
         }
     }
+    check_name(reader, "arg", &arg.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => arg.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => arg.description = Some(parse_description(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "arg" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "arg" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    arg
+    Ok(arg)
 }
 
-fn parse_type(txt: &str) -> Type {
-    match txt {
+fn parse_type<R: Read>(reader: &EventReader<R>, txt: &str) -> Result<Type, ParseError> {
+    Ok(match txt {
         "int" => Type::Int,
         "uint" => Type::Uint,
         "fixed" => Type::Fixed,
@@ -259,40 +368,53 @@ fn parse_type(txt: &str) -> Type {
         "array" => Type::Array,
         "fd" => Type::Fd,
         "destructor" => Type::Destructor,
-        e => panic!("Unexpected type: {}", e),
-    }
+        e => return error(reader, format!("unexpected type `{}`", e)),
+    })
 }
 
-fn parse_entry<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Entry {
+fn parse_entry<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Entry, ParseError> {
     let mut entry = Entry::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => entry.name = attr.value,
             "value" => {
                 entry.value = if attr.value.starts_with("0x") {
-                    u32::from_str_radix(&attr.value[2..], 16).unwrap()
+                    match u32::from_str_radix(&attr.value[2..], 16) {
+                        Ok(v) => v,
+                        Err(_) => {
+                            return error(
+                                reader,
+                                format!("invalid value `{}` for attribute `value`", attr.value),
+                            )
+                        }
+                    }
                 } else {
-                    attr.value.parse().unwrap()
+                    parse_number(reader, &attr)?
                 };
             }
-            "since" => entry.since = attr.value.parse().unwrap(),
+            "since" => entry.since = parse_number(reader, &attr)?,
             "summary" => {
                 entry.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
             }
             _ => {}
         }
     }
+    check_name(reader, "entry", &entry.name)?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => entry.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next_event(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => entry.description = Some(parse_description(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "entry" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "entry" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of document".into()),
             _ => {}
         }
     }
 
-    entry
+    Ok(entry)
 }
@@ -1,31 +1,57 @@
+//! The description of a protocol, as parsed from its XML file
+//!
+//! The descriptions found in the protocol file are stored as `(summary, description)` pairs.
+//!
+//! With the `serde` cargo feature, all these types implement `Serialize` and `Deserialize`,
+//! with the fields named as in this module and the types of arguments as in the XML files.
+
 use proc_macro2::TokenStream;
 use quote::quote;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A protocol
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Protocol {
+    /// The name of the protocol
     pub name: String,
+    /// The copyright notice of the protocol
     pub copyright: Option<String>,
+    /// The description of the protocol
     pub description: Option<(String, String)>,
+    /// The interfaces of the protocol
     pub interfaces: Vec<Interface>,
 }
 
 impl Protocol {
+    /// An empty protocol
     pub fn new(name: String) -> Protocol {
         Protocol { name, copyright: None, description: None, interfaces: Vec::new() }
     }
 }
 
+/// An interface of a protocol
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Interface {
+    /// The name of the interface
     pub name: String,
+    /// The version of the interface
     pub version: u32,
+    /// The description of the interface
     pub description: Option<(String, String)>,
+    /// The requests of the interface, in the order of their opcodes
     pub requests: Vec<Message>,
+    /// The events of the interface, in the order of their opcodes
     pub events: Vec<Message>,
+    /// The enums of the interface
     pub enums: Vec<Enum>,
 }
 
 impl Interface {
+    /// An empty interface, of version 1
     pub fn new() -> Interface {
         Interface {
             name: String::new(),
@@ -38,47 +64,69 @@ impl Interface {
     }
 }
 
+impl Default for Interface {
+    fn default() -> Interface {
+        Interface::new()
+    }
+}
+
+/// A request or an event
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Message {
+    /// The name of the message
     pub name: String,
+    /// The type of the message, `Some(Type::Destructor)` for destructors
     pub typ: Option<Type>,
+    /// The version of the interface the message was introduced in
     pub since: u32,
+    /// The description of the message
     pub description: Option<(String, String)>,
+    /// The arguments of the message
     pub args: Vec<Arg>,
-    pub type_index: usize,
 }
 
 impl Message {
+    /// A message without arguments, introduced in version 1
     pub fn new() -> Message {
-        Message {
-            name: String::new(),
-            typ: None,
-            since: 1,
-            description: None,
-            args: Vec::new(),
-            type_index: 0,
-        }
+        Message { name: String::new(), typ: None, since: 1, description: None, args: Vec::new() }
     }
 
-    pub fn all_null(&self) -> bool {
+    pub(crate) fn all_null(&self) -> bool {
         self.args
             .iter()
             .all(|a| !((a.typ == Type::Object || a.typ == Type::NewId) && a.interface.is_some()))
     }
 }
 
+impl Default for Message {
+    fn default() -> Message {
+        Message::new()
+    }
+}
+
+/// An argument of a message
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Arg {
+    /// The name of the argument
     pub name: String,
+    /// The type of the argument
     pub typ: Type,
+    /// The interface of the object, for `object` and `new_id` arguments
     pub interface: Option<String>,
+    /// The summary of the argument
     pub summary: Option<String>,
+    /// The description of the argument
     pub description: Option<(String, String)>,
+    /// Whether the argument can be null
     pub allow_null: bool,
+    /// The enum of the argument, as `enum` or `interface.enum`
     pub enum_: Option<String>,
 }
 
 impl Arg {
+    /// An `object` argument
     pub fn new() -> Arg {
         Arg {
             name: String::new(),
@@ -92,16 +140,30 @@ impl Arg {
     }
 }
 
+impl Default for Arg {
+    fn default() -> Arg {
+        Arg::new()
+    }
+}
+
+/// An enum of an interface
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Enum {
+    /// The name of the enum
     pub name: String,
+    /// The version of the interface the enum was introduced in
     pub since: u16,
+    /// The description of the enum
     pub description: Option<(String, String)>,
+    /// The entries of the enum
     pub entries: Vec<Entry>,
+    /// Whether the enum is a bitfield
     pub bitfield: bool,
 }
 
 impl Enum {
+    /// An empty enum, introduced in version 1
     pub fn new() -> Enum {
         Enum {
             name: String::new(),
@@ -113,35 +175,67 @@ impl Enum {
     }
 }
 
+impl Default for Enum {
+    fn default() -> Enum {
+        Enum::new()
+    }
+}
+
+/// An entry of an enum
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Entry {
+    /// The name of the entry
     pub name: String,
+    /// The value of the entry
     pub value: u32,
+    /// The version of the interface the entry was introduced in
     pub since: u16,
+    /// The description of the entry
     pub description: Option<(String, String)>,
+    /// The summary of the entry
     pub summary: Option<String>,
 }
 
 impl Entry {
+    /// An entry of value 0, introduced in version 1
     pub fn new() -> Entry {
         Entry { name: String::new(), value: 0, since: 1, description: None, summary: None }
     }
 }
 
+impl Default for Entry {
+    fn default() -> Entry {
+        Entry::new()
+    }
+}
+
+/// The type of an argument, or of a message for destructors
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
 pub enum Type {
+    /// A signed 32-bit integer
     Int,
+    /// An unsigned 32-bit integer
     Uint,
+    /// A signed 24.8 fixed-point number
     Fixed,
+    /// A string
     String,
+    /// An object
     Object,
+    /// A new object, created by the message
     NewId,
+    /// An array of bytes
     Array,
+    /// A file descriptor
     Fd,
+    /// The type of the messages destroying their object
     Destructor,
 }
 
 impl Type {
+    /// Whether arguments of this type can be null
     pub fn nullable(self) -> bool {
         match self {
             Type::String | Type::Object | Type::NewId | Type::Array => true,
@@ -149,20 +243,20 @@ impl Type {
         }
     }
 
-    pub fn rust_type(self) -> TokenStream {
+    pub(crate) fn rust_type(self) -> TokenStream {
         match self {
             Type::Int => quote!(i32),
             Type::Uint => quote!(u32),
             Type::Fixed => quote!(f64),
             Type::Array => quote!(Vec<u8>),
-            Type::Fd => quote!(::std::os::unix::io::RawFd),
+            Type::Fd => quote!(BorrowedFd<'_>),
             Type::String => quote!(String),
             Type::Object => quote!(ProxyId),
             _ => quote!(()),
         }
     }
 
-    pub fn common_type(self) -> TokenStream {
+    pub(crate) fn common_type(self) -> TokenStream {
         match self {
             Type::Int => quote!(Int),
             Type::Uint => quote!(Uint),
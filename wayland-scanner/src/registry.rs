@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::lint::{LintLevel, LintMessage};
+use crate::parse;
+use crate::protocol::{Interface, Protocol};
+use crate::{Error, Side};
+
+// interfaces of the core protocol, exposed by the `protocol` module of
+// `wayland_client` and `wayland_server`
+const CORE_INTERFACES: &[&str] = &[
+    "wl_display",
+    "wl_registry",
+    "wl_callback",
+    "wl_compositor",
+    "wl_shm_pool",
+    "wl_shm",
+    "wl_buffer",
+    "wl_data_offer",
+    "wl_data_source",
+    "wl_data_device",
+    "wl_data_device_manager",
+    "wl_shell",
+    "wl_shell_surface",
+    "wl_surface",
+    "wl_seat",
+    "wl_pointer",
+    "wl_keyboard",
+    "wl_touch",
+    "wl_output",
+    "wl_region",
+    "wl_subcompositor",
+    "wl_subsurface",
+];
+
+/// The modules in which the code of the interfaces a protocol depends on was generated
+///
+/// A protocol can refer to interfaces defined by other protocols, like `wl_surface` from the
+/// core protocol. Given a registry, `try_generate_code_with_registry` and
+/// `try_generate_code_streams_with_registry` import these interfaces at the top of the
+/// generated code, and report the interfaces that cannot be found in the registry.
+///
+/// A new registry contains the interfaces of the core protocol, from the `protocol` module of
+/// `wayland_client` or `wayland_server` depending on its side. The other protocols are added
+/// with the path of the module their code was generated in, for the same side:
+///
+/// ```no_run
+/// # use wayland_scanner::{InterfaceRegistry, Side};
+/// let mut registry = InterfaceRegistry::new(Side::Client);
+/// registry.add_protocol("./xdg-shell.xml", "crate::xdg_shell::client").unwrap();
+/// ```
+///
+/// Registering an interface which is already in the registry replaces its module.
+#[derive(Clone, Debug)]
+pub struct InterfaceRegistry {
+    side: Side,
+    modules: HashMap<String, String>,
+}
+
+impl InterfaceRegistry {
+    /// Create a registry containing the interfaces of the core protocol
+    pub fn new(side: Side) -> InterfaceRegistry {
+        let core_module = match side {
+            Side::Client => "wayland_client::protocol",
+            Side::Server => "wayland_server::protocol",
+        };
+        let modules =
+            CORE_INTERFACES.iter().map(|name| (name.to_string(), core_module.into())).collect();
+        InterfaceRegistry { side, modules }
+    }
+
+    /// The side the code of the registered interfaces was generated for
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// Register all the interfaces of a protocol file
+    ///
+    /// `module` is the path of the module containing the code generated for this protocol,
+    /// like `crate::xdg_shell::client`. Fails if the file cannot be read or is ill-formed.
+    pub fn add_protocol<P: AsRef<Path>>(&mut self, protocol: P, module: &str) -> Result<(), Error> {
+        self.add_protocol_stream(File::open(protocol)?, module)
+    }
+
+    /// Register all the interfaces of a protocol read from a stream
+    ///
+    /// Same as `add_protocol`, but takes an IO stream rather than a filename.
+    pub fn add_protocol_stream<R: Read>(&mut self, protocol: R, module: &str) -> Result<(), Error> {
+        for interface in parse::parse_stream(protocol)?.interfaces {
+            self.modules.insert(interface.name, module.into());
+        }
+        Ok(())
+    }
+
+    /// Register a single interface
+    ///
+    /// `module` is the path of the module containing the module of the interface.
+    pub fn add_interface(&mut self, interface: &str, module: &str) {
+        self.modules.insert(interface.into(), module.into());
+    }
+
+    /// The module the code of an interface was generated in, if it is registered
+    pub fn module(&self, interface: &str) -> Option<&str> {
+        self.modules.get(interface).map(|module| &module[..])
+    }
+
+    // find the modules of the interfaces a protocol depends on, as `(module, interface)` pairs,
+    // along with errors for the interfaces that are not in the registry
+    pub(crate) fn resolve<'a>(
+        &'a self,
+        protocol: &Protocol,
+    ) -> (Vec<(&'a str, String)>, Vec<LintMessage>) {
+        let mut imports = Vec::new();
+        let mut missing = Vec::new();
+        for (interface, location) in external_interfaces(protocol) {
+            match self.module(&interface) {
+                Some(module) => imports.push((module, interface)),
+                None => missing.push(LintMessage {
+                    level: LintLevel::Error,
+                    location,
+                    message: format!(
+                        "interface `{}` is neither defined by this protocol nor registered",
+                        interface
+                    ),
+                }),
+            }
+        }
+        (imports, missing)
+    }
+}
+
+/// The interfaces referenced by the messages of a protocol but not defined in it, along with
+/// the location of their first reference
+///
+/// Enums of other interfaces count as references to these interfaces.
+pub(crate) fn external_interfaces(protocol: &Protocol) -> BTreeMap<String, String> {
+    let defined: HashSet<&str> = protocol.interfaces.iter().map(|i| &i.name[..]).collect();
+    let mut external = BTreeMap::new();
+    for interface in &protocol.interfaces {
+        for (name, location) in referenced_interfaces(interface) {
+            if !defined.contains(name) && !external.contains_key(name) {
+                external.insert(name.to_string(), location);
+            }
+        }
+    }
+    external
+}
+
+/// The interfaces referenced by the messages of an interface, with the location of each
+/// reference
+///
+/// Enums of other interfaces count as references to these interfaces.
+pub(crate) fn referenced_interfaces(interface: &Interface) -> Vec<(&str, String)> {
+    let mut references = Vec::new();
+    for msg in interface.requests.iter().chain(interface.events.iter()) {
+        for arg in &msg.args {
+            let enum_interface = arg.enum_.as_ref().and_then(|enu| {
+                let mut split = enu.splitn(2, '.');
+                match (split.next(), split.next()) {
+                    (Some(iface), Some(_)) => Some(iface),
+                    _ => None,
+                }
+            });
+            for name in arg.interface.as_ref().map(|i| &i[..]).into_iter().chain(enum_interface) {
+                references.push((name, format!("{}.{}.{}", interface.name, msg.name, arg.name)));
+            }
+        }
+    }
+    references
+}
@@ -0,0 +1,294 @@
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::quote;
+
+use crate::protocol::*;
+use crate::util::*;
+use crate::Side;
+
+// the code is generated for crates depending on serde through wayland-commons
+fn serde_path() -> TokenStream {
+    quote!(::wayland_commons::serde)
+}
+
+fn entry_ident(entry: &Entry) -> Ident {
+    let prefix = if entry.name.chars().next().unwrap().is_numeric() { "_" } else { "" };
+    Ident::new(&format!("{}{}", prefix, snake_to_camel(&entry.name)), Span::call_site())
+}
+
+/// `Serialize` and `Deserialize` implementations for an enum, by the names of its entries
+///
+/// Bitfields are represented as the list of the names of their bits.
+pub(crate) fn gen_enum_serde(enu: &Enum) -> TokenStream {
+    let serde = serde_path();
+    let ident = Ident::new(&snake_to_camel(&enu.name), Span::call_site());
+    let names = enu.entries.iter().map(|entry| &entry.name[..]).collect::<Vec<_>>();
+
+    let deserialize_arms = enu.entries.iter().map(|entry| {
+        let variant = entry_ident(entry);
+        let name = &entry.name;
+        quote!(#name => #ident::#variant)
+    });
+    let unknown = quote! {
+        return Err(<D::Error as #serde::de::Error>::unknown_variant(name, &[#(#names),*]))
+    };
+
+    let bitfield = enu.bitfield;
+    let value_arms = enu.entries.iter().map(|entry| {
+        let name = &entry.name;
+        let value = Literal::u32_unsuffixed(entry.value);
+        quote!(#name => Some(#value))
+    });
+    let named_enum = quote! {
+        impl ::wayland_commons::message_serde::NamedEnum for #ident {
+            const BITFIELD: bool = #bitfield;
+            fn value_of(name: &str) -> Option<u32> {
+                match name {
+                    #(#value_arms,)*
+                    _ => None,
+                }
+            }
+        }
+    };
+
+    if enu.bitfield {
+        // only the single bits are listed, the other entries are combinations of them
+        let bits = enu.entries.iter().filter(|entry| entry.value.is_power_of_two()).map(|entry| {
+            let variant = entry_ident(entry);
+            let name = &entry.name;
+            quote! {
+                if self.contains(#ident::#variant) {
+                    seq.serialize_element(#name)?;
+                }
+            }
+        });
+        quote! {
+            impl #serde::Serialize for #ident {
+                fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    use #serde::ser::SerializeSeq;
+                    let mut seq = serializer.serialize_seq(None)?;
+                    #(#bits)*
+                    seq.end()
+                }
+            }
+
+            impl<'de> #serde::Deserialize<'de> for #ident {
+                fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<#ident, D::Error> {
+                    let names = <Vec<String> as #serde::Deserialize>::deserialize(deserializer)?;
+                    let mut value = #ident::empty();
+                    for name in &names {
+                        value |= match &name[..] {
+                            #(#deserialize_arms,)*
+                            _ => #unknown,
+                        };
+                    }
+                    Ok(value)
+                }
+            }
+
+            #named_enum
+        }
+    } else {
+        let serialize_arms = enu.entries.iter().map(|entry| {
+            let variant = entry_ident(entry);
+            let name = &entry.name;
+            quote!(#ident::#variant => #name)
+        });
+        quote! {
+            impl #serde::Serialize for #ident {
+                fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(match *self {
+                        #(#serialize_arms,)*
+                    })
+                }
+            }
+
+            impl<'de> #serde::Deserialize<'de> for #ident {
+                fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<#ident, D::Error> {
+                    let name = <String as #serde::Deserialize>::deserialize(deserializer)?;
+                    let name = &name[..];
+                    Ok(match name {
+                        #(#deserialize_arms,)*
+                        _ => #unknown,
+                    })
+                }
+            }
+
+            #named_enum
+        }
+    }
+}
+
+/// `Serialize` implementation for a message group, see `gen_messagegroup`
+///
+/// The messages are serialized as variants named after the protocol, objects by their id
+/// and file descriptors by their number. As the objects cannot be recreated from their id,
+/// the messages are deserialized by `deserialize_raw()` into their raw form instead, see
+/// `wayland_commons::message_serde`.
+pub(crate) fn gen_messagegroup_serde(
+    name: &Ident,
+    side: Side,
+    receiver: bool,
+    messages: &[Message],
+) -> TokenStream {
+    let serde = serde_path();
+    // the locals are prefixed to not collide with the names of the arguments
+    let group_name = name.to_string();
+
+    let arms = messages.iter().enumerate().map(|(index, msg)| {
+        let index = Literal::u32_unsuffixed(index as u32);
+        let msg_name = &msg.name;
+        let variant = Ident::new(&snake_to_camel(&msg.name), Span::call_site());
+        if msg.args.is_empty() {
+            return quote! {
+                #name::#variant => __serializer.serialize_unit_variant(#group_name, #index, #msg_name)
+            };
+        }
+
+        let fields = msg
+            .args
+            .iter()
+            .filter(|arg| {
+                // client-side requests do not hold the objects they create
+                !(arg.typ == Type::NewId
+                    && !receiver
+                    && side == Side::Client
+                    && arg.interface.is_some())
+            })
+            .map(|arg| {
+                let field_name = Ident::new(
+                    &format!("{}{}", if is_keyword(&arg.name) { "_" } else { "" }, arg.name),
+                    Span::call_site(),
+                );
+                let id = |value: TokenStream| match arg.typ {
+                    Type::NewId if side == Side::Server && !receiver => quote!(#value.id()),
+                    _ => quote!(#value.as_ref().id()),
+                };
+                let value = match arg.typ {
+                    _ if arg.enum_.is_some() => quote!(#field_name),
+                    Type::Fd => quote!(&::std::os::unix::io::AsRawFd::as_raw_fd(#field_name)),
+                    Type::Object | Type::NewId if arg.interface.is_some() || arg.typ == Type::Object => {
+                        if arg.allow_null {
+                            let id = id(quote!(obj));
+                            quote!(&#field_name.as_ref().map(|obj| #id))
+                        } else {
+                            let id = id(quote!(#field_name));
+                            quote!(&#id)
+                        }
+                    }
+                    // bind-like requests, (interface, version, object) server-side
+                    Type::NewId if receiver || side == Side::Server => {
+                        quote!(&(&#field_name.0, #field_name.1, #field_name.2.as_ref().id()))
+                    }
+                    _ => quote!(#field_name),
+                };
+                (field_name, &arg.name, value)
+            })
+            .collect::<Vec<_>>();
+
+        let len = fields.len();
+        let bindings = fields.iter().map(|field| {
+            let field_name = &field.0;
+            quote!(ref #field_name)
+        });
+        let serialize_fields = fields.iter().map(|field| {
+            let (arg_name, value) = (field.1, &field.2);
+            quote!(__state.serialize_field(#arg_name, #value)?;)
+        });
+        quote! {
+            #name::#variant { #(#bindings,)* .. } => {
+                let mut __state = __serializer.serialize_struct_variant(#group_name, #index, #msg_name, #len)?;
+                #(#serialize_fields)*
+                __state.end()
+            }
+        }
+    });
+
+    let message_serde = quote!(::wayland_commons::message_serde);
+    let names = messages.iter().map(|msg| &msg.name[..]).collect::<Vec<_>>();
+    let formats = messages.iter().map(|msg| {
+        let msg_name = &msg.name;
+        let mut fields = Vec::new();
+        let args = msg
+            .args
+            .iter()
+            .map(|arg| {
+                // client-side requests do not hold the objects they create
+                if arg.typ == Type::NewId
+                    && !receiver
+                    && side == Side::Client
+                    && arg.interface.is_some()
+                {
+                    return quote!(#message_serde::ArgumentFormat::NewIdPlaceholder);
+                }
+                fields.push(&arg.name[..]);
+                let nullable = arg.allow_null;
+                match arg.typ {
+                    _ if arg.enum_.is_some() => {
+                        let enum_ident = dotted_to_relname(arg.enum_.as_ref().unwrap());
+                        let signed = arg.typ == Type::Int;
+                        quote!(#message_serde::ArgumentFormat::Enum {
+                            signed: #signed,
+                            bitfield: <#enum_ident as #message_serde::NamedEnum>::BITFIELD,
+                            value_of: <#enum_ident as #message_serde::NamedEnum>::value_of,
+                        })
+                    }
+                    Type::Int => quote!(#message_serde::ArgumentFormat::Int),
+                    Type::Uint => quote!(#message_serde::ArgumentFormat::Uint),
+                    Type::Fixed => quote!(#message_serde::ArgumentFormat::Fixed),
+                    Type::String => {
+                        quote!(#message_serde::ArgumentFormat::Str { nullable: #nullable })
+                    }
+                    Type::Array => {
+                        quote!(#message_serde::ArgumentFormat::Array { nullable: #nullable })
+                    }
+                    Type::Object => {
+                        quote!(#message_serde::ArgumentFormat::Object { nullable: #nullable })
+                    }
+                    Type::NewId if arg.interface.is_some() => {
+                        quote!(#message_serde::ArgumentFormat::NewId { nullable: #nullable })
+                    }
+                    Type::NewId => {
+                        let with_id = receiver || side == Side::Server;
+                        quote!(#message_serde::ArgumentFormat::UntypedNewId { with_id: #with_id })
+                    }
+                    Type::Fd => quote!(#message_serde::ArgumentFormat::Fd),
+                    Type::Destructor => unreachable!("Destructor is not a valid argument type."),
+                }
+            })
+            .collect::<Vec<_>>();
+        quote! {
+            #message_serde::MessageFormat {
+                name: #msg_name,
+                fields: &[#(#fields),*],
+                args: &[#(#args),*],
+            }
+        }
+    });
+
+    quote! {
+        impl #name {
+            /// Deserialize a message serialized by the `Serialize` implementation of this enum
+            ///
+            /// As the objects cannot be recreated from their id, the message is deserialized
+            /// into its raw form, with the given sender id. File descriptors cannot be
+            /// deserialized. See `wayland_commons::message_serde` for details.
+            pub fn deserialize_raw<'de, D: #serde::Deserializer<'de>>(
+                deserializer: D,
+                sender_id: u32,
+            ) -> Result<::wayland_commons::wire::Message, D::Error> {
+                const NAMES: &[&str] = &[#(#names),*];
+                const FORMATS: &[#message_serde::MessageFormat] = &[#(#formats),*];
+                #message_serde::deserialize_message(deserializer, #group_name, NAMES, FORMATS, sender_id)
+            }
+        }
+
+        impl #serde::Serialize for #name {
+            fn serialize<S: #serde::Serializer>(&self, __serializer: S) -> Result<S::Ok, S::Error> {
+                use #serde::ser::SerializeStructVariant;
+                match *self {
+                    #(#arms,)*
+                }
+            }
+        }
+    }
+}
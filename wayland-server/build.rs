@@ -10,11 +10,16 @@ fn main() {
     let out_dir_str = var("OUT_DIR").unwrap();
     let out_dir = Path::new(&out_dir_str);
 
+    let serde = var("CARGO_FEATURE_SERDE").ok().is_some();
+
     println!("cargo:rerun-if-changed={}", protocol_file);
-    generate_code_with_destructor_events(
-        protocol_file,
-        out_dir.join("wayland_api.rs"),
-        Side::Server,
-        &[("wl_callback", "done")],
-    );
+    let options = GenerationOptions::new(Side::Server)
+        .destructor_events(&[("wl_callback", "done")])
+        .serde(serde)
+        .cargo_warnings(true);
+    if let Err(e) =
+        try_generate_code_with_options(protocol_file, out_dir.join("wayland_api.rs"), &options)
+    {
+        panic!("Unable to generate code for protocol file `{}`: {}", protocol_file, e);
+    }
 }
@@ -58,6 +58,67 @@ impl Client {
         self.inner.kill()
     }
 
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Returns the error that made the server kill this client
+    ///
+    /// This is `None` while the client is alive, or if it disconnected or was killed using
+    /// `kill()`. Otherwise it is the error that killed it, notably:
+    ///
+    /// - an error of kind `InvalidData` wrapping a `MessageTooLarge` if an event could not
+    ///   be sent to the client because it does not fit in its buffers
+    /// - an error of kind `Other` if the outgoing queue of the client exceeded the limit set
+    ///   by `Display::set_max_pending_outgoing_bytes()`
+    /// - an error of kind `InvalidData` if the client sent a malformed request or made a
+    ///   protocol error
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn kill_reason(&self) -> Option<std::io::Error> {
+        self.inner.kill_reason()
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Sets a hook capturing the raw traffic of this client
+    ///
+    /// The hook will receive the exact bytes of all requests received and events sent
+    /// from now on, see the [`capture`](capture/index.html) module for details. A
+    /// `capture::CaptureWriter` can be used to save the traffic to a file. Passing `None`
+    /// stops the capture. To capture a connection from its start, set the hook on the client
+    /// returned by `Display::create_client()`.
+    ///
+    /// Returns the previously set hook, if any. Does nothing if the client is already dead.
+    ///
+    /// This method is only available with the rust implementation, as `libwayland-server.so`
+    /// does not give access to its sockets.
+    pub fn set_capture_hook(
+        &self,
+        hook: Option<Box<dyn crate::capture::CaptureHook>>,
+    ) -> Option<Box<dyn crate::capture::CaptureHook>> {
+        self.inner.set_capture_hook(hook)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the size of the buffers of this client
+    ///
+    /// See `Display::set_default_max_buffer_size()` for details. Does nothing if the client
+    /// is already dead.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn set_max_buffer_size(&self, size: usize) {
+        self.inner.set_max_buffer_size(size)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Returns the number of bytes of events sent to this client but not yet written
+    /// to its socket
+    ///
+    /// This grows when the client does not read its socket fast enough, see
+    /// `Display::set_outgoing_high_water_mark()`. Returns 0 if the client is dead.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn pending_outgoing_bytes(&self) -> usize {
+        self.inner.pending_outgoing_bytes()
+    }
+
     /// Returns a reference to the `UserDataMap` associated with this client
     ///
     /// See `UserDataMap` documentation for details about its use.
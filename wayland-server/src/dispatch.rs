@@ -0,0 +1,118 @@
+use crate::{DisplayHandle, Interface, Main, Resource};
+
+/// A state handling the binds of a global
+///
+/// This trait is implemented by the state of your compositor for each interface whose globals
+/// it handles. The globals are created with `Display::create_typed_global()`, along with a
+/// global data of type `G`, and the `bind()` method is invoked whenever a client instantiates
+/// one of them, with a mutable access to the state and the handle of the display.
+///
+/// The new resource is not assigned to anything, `bind()` is expected to assign it, usually
+/// with `Main::assign_dispatch()`.
+pub trait GlobalDispatch<I: Interface + AsRef<Resource<I>> + From<Resource<I>>, G = ()>:
+    Sized + 'static
+{
+    /// Handle the instantiation of a global by a client
+    ///
+    /// The version is the one requested by the client, which is not greater than the version
+    /// of the global.
+    fn bind(
+        &mut self,
+        resource: Main<I>,
+        version: u32,
+        global_data: &G,
+        dhandle: &DisplayHandle<Self>,
+    );
+}
+
+/// A state handling the requests of the resources of an interface
+///
+/// This trait is implemented by the state of your compositor for each interface whose resources
+/// it handles. The resources are assigned to the state with `Main::assign_dispatch()`, along
+/// with a user data of type `D`, and their requests are then given to the `request()` method,
+/// with a mutable access to the state and the handle of the display.
+///
+/// This is an alternative to assigning the resources to `Filter`s and recovering the state from
+/// the `DispatchData`. See the typed dispatching section of the `Display` documentation.
+///
+/// ```no_run
+/// # extern crate wayland_server;
+/// use wayland_server::{Display, DisplayHandle, Dispatch, GlobalDispatch, Main, Resource};
+/// use wayland_server::protocol::{wl_compositor, wl_surface};
+///
+/// struct State {
+///     surfaces: Vec<wl_surface::WlSurface>,
+/// }
+///
+/// struct SurfaceData {
+///     created_by_version: u32,
+/// }
+///
+/// impl GlobalDispatch<wl_compositor::WlCompositor> for State {
+///     fn bind(
+///         &mut self,
+///         resource: Main<wl_compositor::WlCompositor>,
+///         _: u32,
+///         _: &(),
+///         dhandle: &DisplayHandle<State>,
+///     ) {
+///         resource.assign_dispatch(dhandle, ());
+///     }
+/// }
+///
+/// impl Dispatch<wl_compositor::WlCompositor> for State {
+///     fn request(
+///         &mut self,
+///         resource: Main<wl_compositor::WlCompositor>,
+///         request: wl_compositor::Request,
+///         _: &(),
+///         dhandle: &DisplayHandle<State>,
+///     ) {
+///         if let wl_compositor::Request::CreateSurface { id } = request {
+///             let data = SurfaceData { created_by_version: resource.as_ref().version() };
+///             id.assign_dispatch(dhandle, data);
+///             self.surfaces.push((*id).clone());
+///         }
+///     }
+/// }
+///
+/// impl Dispatch<wl_surface::WlSurface, SurfaceData> for State {
+///     fn request(
+///         &mut self,
+///         _: Main<wl_surface::WlSurface>,
+///         _: wl_surface::Request,
+///         _: &SurfaceData,
+///         _: &DisplayHandle<State>,
+///     ) {
+///     }
+///
+///     fn destroyed(&mut self, resource: Resource<wl_surface::WlSurface>, _: &SurfaceData) {
+///         self.surfaces.retain(|surface| !surface.as_ref().equals(&resource));
+///     }
+/// }
+///
+/// let mut display = Display::<State>::new_typed();
+/// display.create_typed_global::<wl_compositor::WlCompositor, _>(4, ());
+/// ```
+pub trait Dispatch<I: Interface + AsRef<Resource<I>> + From<Resource<I>>, D = ()>:
+    Sized + 'static
+{
+    /// Handle a request received by a resource assigned to this state
+    fn request(
+        &mut self,
+        resource: Main<I>,
+        request: I::Request,
+        data: &D,
+        dhandle: &DisplayHandle<Self>,
+    );
+
+    /// Handle the destruction of a resource assigned to this state
+    ///
+    /// This is invoked when the resource is destroyed, either by a destructor request or
+    /// because its client disconnected. It is only invoked for the resources destroyed by the
+    /// dispatching and flushing methods of the display, as the state is not available
+    /// otherwise (while the display is dropped for example). Does nothing by default.
+    fn destroyed(&mut self, resource: Resource<I>, data: &D) {
+        let _ = (resource, data);
+    }
+}
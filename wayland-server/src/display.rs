@@ -1,23 +1,73 @@
+use std::cell::Cell;
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 use std::os::unix::io::{IntoRawFd, RawFd};
 use std::path::PathBuf;
+use std::rc::Rc;
 
 #[cfg(feature = "use_system_lib")]
 use wayland_sys::server::wl_display;
 
 use crate::imp::DisplayInner;
 
-use crate::{Client, Filter, Global, Interface, Main, Resource};
+use crate::{Client, Filter, Global, GlobalDispatch, Interface, Main, Resource};
 
 /// The wayland display
 ///
 /// This is the core of your wayland server, this object must
 /// be kept alive as long as your server is running. It allows
 /// you to manage listening sockets and clients.
-pub struct Display {
+///
+/// ## Typed dispatching
+///
+/// A display can also be generic over the state of your compositor, in which case it is
+/// created with `Display::new_typed()`. Its globals are then created with `create_typed_global()`
+/// and their binds are given to the [`GlobalDispatch`](trait.GlobalDispatch.html) implementation of
+/// the state, while the resources assigned with `Main::assign_dispatch()`, given the
+/// [`DisplayHandle`](struct.DisplayHandle.html) of the display, have their requests and
+/// destruction given to its [`Dispatch`](trait.Dispatch.html) implementation. The state is
+/// given to the dispatching methods, like `dispatch_typed()` and `flush_clients_typed()`, which
+/// only accept a state of the type of the display.
+///
+/// Resources assigned to a `Filter` can be used alongside, the `DispatchData` given to them is
+/// then empty. The methods taking arbitrary dispatch data are only available on untyped
+/// displays.
+pub struct Display<State = ()> {
     inner: DisplayInner,
+    state: StateSlot<State>,
+}
+
+// the state of a display while it is being dispatched, null otherwise
+type StateSlot<State> = Rc<Cell<*mut State>>;
+
+/// A handle to a typed display
+///
+/// This handle can be cloned and is required to assign resources to the state of the display
+/// with `Main::assign_dispatch()`. A reference to it is also given to the `GlobalDispatch` and
+/// `Dispatch` implementations, allowing them to assign the resources they create.
+pub struct DisplayHandle<State> {
+    state: StateSlot<State>,
+}
+
+impl<State> Clone for DisplayHandle<State> {
+    fn clone(&self) -> DisplayHandle<State> {
+        DisplayHandle { state: self.state.clone() }
+    }
+}
+
+impl<State> DisplayHandle<State> {
+    // invoke `f` with the state if the display is being dispatched, taking the state out of the
+    // display meanwhile so that it is never accessed twice
+    pub(crate) fn with_state<T, F: FnOnce(&mut State) -> T>(&self, f: F) -> Option<T> {
+        let state = self.state.replace(std::ptr::null_mut());
+        if state.is_null() {
+            return None;
+        }
+        let ret = f(unsafe { &mut *state });
+        self.state.set(state);
+        Some(ret)
+    }
 }
 
 impl Display {
@@ -30,9 +80,165 @@ impl Display {
     /// your need to add listening sockets using the `add_socket*` methods.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Display {
-        Display { inner: DisplayInner::new() }
+        Display::new_typed()
+    }
+
+    /// Flush events to the clients
+    ///
+    /// Will send as many pending events as possible to the respective sockets of the clients.
+    /// Will not block, but might not send everything if the socket buffer fills up.
+    ///
+    /// The provided `data` will be mutably accessible from all the callbacks that may be called
+    /// during this (destructors notably) via the [`DispatchData`](struct.DispatchData.html) mechanism.
+    /// If you don't need global data, you can just provide a `&mut ()` there.
+    pub fn flush_clients<T: std::any::Any>(&mut self, data: &mut T) {
+        self.flush_clients_with(&mut (), data)
+    }
+
+    /// Dispatches all pending messages to their respective filters
+    ///
+    /// This method will block waiting for messages until one of these occur:
+    ///
+    /// - Some messages are received, in which case all pending messages are processed
+    /// - The timeout is reached
+    /// - An error occurs
+    ///
+    /// If `timeout` is a duration of 0, this function will only process pending messages and then
+    /// return, not blocking.
+    ///
+    /// The provided `data` will be mutably accessible from all the callbacks, via the
+    /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
+    /// can just provide a `&mut ()` there.
+    ///
+    /// In general for good performance you will want to integrate the `Display` into your own event loop,
+    /// monitoring the file descriptor retrieved by the `get_poll_fd()` method, and only calling this method
+    /// when messages are available, with a timeout of `0`.
+    pub fn dispatch<T: std::any::Any>(
+        &mut self,
+        timeout: std::time::Duration,
+        data: &mut T,
+    ) -> IoResult<()> {
+        self.dispatch_with(timeout, &mut (), data)
+    }
+}
+
+impl<State> Display<State> {
+    /// Create a new display dispatching messages to a state of type `State`
+    ///
+    /// See the typed dispatching section of the `Display` documentation.
+    pub fn new_typed() -> Display<State> {
+        Display { inner: DisplayInner::new(), state: Rc::new(Cell::new(std::ptr::null_mut())) }
+    }
+
+    /// Create a new handle to this display
+    ///
+    /// See `DisplayHandle` documentation for its use.
+    pub fn handle(&self) -> DisplayHandle<State> {
+        DisplayHandle { state: self.state.clone() }
+    }
+
+    /// Create a new global object handled by the state
+    ///
+    /// Behaves like `create_global()`, the binds of this global being given to the
+    /// `GlobalDispatch<I, G>` implementation of the state along with `data`.
+    pub fn create_typed_global<I, G>(&mut self, version: u32, data: G) -> Global<I>
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+        State: GlobalDispatch<I, G>,
+        G: 'static,
+    {
+        let filter = typed_global_filter::<State, I, G>(self.handle(), data);
+        self.create_global(version, filter)
+    }
+
+    /// Create a new global object handled by the state, with a client filter
+    ///
+    /// Behaves like `create_global_with_filter()`, the binds of this global being given to the
+    /// `GlobalDispatch<I, G>` implementation of the state along with `data`.
+    pub fn create_typed_global_with_filter<I, G, F>(
+        &mut self,
+        version: u32,
+        data: G,
+        client_filter: F,
+    ) -> Global<I>
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+        State: GlobalDispatch<I, G>,
+        G: 'static,
+        F: FnMut(Client) -> bool + 'static,
+    {
+        let filter = typed_global_filter::<State, I, G>(self.handle(), data);
+        self.create_global_with_filter(version, filter, client_filter)
+    }
+
+    /// Flush events to the clients, giving the state to the destruction callbacks
+    ///
+    /// Behaves like `Display::flush_clients()`.
+    pub fn flush_clients_typed(&mut self, state: &mut State) {
+        self.flush_clients_with(state, &mut ())
+    }
+
+    /// Dispatches all pending messages to the state
+    ///
+    /// Behaves like `Display::dispatch()`, the requests of the resources assigned with
+    /// `Main::assign_dispatch()` being given to the `Dispatch` implementations of `state`.
+    pub fn dispatch_typed(
+        &mut self,
+        timeout: std::time::Duration,
+        state: &mut State,
+    ) -> IoResult<()> {
+        self.dispatch_with(timeout, state, &mut ())
+    }
+
+    // make `state` accessible to the resources assigned to it for the duration of `f`
+    fn with_state<T, F: FnOnce(&mut DisplayInner) -> T>(&mut self, state: &mut State, f: F) -> T {
+        struct Reset<'a, S>(&'a Cell<*mut S>);
+        impl<'a, S> Drop for Reset<'a, S> {
+            fn drop(&mut self) {
+                self.0.set(std::ptr::null_mut());
+            }
+        }
+        self.state.set(state);
+        let _reset = Reset(&self.state);
+        f(&mut self.inner)
+    }
+
+    fn flush_clients_with<T: std::any::Any>(&mut self, state: &mut State, data: &mut T) {
+        let data = crate::DispatchData::wrap(data);
+        self.with_state(state, |inner| inner.flush_clients(data))
     }
 
+    fn dispatch_with<T: std::any::Any>(
+        &mut self,
+        timeout: std::time::Duration,
+        state: &mut State,
+        data: &mut T,
+    ) -> IoResult<()> {
+        let data = crate::DispatchData::wrap(data);
+        let ms = timeout.as_millis();
+        let clamped_timeout = if ms > std::i32::MAX as u128 { std::i32::MAX } else { ms as i32 };
+        self.with_state(state, |inner| inner.dispatch(clamped_timeout, data))
+    }
+}
+
+fn typed_global_filter<State, I, G>(handle: DisplayHandle<State>, data: G) -> Filter<(Main<I>, u32)>
+where
+    I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    State: GlobalDispatch<I, G>,
+    G: 'static,
+{
+    Filter::new(move |(resource, version): (Main<I>, u32), _, _| {
+        let bound = handle.with_state(|state| state.bind(resource, version, &data, &handle));
+        if bound.is_none() {
+            eprintln!(
+                "[wayland-server] Bind of a {} global dispatched outside of its display, ignoring.",
+                I::NAME
+            );
+        }
+    })
+}
+
+impl<State> Display<State> {
     /// Create a new global object
     ///
     /// This object will be advertised to all clients, and they will
@@ -102,48 +308,6 @@ impl Display {
         ))
     }
 
-    /// Flush events to the clients
-    ///
-    /// Will send as many pending events as possible to the respective sockets of the clients.
-    /// Will not block, but might not send everything if the socket buffer fills up.
-    ///
-    /// The provided `data` will be mutably accessible from all the callbacks that may be called
-    /// during this (destructors notably) via the [`DispatchData`](struct.DispatchData.html) mechanism.
-    /// If you don't need global data, you can just provide a `&mut ()` there.
-    pub fn flush_clients<T: std::any::Any>(&mut self, data: &mut T) {
-        let data = crate::DispatchData::wrap(data);
-        self.inner.flush_clients(data)
-    }
-
-    /// Dispatches all pending messages to their respective filters
-    ///
-    /// This method will block waiting for messages until one of these occur:
-    ///
-    /// - Some messages are received, in which case all pending messages are processed
-    /// - The timeout is reached
-    /// - An error occurs
-    ///
-    /// If `timeout` is a duration of 0, this function will only process pending messages and then
-    /// return, not blocking.
-    ///
-    /// The provided `data` will be mutably accessible from all the callbacks, via the
-    /// [`DispatchData`](struct.DispatchData.html) mechanism. If you don't need global data, you
-    /// can just provide a `&mut ()` there.
-    ///
-    /// In general for good performance you will want to integrate the `Display` into your own event loop,
-    /// monitoring the file descriptor retrieved by the `get_poll_fd()` method, and only calling this method
-    /// when messages are available, with a timeout of `0`.
-    pub fn dispatch<T: std::any::Any>(
-        &mut self,
-        timeout: std::time::Duration,
-        data: &mut T,
-    ) -> IoResult<()> {
-        let data = crate::DispatchData::wrap(data);
-        let ms = timeout.as_millis();
-        let clamped_timeout = if ms > std::i32::MAX as u128 { std::i32::MAX } else { ms as i32 };
-        self.inner.dispatch(clamped_timeout, data)
-    }
-
     /// Retrieve the underlying file descriptor
     ///
     /// This file descriptor can be monitored for activity with a poll/epoll like mechanism.
@@ -156,7 +320,7 @@ impl Display {
     }
 }
 
-impl Display {
+impl<State> Display<State> {
     /// Add a listening socket to this display
     ///
     /// Wayland clients will be able to connect to your compositor from this socket.
@@ -231,10 +395,96 @@ impl Display {
         let data = crate::DispatchData::wrap(data);
         Client::make(self.inner.create_client(fd, data))
     }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the size of the buffers of the clients
+    ///
+    /// The size is in bytes, and is the maximum size of the messages that can be sent and
+    /// received on the connection of a client. It cannot be smaller than the default of 4096
+    /// bytes, and a message cannot be larger than 65535 bytes in any case.
+    ///
+    /// This size is used for the clients connecting from now on, it can be changed for an
+    /// existing client with `Client::set_max_buffer_size()`.
+    ///
+    /// Sending an event larger than this size is an error which kills the client, the
+    /// `MessageTooLarge` error naming the faulty event is printed to stderr and returned by
+    /// `Client::kill_reason()`.
+    ///
+    /// This method is only available with the rust implementation, as this version of
+    /// `libwayland-server.so` does not allow to configure its buffers.
+    pub fn set_default_max_buffer_size(&mut self, size: usize) {
+        self.inner.set_default_max_buffer_size(size)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the maximum size of the outgoing queue of the clients
+    ///
+    /// When a client does not read its socket fast enough, the events sent to it are kept
+    /// in an outgoing queue until they can be sent by `flush_clients()`. If this queue would
+    /// grow larger than `max` bytes, the client is killed. `None`, the default, means no limit.
+    ///
+    /// This limit applies to all clients, including the ones already connected. See
+    /// `set_outgoing_high_water_mark()` and `Client::pending_outgoing_bytes()` to handle
+    /// slow clients before reaching it.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn set_max_pending_outgoing_bytes(&mut self, max: Option<usize>) {
+        self.inner.set_max_pending_outgoing_bytes(max)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Enable or disable the detection of stale object ids
+    ///
+    /// Object ids are reused as soon as the client has acknowledged their destruction, so
+    /// an old `Resource` may refer to an id that now belongs to a different object. Such
+    /// uses are always ignored, and when this detection is enabled they are also reported
+    /// on stderr. This is meant as a debugging tool and is disabled by default.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn set_stale_id_detection(&mut self, enabled: bool) {
+        self.inner.set_stale_id_detection(enabled)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set a callback invoked when the outgoing queue of a client grows too large
+    ///
+    /// Whenever `flush_clients()` cannot send all pending events of a client and more than
+    /// `mark` bytes remain pending, the callback is invoked with the client and the number of
+    /// pending bytes. It is invoked again only after the client has dropped below the mark.
+    /// It allows you to decide whether to wait for the client or to kill it.
+    ///
+    /// This method is only available with the rust implementation.
+    pub fn set_outgoing_high_water_mark<F>(&mut self, mark: usize, callback: F)
+    where
+        F: FnMut(Client, usize, crate::DispatchData<'_>) + 'static,
+    {
+        self.inner.set_outgoing_high_water_mark(mark, callback)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the logger receiving the protocol messages of this display
+    ///
+    /// The logger will receive all requests dispatched and events sent from now on, for all
+    /// clients, see the [`debug`](debug/index.html) module for the available loggers. Passing
+    /// `None` disables the logging.
+    ///
+    /// If the `WAYLAND_DEBUG` env variable is set to `1` or `server` when the display is
+    /// created, a `debug::StderrLogger` is installed by default.
+    ///
+    /// Returns the previously set logger, if any.
+    ///
+    /// This method is only available with the rust implementation, as `libwayland-server.so`
+    /// does not allow to hook into its debug output.
+    pub fn set_protocol_logger(
+        &mut self,
+        logger: Option<std::sync::Arc<dyn crate::debug::ProtocolLogger>>,
+    ) -> Option<std::sync::Arc<dyn crate::debug::ProtocolLogger>> {
+        self.inner.set_protocol_logger(logger)
+    }
 }
 
 #[cfg(feature = "use_system_lib")]
-impl Display {
+impl<State> Display<State> {
     /// Retrieve a pointer from the C lib to this `wl_display`
     pub fn c_ptr(&self) -> *mut wl_display {
         self.inner.ptr()
@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::io::Result as IoResult;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::Display;
+
+/// An event source driving a `Display`
+///
+/// This adapter bundles the three operations needed to integrate a `Display` into an
+/// event loop:
+///
+/// - monitoring its file descriptor for readiness, using `poll_fd()`
+/// - once it is readable, dispatching the pending requests without blocking and flushing
+///   the events generated in the process to the clients, using `dispatch()`
+/// - before the event loop goes to sleep, flushing the events that were sent from outside
+///   of a dispatch (from a timer callback for example), using `flush()`
+///
+/// The poll fd is level-triggered: as long as there are pending messages it will be reported
+/// as readable, so there is no risk of missing events if a dispatch is delayed.
+///
+/// The `Display` is shared using a `Rc<RefCell<_>>` so that you can keep access to it to
+/// create globals or sockets. It will however be mutably borrowed during the dispatching,
+/// so your filters must not try to access it through this `Rc`.
+///
+/// If the `calloop` cargo feature is enabled, this type also implements calloop's `EventSource`
+/// trait. Its callback receives a `&mut Display` as metadata and is expected to invoke
+/// `Display::dispatch_and_flush()` with the event loop shared data, or
+/// `Display::dispatch_and_flush_typed()` with the state of a typed display:
+///
+/// ```ignore
+/// let source = DisplaySource::new(display.clone());
+/// event_loop.handle().insert_source(source, |(), display, state: &mut State| {
+///     display.dispatch_and_flush(state)
+/// })?;
+/// // and as part of the loop iterations
+/// event_loop.run(None, &mut state, |state| display.borrow_mut().flush_clients(state))?;
+/// ```
+pub struct DisplaySource<State = ()> {
+    display: Rc<RefCell<Display<State>>>,
+    #[cfg(feature = "calloop")]
+    source: calloop::generic::Generic<PollFd>,
+}
+
+impl<State> DisplaySource<State> {
+    /// Create a new event source driving given display
+    pub fn new(display: Rc<RefCell<Display<State>>>) -> DisplaySource<State> {
+        #[cfg(feature = "calloop")]
+        let source = calloop::generic::Generic::new(
+            PollFd(display.borrow().get_poll_fd()),
+            calloop::Interest::READ,
+            calloop::Mode::Level,
+        );
+        DisplaySource {
+            display,
+            #[cfg(feature = "calloop")]
+            source,
+        }
+    }
+
+    /// Access the display driven by this source
+    pub fn display(&self) -> &Rc<RefCell<Display<State>>> {
+        &self.display
+    }
+
+    /// Retrieve the file descriptor to monitor for readability
+    ///
+    /// This is the same file descriptor as the one returned by `Display::get_poll_fd()`.
+    pub fn poll_fd(&self) -> RawFd {
+        self.display.borrow().get_poll_fd()
+    }
+
+    /// Process the pending messages of the display, giving them to the state
+    ///
+    /// Behaves like `DisplaySource::dispatch()`, see `Display::dispatch_and_flush_typed()`.
+    pub fn dispatch_typed(&self, state: &mut State) -> IoResult<()> {
+        self.display.borrow_mut().dispatch_and_flush_typed(state)
+    }
+
+    /// Flush events to the clients, giving the state to the destruction callbacks
+    ///
+    /// Behaves like `DisplaySource::flush()`, see `Display::flush_clients_typed()`.
+    pub fn flush_typed(&self, state: &mut State) {
+        self.display.borrow_mut().flush_clients_typed(state)
+    }
+}
+
+impl DisplaySource {
+    /// Process the pending messages of the display
+    ///
+    /// This is meant to be called when the poll fd is reported as readable. It never blocks.
+    /// See `Display::dispatch_and_flush()` for details.
+    pub fn dispatch<T: std::any::Any>(&self, data: &mut T) -> IoResult<()> {
+        self.display.borrow_mut().dispatch_and_flush(data)
+    }
+
+    /// Flush events to the clients
+    ///
+    /// This is meant to be called before your event loop goes to sleep, to ensure that
+    /// the events sent outside of a dispatch reach the clients. See `Display::flush_clients()`.
+    pub fn flush<T: std::any::Any>(&self, data: &mut T) {
+        self.display.borrow_mut().flush_clients(data)
+    }
+}
+
+impl Display {
+    /// Dispatch pending messages and flush the resulting events
+    ///
+    /// This method processes all pending messages without blocking, as `dispatch()` with a
+    /// timeout of 0 would, and then sends to the clients the events generated in the process,
+    /// as `flush_clients()` would.
+    ///
+    /// It is the operation to invoke whenever the file descriptor retrieved from
+    /// `get_poll_fd()` becomes readable.
+    pub fn dispatch_and_flush<T: std::any::Any>(&mut self, data: &mut T) -> IoResult<()> {
+        let ret = self.dispatch(Duration::from_millis(0), data);
+        self.flush_clients(data);
+        ret
+    }
+}
+
+impl<State> Display<State> {
+    /// Dispatch pending messages to the state and flush the resulting events
+    ///
+    /// Behaves like `Display::dispatch_and_flush()`, using `dispatch_typed()` and
+    /// `flush_clients_typed()`.
+    pub fn dispatch_and_flush_typed(&mut self, state: &mut State) -> IoResult<()> {
+        let ret = self.dispatch_typed(Duration::from_millis(0), state);
+        self.flush_clients_typed(state);
+        ret
+    }
+}
+
+#[cfg(feature = "calloop")]
+struct PollFd(RawFd);
+
+#[cfg(feature = "calloop")]
+impl std::os::unix::io::AsRawFd for PollFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+#[cfg(feature = "calloop")]
+impl<State> calloop::EventSource for DisplaySource<State> {
+    type Event = ();
+    type Metadata = Display<State>;
+    type Ret = IoResult<()>;
+    type Error = std::io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: calloop::Readiness,
+        token: calloop::Token,
+        mut callback: F,
+    ) -> IoResult<calloop::PostAction>
+    where
+        F: FnMut((), &mut Display<State>) -> IoResult<()>,
+    {
+        let display = &self.display;
+        self.source.process_events(readiness, token, |_, _| {
+            callback((), &mut display.borrow_mut())?;
+            Ok(calloop::PostAction::Continue)
+        })
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut calloop::Poll,
+        token_factory: &mut calloop::TokenFactory,
+    ) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut calloop::Poll,
+        token_factory: &mut calloop::TokenFactory,
+    ) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut calloop::Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
@@ -55,6 +55,16 @@
 //! a request), unless the exact message received is a destructor (which is indicated in the API
 //! documentations).
 //!
+//! ### Typed dispatching
+//!
+//! Alternatively, the state of your compositor can implement the `GlobalDispatch<I, G>` and
+//! `Dispatch<I, D>` traits for the interfaces it handles. Globals created with
+//! `Display::create_typed_global()` then have their binds given to the state, and resources
+//! assigned with `Main::assign_dispatch()`, given the `DisplayHandle` of the display, have their
+//! requests and destruction given to it, along with their typed global data or user data, when
+//! dispatching a `Display<State>` created with `Display::new_typed()`. Both models can be used on
+//! the same display.
+//!
 //! ## General structure
 //!
 //! The core of your server is the `Display` object. It represent the ability of your program to
@@ -65,6 +75,15 @@
 //! yourself using the `Display::flush_clients` and `Display::dispatch` methods. The `Display::get_poll_fd`
 //! methods provides you with a file descriptor that can be used in a polling structure to integrate
 //! the wayland socket in an event loop.
+//!
+//! The `DisplaySource` adapter bundles these operations together, and implements calloop's
+//! `EventSource` trait if the `calloop` cargo feature is enabled.
+//!
+//! If you activate the `serde` cargo feature, the `Request` and `Event` enums of the `protocol`
+//! module implement serde's `Serialize` trait, objects being serialized by their id, and the
+//! other enums implement `Serialize` and `Deserialize` by the names of their entries. As objects
+//! cannot be recreated from their id, the messages are deserialized into their raw form by the
+//! `deserialize_raw()` method of these enums.
 
 #![warn(missing_docs)]
 
@@ -75,19 +94,25 @@ extern crate bitflags;
 extern crate wayland_sys;
 
 mod client;
+mod dispatch;
 mod display;
+mod event_source;
 mod globals;
 mod resource;
 
 pub use client::Client;
-pub use display::Display;
+pub use dispatch::{Dispatch, GlobalDispatch};
+pub use display::{Display, DisplayHandle};
+pub use event_source::DisplaySource;
 pub use globals::Global;
 pub use resource::{Main, Resource};
 
 pub use anonymous_object::AnonymousObject;
 pub use wayland_commons::user_data::UserDataMap;
+pub use wayland_commons::{capture, debug};
 pub use wayland_commons::{
     filter::{DispatchData, Filter},
+    wire::{BorrowedFd, MessageTooLarge, OwnedFd},
     Interface, MessageGroup, NoMessage,
 };
 
@@ -119,7 +144,9 @@ pub mod protocol {
     pub(crate) use crate::{AnonymousObject, Main, Resource, ResourceMap};
     pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
     pub(crate) use wayland_commons::smallvec;
-    pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+    pub(crate) use wayland_commons::wire::{
+        Argument, ArgumentType, BorrowedFd, Message, MessageDesc, OwnedFd,
+    };
     pub(crate) use wayland_commons::{Interface, MessageGroup};
     pub(crate) use wayland_sys as sys;
     include!(concat!(env!("OUT_DIR"), "/wayland_api.rs"));
@@ -6,7 +6,7 @@ use wayland_commons::{Interface, MessageGroup};
 use wayland_sys::server::*;
 
 use crate::imp::ResourceInner;
-use crate::{Client, Filter};
+use crate::{Client, Dispatch, DisplayHandle, Filter};
 
 /// An handle to a wayland resource
 ///
@@ -322,6 +322,61 @@ where
         self.inner.as_ref().inner.assign_destructor(filter)
     }
 
+    /// Assign this object to the state of its display, with given user data
+    ///
+    /// All future requests received by this object will be delivered to the `Dispatch<I, D>`
+    /// implementation of `State`, the state the display of `dhandle` is dispatched with by
+    /// methods like `Display::dispatch_typed()`. Its destruction will be delivered to
+    /// `Dispatch::destroyed()` as well, replacing any destructor previously assigned.
+    ///
+    /// The data is stored as the user data of the resource, and can be retrieved from any
+    /// handle to it with `user_data().get::<D>()`.
+    ///
+    /// **Panics** if the user data of the resource was already set.
+    pub fn assign_dispatch<State, D>(&self, dhandle: &DisplayHandle<State>, data: D)
+    where
+        I: AsRef<Resource<I>> + From<Resource<I>>,
+        State: Dispatch<I, D>,
+        D: 'static,
+        I::Request: MessageGroup<Map = crate::ResourceMap>,
+    {
+        let resource = self.inner.as_ref();
+        let mut inserted = false;
+        resource.user_data().set(|| {
+            inserted = true;
+            data
+        });
+        assert!(
+            inserted || !resource.is_alive(),
+            "The user data of {}@{} was already set.",
+            I::NAME,
+            resource.id()
+        );
+        let request_dhandle = dhandle.clone();
+        self.assign(Filter::new(move |(resource, request): (Main<I>, I::Request), _, _| {
+            let handle = resource.as_ref().clone();
+            let data = match handle.user_data().get::<D>() {
+                Some(data) => data,
+                None => return,
+            };
+            let dhandle = &request_dhandle;
+            if dhandle.with_state(|state| state.request(resource, request, data, dhandle)).is_none() {
+                eprintln!(
+                    "[wayland-server] Request for {}@{} dispatched outside of its display, ignoring.",
+                    I::NAME,
+                    handle.id()
+                );
+            }
+        }));
+        let dhandle = dhandle.clone();
+        self.assign_destructor(Filter::new(move |resource: Resource<I>, _, _| {
+            // the state is not available when the resource is destroyed outside of a dispatch
+            if let Some(data) = resource.user_data().get::<D>() {
+                dhandle.with_state(|state| state.destroyed(resource.clone(), data));
+            }
+        }));
+    }
+
     /// Create a `Main` instance from a C pointer to a new object
     ///
     /// Create a `Main` from a raw pointer to a wayland object from the
@@ -2,16 +2,19 @@ use std::cell::RefCell;
 use std::ffi::CString;
 use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
 use std::rc::Rc;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, ThreadId};
 
 use nix::Result as NixResult;
 
-use wayland_commons::debug;
+use wayland_commons::capture::CaptureHook;
+use wayland_commons::debug::{LoggedMessage, MessageDirection};
 use wayland_commons::map::{Object, ObjectMap, ObjectMetadata, SERVER_ID_LIMIT};
-use wayland_commons::socket::{BufferedSocket, Socket};
-use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc, MessageParseError};
+use wayland_commons::socket::{BufferedSocket, Socket, MAX_BYTES_OUT};
+use wayland_commons::wire::{
+    Argument, ArgumentType, ArgumentView, Message, MessageDesc, MessageParseError, MessageTooLarge,
+};
 use wayland_commons::{smallvec, ThreadGuard};
 
 use crate::{DispatchData, Interface, UserDataMap};
@@ -19,15 +22,34 @@ use crate::{DispatchData, Interface, UserDataMap};
 use super::event_loop_glue::{FdManager, Token};
 use super::globals::GlobalManager;
 use super::resources::{ObjectMeta, ResourceDestructor, ResourceInner};
-use super::{Dispatched, WAYLAND_DEBUG};
+use super::{Dispatched, SharedLogger};
 
 #[derive(Clone, Debug)]
 pub(crate) enum Error {
     Protocol,
     Parse(MessageParseError),
+    TooLarge(MessageTooLarge),
+    QueueFull,
     Nix(::nix::Error),
 }
 
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> std::io::Error {
+        use std::io::ErrorKind;
+        match error {
+            Error::Protocol => {
+                std::io::Error::new(ErrorKind::InvalidData, "the client made a protocol error")
+            }
+            Error::Parse(e) => std::io::Error::new(ErrorKind::InvalidData, e),
+            Error::TooLarge(e) => std::io::Error::new(ErrorKind::InvalidData, e),
+            Error::QueueFull => {
+                std::io::Error::new(ErrorKind::Other, "the outgoing queue of the client is full")
+            }
+            Error::Nix(e) => e.as_errno().unwrap_or(::nix::errno::Errno::EINVAL).into(),
+        }
+    }
+}
+
 type BoxedClientDestructor = Box<dyn FnMut(Arc<UserDataMap>, DispatchData<'_>)>;
 
 pub(crate) struct ClientConnection {
@@ -38,6 +60,9 @@ pub(crate) struct ClientConnection {
     last_error: Option<Error>,
     pending_destructors: Vec<ResourceInner>,
     zombie_clients: Arc<Mutex<Vec<ClientConnection>>>,
+    pub(crate) logger: SharedLogger,
+    stale_id_detection: Arc<AtomicBool>,
+    above_high_water_mark: bool,
 }
 
 impl ClientConnection {
@@ -45,8 +70,13 @@ impl ClientConnection {
         fd: RawFd,
         display_object: Object<ObjectMeta>,
         zombies: Arc<Mutex<Vec<ClientConnection>>>,
+        logger: SharedLogger,
+        stale_id_detection: Arc<AtomicBool>,
+        buffer_size: usize,
+        max_pending_bytes: Option<usize>,
     ) -> ClientConnection {
-        let socket = BufferedSocket::new(Socket::from_raw_fd(fd));
+        let mut socket = BufferedSocket::with_max_buffer_size(Socket::from_raw_fd(fd), buffer_size);
+        socket.set_max_pending_bytes(max_pending_bytes);
 
         let mut map = ObjectMap::new();
         // Insert first pre-existing object
@@ -60,9 +90,16 @@ impl ClientConnection {
             last_error: None,
             pending_destructors: Vec::new(),
             zombie_clients: zombies,
+            logger,
+            stale_id_detection,
+            above_high_water_mark: false,
         }
     }
 
+    pub(crate) fn stale_id_detection(&self) -> bool {
+        self.stale_id_detection.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn schedule_destructor(&mut self, resource: ResourceInner) {
         self.pending_destructors.push(resource);
     }
@@ -75,7 +112,7 @@ impl ClientConnection {
         }
     }
 
-    pub(crate) fn write_message(&mut self, msg: &Message) -> NixResult<()> {
+    pub(crate) fn write_message(&mut self, msg: Message) -> NixResult<()> {
         self.socket.write_message(msg)
     }
 
@@ -83,11 +120,41 @@ impl ClientConnection {
         self.socket.flush()
     }
 
+    // internal method
+    //
+    // records that an event could not be sent because it is too large, which will kill the
+    // client the next time its requests are processed
+    pub(crate) fn message_too_large(&mut self, interface: &'static str, message: &'static str) {
+        let error =
+            MessageTooLarge { interface, message, buffer_size: self.socket.max_buffer_size() };
+        if self.last_error.is_some() {
+            return;
+        }
+        eprintln!("[wayland-server] {}", error);
+        self.last_error = Some(Error::TooLarge(error));
+    }
+
+    // internal method
+    //
+    // records that an event could not be sent, which will kill the client
+    pub(crate) fn write_failed(&mut self, error: ::nix::Error) {
+        if self.last_error.is_some() {
+            return;
+        }
+        self.last_error = Some(match error {
+            ::nix::Error::Sys(::nix::errno::Errno::ENOBUFS) => {
+                eprintln!("[wayland-server] The outgoing queue of a client is full, killing it.");
+                Error::QueueFull
+            }
+            error => Error::Nix(error),
+        });
+    }
+
     pub(crate) fn delete_id(&mut self, id: u32) -> NixResult<()> {
         self.map.lock().unwrap().remove(id);
 
         if id < SERVER_ID_LIMIT {
-            self.write_message(&Message {
+            self.write_message(Message {
                 sender_id: 1,
                 opcode: 1,
                 args: smallvec![Argument::Uint(id)],
@@ -103,39 +170,45 @@ impl ClientConnection {
         }
         // acquire the map lock, this means no objects can be created nor destroyed while we
         // are reading requests
-        let mut map = self.map.lock().unwrap();
+        let map = self.map.clone();
+        let mut map = map.lock().unwrap();
         // read messages
-        let ret = self.socket.read_one_message(|id, opcode| {
-            map.find(id).and_then(|o| o.requests.get(opcode as usize)).map(|desc| desc.signature)
-        });
-        let msg = match ret {
-            Ok(msg) => msg,
-            Err(MessageParseError::Malformed) => {
-                self.last_error = Some(Error::Parse(MessageParseError::Malformed));
-                return Err(Error::Parse(MessageParseError::Malformed));
-            }
-            Err(MessageParseError::MissingData) | Err(MessageParseError::MissingFD) => {
+        match self.read_buffered_request(&mut map) {
+            Err(Error::Parse(MessageParseError::MissingData))
+            | Err(Error::Parse(MessageParseError::MissingFD)) => {
                 // missing data, read sockets and try again
                 self.socket.fill_incoming_buffers().map_err(Error::Nix)?;
 
-                let msg = self.socket.read_one_message(|id, opcode| {
-                    map.find(id)
-                        .and_then(|o| o.requests.get(opcode as usize))
-                        .map(|desc| desc.signature)
-                });
-
-                match msg {
-                    Ok(msg) => msg,
-                    Err(MessageParseError::Malformed) => {
-                        self.last_error = Some(Error::Parse(MessageParseError::Malformed));
-                        return Err(Error::Parse(MessageParseError::Malformed));
-                    }
-                    Err(MessageParseError::MissingData) | Err(MessageParseError::MissingFD) => {
+                match self.read_buffered_request(&mut map) {
+                    Err(Error::Parse(MessageParseError::MissingData))
+                    | Err(Error::Parse(MessageParseError::MissingFD)) => {
                         // still nothing, there is nothing to read
-                        return Ok(None);
+                        Ok(None)
                     }
+                    ret => ret,
                 }
             }
+            ret => ret,
+        }
+    }
+
+    // internal method
+    //
+    // parses a request from the incoming buffers without copying it, and only takes
+    // ownership of its contents if it is to be dispatched
+    fn read_buffered_request(
+        &mut self,
+        map: &mut ObjectMap<ObjectMeta>,
+    ) -> Result<Option<Message>, Error> {
+        let msg = match self.socket.read_one_message_view(|id, opcode| {
+            map.find(id).and_then(|o| o.requests.get(opcode as usize)).map(|desc| desc.signature)
+        }) {
+            Ok(msg) => msg,
+            Err(MessageParseError::Malformed) => {
+                self.last_error = Some(Error::Parse(MessageParseError::Malformed));
+                return Err(Error::Parse(MessageParseError::Malformed));
+            }
+            Err(e) => return Err(Error::Parse(e)),
         };
 
         // we reach here, there is now a message to process in msg
@@ -147,11 +220,7 @@ impl ClientConnection {
                 // this is a message sent to a destroyed object
                 // to avoid dying because of races, we just consume it into void
                 // closing any associated FDs
-                for a in msg.args {
-                    if let Argument::Fd(fd) = a {
-                        let _ = ::nix::unistd::close(fd);
-                    }
-                }
+                drop(msg);
 
                 return Ok(None);
             }
@@ -162,7 +231,7 @@ impl ClientConnection {
             let new_id = msg
                 .args
                 .iter()
-                .flat_map(|a| if let Argument::NewId(nid) = *a { Some(nid) } else { None })
+                .flat_map(|a| if let ArgumentView::NewId(nid) = *a { Some(nid) } else { None })
                 .next()
                 .unwrap();
 
@@ -187,17 +256,25 @@ impl ClientConnection {
             );
         }
 
-        Ok(Some(msg))
+        // the request is dispatched as a generated message, owning its strings and arrays
+        Ok(Some(msg.into_owned()))
     }
 
     fn cleanup(mut self, mut data: crate::DispatchData) {
         let dummy_client = ClientInner {
             data: Arc::new(Mutex::new(None)),
+            kill_reason: Arc::new(Mutex::new(None)),
             user_data_map: self.user_data_map.clone(),
             loop_thread: thread::current().id(),
         };
         self.map.lock().unwrap().with_all(|id, obj| {
-            let resource = ResourceInner { id, object: obj.clone(), client: dummy_client.clone() };
+            // the client is dead, so the generation of the resource does not matter anymore
+            let resource = ResourceInner {
+                id,
+                generation: 0,
+                object: obj.clone(),
+                client: dummy_client.clone(),
+            };
             obj.meta.alive.store(false, Ordering::Release);
             if let Some(ref dest) = obj.meta.destructor {
                 (&mut *dest.get().borrow_mut())(resource, data.reborrow());
@@ -213,6 +290,7 @@ impl ClientConnection {
 #[derive(Clone)]
 pub(crate) struct ClientInner {
     pub(crate) data: Arc<Mutex<Option<ClientConnection>>>,
+    kill_reason: Arc<Mutex<Option<Error>>>,
     user_data_map: Arc<UserDataMap>,
     pub(crate) loop_thread: ThreadId,
 }
@@ -234,6 +312,7 @@ impl ClientInner {
 
     pub(crate) fn kill(&self) {
         if let Some(mut clientconn) = self.data.lock().unwrap().take() {
+            *self.kill_reason.lock().unwrap() = clientconn.last_error.take();
             let _ = clientconn.socket.flush();
             // call all objects destructors
             let zombies = clientconn.zombie_clients.clone();
@@ -241,6 +320,35 @@ impl ClientInner {
         }
     }
 
+    pub(crate) fn kill_reason(&self) -> Option<std::io::Error> {
+        self.kill_reason.lock().unwrap().clone().map(Into::into)
+    }
+
+    pub(crate) fn set_capture_hook(
+        &self,
+        hook: Option<Box<dyn CaptureHook>>,
+    ) -> Option<Box<dyn CaptureHook>> {
+        if let Some(ref mut cx) = *self.data.lock().unwrap() {
+            cx.socket.set_capture_hook(hook)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn set_max_buffer_size(&self, size: usize) {
+        if let Some(ref mut cx) = *self.data.lock().unwrap() {
+            cx.socket.set_max_buffer_size(size)
+        }
+    }
+
+    pub(crate) fn pending_outgoing_bytes(&self) -> usize {
+        if let Some(ref cx) = *self.data.lock().unwrap() {
+            cx.socket.pending_outgoing_bytes()
+        } else {
+            0
+        }
+    }
+
     pub(crate) fn user_data_map(&self) -> &UserDataMap {
         &self.user_data_map
     }
@@ -266,7 +374,7 @@ impl ClientInner {
 
     pub(crate) fn post_error(&self, object: u32, error_code: u32, msg: String) {
         if let Some(ref mut data) = *self.data.lock().unwrap() {
-            let _ = data.write_message(&Message {
+            let _ = data.write_message(Message {
                 sender_id: 1,
                 opcode: 0,
                 args: smallvec![
@@ -314,29 +422,53 @@ impl ClientInner {
         }
     }
 
+    pub(crate) fn stale_id_detection(&self) -> bool {
+        if let Some(ref cx) = *self.data.lock().unwrap() {
+            cx.stale_id_detection()
+        } else {
+            false
+        }
+    }
+
+    // returns false if there is no object with this id and generation
     pub(crate) fn set_dispatcher_for(
         &self,
         id: u32,
+        generation: u32,
         dispatcher: Arc<ThreadGuard<RefCell<dyn super::Dispatcher>>>,
-    ) {
+    ) -> bool {
         let guard = self.data.lock().unwrap();
         if let Some(ref cx) = *guard {
-            let _ = cx.map.lock().unwrap().with(id, move |obj| {
-                obj.meta.dispatcher = dispatcher;
-            });
+            cx.map
+                .lock()
+                .unwrap()
+                .with_generation(id, generation, move |obj| {
+                    obj.meta.dispatcher = dispatcher;
+                })
+                .is_ok()
+        } else {
+            false
         }
     }
 
+    // returns false if there is no object with this id and generation
     pub(crate) fn set_destructor_for(
         &self,
         id: u32,
+        generation: u32,
         destructor: Arc<ThreadGuard<ResourceDestructor>>,
-    ) {
+    ) -> bool {
         let guard = self.data.lock().unwrap();
         if let Some(ref cx) = *guard {
-            let _ = cx.map.lock().unwrap().with(id, move |obj| {
-                obj.meta.destructor = Some(destructor);
-            });
+            cx.map
+                .lock()
+                .unwrap()
+                .with_generation(id, generation, move |obj| {
+                    obj.meta.destructor = Some(destructor);
+                })
+                .is_ok()
+        } else {
+            false
         }
     }
 }
@@ -346,21 +478,55 @@ pub(crate) struct ClientManager {
     clients: Vec<(RefCell<Option<Token>>, ClientInner)>,
     zombie_clients: Arc<Mutex<Vec<ClientConnection>>>,
     global_mgr: Rc<RefCell<GlobalManager>>,
+    logger: SharedLogger,
+    stale_id_detection: Arc<AtomicBool>,
+    pub(crate) buffer_size: usize,
+    max_pending_bytes: Option<usize>,
+    high_water_mark: Option<(usize, Box<HighWaterMarkCallback>)>,
 }
 
+type HighWaterMarkCallback = dyn FnMut(crate::Client, usize, DispatchData<'_>);
+
 impl ClientManager {
     pub(crate) fn new(
         epoll_mgr: Rc<FdManager>,
         global_mgr: Rc<RefCell<GlobalManager>>,
+        logger: SharedLogger,
     ) -> ClientManager {
         ClientManager {
             epoll_mgr,
             clients: Vec::new(),
             zombie_clients: Arc::new(Mutex::new(Vec::new())),
             global_mgr,
+            logger,
+            stale_id_detection: Arc::new(AtomicBool::new(false)),
+            buffer_size: MAX_BYTES_OUT,
+            max_pending_bytes: None,
+            high_water_mark: None,
+        }
+    }
+
+    pub(crate) fn set_max_pending_bytes(&mut self, max: Option<usize>) {
+        self.max_pending_bytes = max;
+        for (_, client) in &self.clients {
+            if let Some(ref mut cx) = *client.data.lock().unwrap() {
+                cx.socket.set_max_pending_bytes(max);
+            }
         }
     }
 
+    pub(crate) fn set_stale_id_detection(&self, enabled: bool) {
+        self.stale_id_detection.store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_high_water_mark(
+        &mut self,
+        mark: usize,
+        callback: Box<HighWaterMarkCallback>,
+    ) {
+        self.high_water_mark = Some((mark, callback));
+    }
+
     pub(crate) unsafe fn init_client(
         &mut self,
         fd: RawFd,
@@ -378,17 +544,27 @@ impl ClientManager {
             childs_from_requests: display_req_child,
         };
 
-        let cx = ClientConnection::new(fd, display_object, self.zombie_clients.clone());
+        let cx = ClientConnection::new(
+            fd,
+            display_object,
+            self.zombie_clients.clone(),
+            self.logger.clone(),
+            self.stale_id_detection.clone(),
+            self.buffer_size,
+            self.max_pending_bytes,
+        );
         let map = cx.map.clone();
         let user_data_map = cx.user_data_map.clone();
 
         let client = ClientInner {
             data: Arc::new(Mutex::new(Some(cx))),
+            kill_reason: Arc::new(Mutex::new(None)),
             user_data_map,
             loop_thread: thread::current().id(), // init_client is only called by the display, which does not change threads
         };
 
-        let implementation = ClientImplementation { inner: client.clone(), map };
+        let implementation =
+            ClientImplementation { inner: client.clone(), map, logger: self.logger.clone() };
 
         // process any pending messages before inserting it into the event loop
         implementation.process_messages(data);
@@ -419,10 +595,30 @@ impl ClientManager {
     pub(crate) fn flush_all(&mut self, mut disp_data: crate::DispatchData) {
         // flush all clients and cleanup dead ones
         let epoll_mgr = self.epoll_mgr.clone();
+        let mark = self.high_water_mark.as_ref().map(|&(mark, _)| mark);
+        let mut failed = Vec::new();
+        let mut above_mark = Vec::new();
         self.clients.retain(|&(ref s, ref c)| {
             if let Some(ref mut data) = *c.data.lock().unwrap() {
                 data.call_destructors(disp_data.reborrow());
-                data.flush().is_ok()
+                if data.last_error.is_some() {
+                    // an event could not be sent
+                    failed.push(c.clone());
+                }
+                let ret = match data.flush() {
+                    // the socket is full, the rest will be sent later
+                    Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => true,
+                    ret => ret.is_ok(),
+                };
+                if let Some(mark) = mark {
+                    let pending = data.socket.pending_outgoing_bytes();
+                    let above = pending >= mark;
+                    if above && !data.above_high_water_mark {
+                        above_mark.push((c.clone(), pending));
+                    }
+                    data.above_high_water_mark = above;
+                }
+                ret
             } else {
                 // This is a dead client, clean it up
                 if let Some(token) = s.borrow_mut().take() {
@@ -432,6 +628,18 @@ impl ClientManager {
             }
         });
 
+        for client in failed {
+            client.kill();
+        }
+
+        if let Some((_, ref mut callback)) = self.high_water_mark {
+            for (client, pending) in above_mark {
+                if client.alive() {
+                    callback(crate::Client::make(client), pending, disp_data.reborrow());
+                }
+            }
+        }
+
         let mut guard = self.zombie_clients.lock().unwrap();
         for zombie in guard.drain(..) {
             zombie.cleanup(disp_data.reborrow());
@@ -440,7 +648,7 @@ impl ClientManager {
 
     // kill & cleanup all clients
     pub(crate) fn kill_all(&mut self) {
-        for &(_, ref client) in &self.clients {
+        for (_, client) in &self.clients {
             client.kill();
         }
         self.flush_all(crate::DispatchData::wrap(&mut ()));
@@ -522,6 +730,7 @@ fn no_child(_: u16, _: u32, _: &ObjectMeta) -> Option<Object<ObjectMeta>> {
 struct ClientImplementation {
     inner: ClientInner,
     map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
+    logger: SharedLogger,
 }
 
 impl ClientImplementation {
@@ -567,6 +776,17 @@ impl ClientImplementation {
             };
 
             let object = res.object.clone();
+            if let Some(ref logger) = *self.logger.lock().unwrap() {
+                logger.log(&LoggedMessage {
+                    direction: MessageDirection::Dispatched,
+                    timestamp: std::time::SystemTime::now(),
+                    interface: object.interface,
+                    id,
+                    alive: true,
+                    message: object.requests[opcode as usize].name,
+                    args: &msg.args,
+                });
+            }
             let mut dispatcher = object.meta.dispatcher.get().borrow_mut();
 
             match dispatcher.dispatch(msg, res, &mut resourcemap, data.reborrow()) {
@@ -609,15 +829,6 @@ impl super::Dispatcher for DisplayDispatcher {
     ) -> Dispatched {
         use crate::protocol::wl_callback;
 
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_dispatched_message(
-                "wl_display",
-                1,
-                DISPLAY_REQUESTS[msg.opcode as usize].name,
-                &msg.args,
-            );
-        }
-
         match msg.opcode {
             // sync
             0 => {
@@ -673,15 +884,6 @@ impl super::Dispatcher for RegistryDispatcher {
         map: &mut super::ResourceMap,
         data: crate::DispatchData,
     ) -> Dispatched {
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_dispatched_message(
-                "wl_registry",
-                resource.id,
-                REGISTRY_REQUESTS[msg.opcode as usize].name,
-                &msg.args,
-            );
-        }
-
         let mut iter = msg.args.into_iter();
         let global_id = match iter.next() {
             Some(Argument::Uint(u)) => u,
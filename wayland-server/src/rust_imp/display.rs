@@ -6,7 +6,9 @@ use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::os::unix::net::UnixListener;
 use std::path::Path;
 use std::rc::Rc;
-use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use wayland_commons::debug::{ProtocolLogger, StderrLogger};
 
 use crate::display::get_runtime_dir;
 use crate::{Interface, Main, Resource};
@@ -14,7 +16,7 @@ use crate::{Interface, Main, Resource};
 use super::clients::ClientManager;
 use super::event_loop_glue::{FdManager, Token};
 use super::globals::GlobalManager;
-use super::{ClientInner, GlobalInner, WAYLAND_DEBUG};
+use super::{ClientInner, GlobalInner, SharedLogger};
 
 pub(crate) const DISPLAY_ERROR_INVALID_OBJECT: u32 = 0;
 pub(crate) const DISPLAY_ERROR_INVALID_METHOD: u32 = 1;
@@ -26,25 +28,55 @@ pub(crate) struct DisplayInner {
     pub(crate) clients_mgr: Rc<RefCell<ClientManager>>,
     global_mgr: Rc<RefCell<GlobalManager>>,
     listeners: Vec<Token>,
+    logger: SharedLogger,
 }
 
 impl DisplayInner {
     pub(crate) fn new() -> DisplayInner {
+        let logger: SharedLogger = Arc::new(Mutex::new(None));
         if let Some(value) = std::env::var_os("WAYLAND_DEBUG") {
             // Follow libwayland-client and enable debug log only on `1` and `server` values.
             if value == "1" || value == "server" {
-                // Toggle debug log.
-                WAYLAND_DEBUG.store(true, Ordering::Relaxed);
+                *logger.lock().unwrap() = Some(Arc::new(StderrLogger));
             }
         }
 
         let global_mgr = Rc::new(RefCell::new(GlobalManager::new()));
         let epoll_mgr = Rc::new(FdManager::new().unwrap());
 
-        let clients_mgr =
-            Rc::new(RefCell::new(ClientManager::new(epoll_mgr.clone(), global_mgr.clone())));
+        let clients_mgr = Rc::new(RefCell::new(ClientManager::new(
+            epoll_mgr.clone(),
+            global_mgr.clone(),
+            logger.clone(),
+        )));
+
+        DisplayInner { epoll_mgr, clients_mgr, global_mgr, listeners: Vec::new(), logger }
+    }
+
+    pub(crate) fn set_default_max_buffer_size(&mut self, size: usize) {
+        self.clients_mgr.borrow_mut().buffer_size = size;
+    }
+
+    pub(crate) fn set_max_pending_outgoing_bytes(&mut self, max: Option<usize>) {
+        self.clients_mgr.borrow_mut().set_max_pending_bytes(max)
+    }
+
+    pub(crate) fn set_stale_id_detection(&mut self, enabled: bool) {
+        self.clients_mgr.borrow().set_stale_id_detection(enabled)
+    }
 
-        DisplayInner { epoll_mgr, clients_mgr, global_mgr, listeners: Vec::new() }
+    pub(crate) fn set_outgoing_high_water_mark<F>(&mut self, mark: usize, callback: F)
+    where
+        F: FnMut(crate::Client, usize, crate::DispatchData<'_>) + 'static,
+    {
+        self.clients_mgr.borrow_mut().set_high_water_mark(mark, Box::new(callback))
+    }
+
+    pub(crate) fn set_protocol_logger(
+        &mut self,
+        logger: Option<Arc<dyn ProtocolLogger>>,
+    ) -> Option<Arc<dyn ProtocolLogger>> {
+        std::mem::replace(&mut *self.logger.lock().unwrap(), logger)
     }
 
     pub(crate) fn create_global<I, F1, F2>(
@@ -201,7 +201,7 @@ impl GlobalManager {
 
 fn send_global_msg(reg: &(u32, ClientInner), global_id: u32, interface: CString, version: u32) {
     if let Some(ref mut clientconn) = *reg.1.data.lock().unwrap() {
-        let _ = clientconn.write_message(&Message {
+        let _ = clientconn.write_message(Message {
             sender_id: reg.0,
             opcode: 0,
             args: smallvec![
@@ -248,7 +248,7 @@ fn send_destroyed_global(
                 continue;
             }
             if let Some(ref mut clientconn) = *client.data.lock().unwrap() {
-                let _ = clientconn.write_message(&Message {
+                let _ = clientconn.write_message(Message {
                     sender_id: id,
                     opcode: 1,
                     args: smallvec![Argument::Uint(global_id)],
@@ -258,7 +258,7 @@ fn send_destroyed_global(
     } else {
         for &(id, ref client) in registries {
             if let Some(ref mut clientconn) = *client.data.lock().unwrap() {
-                let _ = clientconn.write_message(&Message {
+                let _ = clientconn.write_message(Message {
                     sender_id: id,
                     opcode: 1,
                     args: smallvec![Argument::Uint(global_id)],
@@ -1,10 +1,10 @@
 use std::cell::RefCell;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 
 use downcast_rs::Downcast;
 
-use wayland_commons::debug;
+use wayland_commons::debug::ProtocolLogger;
 use wayland_commons::map::ObjectMap;
 use wayland_commons::wire::Message;
 use wayland_commons::{MessageGroup, ThreadGuard};
@@ -24,8 +24,8 @@ pub(crate) use self::resources::ResourceInner;
 
 use self::resources::ResourceDestructor;
 
-/// Flag to toggle debug output.
-static WAYLAND_DEBUG: AtomicBool = AtomicBool::new(false);
+/// The protocol logger of a display, shared by its clients.
+pub(crate) type SharedLogger = Arc<Mutex<Option<Arc<dyn ProtocolLogger>>>>;
 
 /// A handle to the object map internal to the library state
 ///
@@ -121,15 +121,6 @@ where
     ) -> Dispatched {
         let opcode = msg.opcode as usize;
 
-        if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-            debug::print_dispatched_message(
-                resource.object.interface,
-                resource.id,
-                resource.object.requests[opcode].name,
-                &msg.args,
-            );
-        }
-
         let message = match I::Request::from_raw(msg, map) {
             Ok(msg) => msg,
             Err(_) => return Dispatched::BadMsg,
@@ -4,12 +4,12 @@ use std::sync::{Arc, Mutex};
 
 use crate::{Interface, Main, Resource};
 
-use wayland_commons::debug;
+use wayland_commons::debug::{LoggedMessage, MessageDirection};
 use wayland_commons::map::{Object, ObjectMap, ObjectMetadata};
 use wayland_commons::user_data::UserData;
 use wayland_commons::{MessageGroup, ThreadGuard};
 
-use super::{ClientInner, Dispatcher, WAYLAND_DEBUG};
+use super::{ClientInner, Dispatcher};
 
 pub(crate) type ResourceDestructor = RefCell<dyn FnMut(ResourceInner, crate::DispatchData<'_>)>;
 
@@ -50,6 +50,7 @@ impl ObjectMeta {
 #[derive(Clone)]
 pub(crate) struct ResourceInner {
     pub(crate) id: u32,
+    pub(crate) generation: u32,
     pub(crate) object: Object<ObjectMeta>,
     pub(crate) client: ClientInner,
 }
@@ -60,8 +61,8 @@ impl ResourceInner {
         map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
         client: ClientInner,
     ) -> Option<ResourceInner> {
-        let me = map.lock().unwrap().find(id);
-        me.map(|obj| ResourceInner { id, object: obj, client })
+        let me = map.lock().unwrap().find_with_generation(id);
+        me.map(|(obj, generation)| ResourceInner { id, generation, object: obj, client })
     }
 
     pub(crate) fn is_interface<I: Interface>(&self) -> bool {
@@ -75,22 +76,41 @@ impl ResourceInner {
             let destructor = msg.is_destructor();
             let msg = msg.into_raw(self.id);
 
-            if WAYLAND_DEBUG.load(Ordering::Relaxed) {
-                debug::print_send_message(
-                    I::NAME,
-                    self.id,
-                    is_alive,
-                    self.object.events[msg.opcode as usize].name,
-                    &msg.args,
-                );
+            if let Some(ref logger) = *conn_lock.logger.lock().unwrap() {
+                logger.log(&LoggedMessage {
+                    direction: MessageDirection::Sent,
+                    timestamp: std::time::SystemTime::now(),
+                    interface: I::NAME,
+                    id: self.id,
+                    alive: is_alive,
+                    message: self.object.events[msg.opcode as usize].name,
+                    args: &msg.args,
+                });
             }
 
             if !is_alive {
+                if conn_lock.stale_id_detection() {
+                    eprintln!(
+                        "[wayland-server] Event {}.{} sent to dead object {}@{} (generation {}), ignoring.",
+                        I::NAME,
+                        self.object.events[msg.opcode as usize].name,
+                        I::NAME,
+                        self.id,
+                        self.generation
+                    );
+                }
                 return;
             }
 
             // TODO: figure our if this can fail and still be recoverable ?
-            conn_lock.write_message(&msg).expect("Sending a message failed.");
+            let opcode = msg.opcode;
+            match conn_lock.write_message(msg) {
+                Ok(()) => {}
+                Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG)) => {
+                    conn_lock.message_too_large(I::NAME, self.object.events[opcode as usize].name)
+                }
+                Err(e) => conn_lock.write_failed(e),
+            }
             if destructor {
                 self.object.meta.alive.store(false, Ordering::Release);
                 // schedule a destructor
@@ -118,6 +138,12 @@ impl ResourceInner {
     }
 
     pub(crate) fn post_error(&self, error_code: u32, msg: String) {
+        if !self.is_alive() && self.client.stale_id_detection() {
+            eprintln!(
+                "[wayland-server] Protocol error posted on dead object {}@{} (generation {}).",
+                self.object.interface, self.id, self.generation
+            );
+        }
         self.client.post_error(self.id, error_code, msg)
     }
 
@@ -147,7 +173,17 @@ impl ResourceInner {
         E: From<(Main<I>, I::Request)> + 'static,
         I::Request: MessageGroup<Map = super::ResourceMap>,
     {
-        self.client.set_dispatcher_for(self.id, super::make_dispatcher(filter));
+        let dispatcher = super::make_dispatcher(filter);
+        if !self.client.set_dispatcher_for(self.id, self.generation, dispatcher)
+            && self.client.stale_id_detection()
+        {
+            eprintln!(
+                "[wayland-server] Assigning a filter to dead object {}@{} (generation {}), ignoring.",
+                I::NAME,
+                self.id,
+                self.generation
+            );
+        }
     }
 
     pub fn assign_destructor<I, E>(&self, filter: crate::Filter<E>)
@@ -155,6 +191,16 @@ impl ResourceInner {
         I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
         E: From<Resource<I>> + 'static,
     {
-        self.client.set_destructor_for(self.id, super::make_destructor(filter));
+        let destructor = super::make_destructor(filter);
+        if !self.client.set_destructor_for(self.id, self.generation, destructor)
+            && self.client.stale_id_detection()
+        {
+            eprintln!(
+                "[wayland-server] Assigning a destructor to dead object {}@{} (generation {}), ignoring.",
+                I::NAME,
+                self.id,
+                self.generation
+            );
+        }
     }
 }